@@ -0,0 +1,192 @@
+use std::collections::HashMap;
+use std::path::Path;
+
+use chrono::{Duration, NaiveDate, NaiveTime};
+
+use crate::error::Result;
+use crate::interactive::day_name;
+use crate::todoer::{Status, Todoer, Todos, DAY_FORMAT};
+use crate::todoers::Todoers;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AgendaItem {
+    pub name: String,
+    // The day or named list the todo is on
+    pub list: String,
+    pub status: Status,
+    pub due: NaiveDate,
+    pub time: Option<NaiveTime>,
+}
+
+// Open todos with a due date from every list, overdue ones and those due in the next days
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Agenda {
+    pub today: NaiveDate,
+    // By due date and time, todos without a time first
+    pub items: Vec<AgendaItem>,
+}
+
+impl Agenda {
+    // Todos carried over are on several day files, the latest one counts. They are told apart from
+    // other todos of the same name by the day they were first added
+    pub fn new<'a>(
+        todoers: impl IntoIterator<Item = &'a Todoer>,
+        today: NaiveDate,
+        days: u32,
+    ) -> Self {
+        let last = today + Duration::days(days.into());
+        let mut latest: HashMap<(String, String), AgendaItem> = HashMap::new();
+        let mut order: Vec<(String, String)> = Vec::new();
+        for todoer in todoers {
+            let list = day_name(&todoer.config);
+            let Todos(todos) = &todoer.data;
+            for index in 0..todoer.size {
+                let Some(todo) = todos.get(&index) else {
+                    continue;
+                };
+                let first_day = todo
+                    .since
+                    .map_or_else(|| list.clone(), |day| day.format(DAY_FORMAT).to_string());
+                let key = (first_day, todo.name.clone());
+                let Some(due) = todo.due() else {
+                    latest.remove(&key);
+                    continue;
+                };
+                let item = AgendaItem {
+                    name: todo.name.clone(),
+                    list: list.clone(),
+                    status: todo.status,
+                    due,
+                    time: todo.due_time(),
+                };
+                if latest.insert(key.clone(), item).is_none() {
+                    order.push(key);
+                }
+            }
+        }
+        let mut items: Vec<AgendaItem> = order
+            .into_iter()
+            .filter_map(|key| latest.remove(&key))
+            .filter(|item| !item.status.is_closed() && item.due <= last)
+            .collect();
+        items.sort_by_key(|item| (item.due, item.time));
+        Agenda { today, items }
+    }
+
+    // Every list in the todo directory, with `current` in place of its file as saved
+    pub fn load(todo_dir: &Path, current: &Todoer, today: NaiveDate, days: u32) -> Result<Self> {
+        let todoers = if std::fs::metadata(todo_dir).is_ok() {
+            Todoers::from_todos_dir(todo_dir.to_path_buf())?.todoers
+        } else {
+            Vec::new()
+        };
+        let mut todoers: Vec<&Todoer> = todoers
+            .iter()
+            .filter(|todoer| todoer.config != current.config)
+            .collect();
+        todoers.push(current);
+        todoers.sort_by(|a, b| a.config.cmp(&b.config));
+        Ok(Agenda::new(todoers, today, days))
+    }
+
+    // Overdue, Today, Tomorrow, then the day such as `Mon 2022-11-28`
+    pub fn heading(&self, due: NaiveDate) -> String {
+        match (due - self.today).num_days() {
+            ..=-1 => String::from("Overdue"),
+            0 => String::from("Today"),
+            1 => String::from("Tomorrow"),
+            _ => due.format("%a %Y-%m-%d").to_string(),
+        }
+    }
+
+    pub fn groups(&self) -> Vec<(String, Vec<&AgendaItem>)> {
+        let mut groups: Vec<(String, Vec<&AgendaItem>)> = Vec::new();
+        for item in &self.items {
+            let heading = self.heading(item.due);
+            match groups.last_mut() {
+                Some((last, items)) if *last == heading => items.push(item),
+                _ => groups.push((heading, vec![item])),
+            }
+        }
+        groups
+    }
+
+    // Overdue todos say when they were due, every todo which list it is on
+    pub fn print(&self) -> String {
+        let mut res = String::new();
+        for (heading, items) in self.groups() {
+            res += &format!("\n{}\n", heading);
+            for item in items {
+                let late = if item.due < self.today {
+                    format!(", due {}", item.due.format(DAY_FORMAT))
+                } else {
+                    String::new()
+                };
+                res += &format!("- {} ({}{})\n", item.name, item.list, late);
+            }
+        }
+        if res.is_empty() {
+            res = String::from("\nNothing due\n");
+        }
+        res
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use chrono::NaiveDate;
+
+    use super::Agenda;
+    use crate::testing::todo_dir;
+    use crate::todoer::Todoer;
+
+    #[test]
+    fn agenda() {
+        let todo_dir = todo_dir("agenda", "agenda");
+        std::fs::write(
+            todo_dir.join("2022-11-24.md"),
+            "2022-11-24\n0/3\n- [ ] pay rent due:2022-11-23\n- [ ] call bank due:2022-11-25\n- [ ] file taxes due:2022-11-24\n",
+        )
+        .unwrap();
+        // Carried over from the day before, and done since
+        std::fs::write(
+            todo_dir.join("2022-11-25.md"),
+            "2022-11-25\n1/2\n- [ ] pay rent due:2022-11-23 since:2022-11-24\n- [x] call bank due:2022-11-25 since:2022-11-24\n",
+        )
+        .unwrap();
+        std::fs::write(
+            todo_dir.join("errands.md"),
+            "errands\n0/3\n- [ ] buy paint due:2022-11-26\n- [ ] renew passport due:2023-01-10\n- [-] sell bike due:2022-11-26\n",
+        )
+        .unwrap();
+        let mut today = Todoer::from_config(todo_dir.join("2022-11-25.md"), true).unwrap();
+        today.set_value(String::from("call vendor due:2022-11-28 at:15:00"));
+        today.set_value(String::from("standup due:2022-11-28 at:09:30"));
+
+        let day = NaiveDate::from_ymd_opt(2022, 11, 25).unwrap();
+        let agenda = Agenda::load(&todo_dir, &today, day, 7).unwrap();
+        assert_eq!(
+            agenda.print(),
+            "\nOverdue\n\
+             - pay rent due:2022-11-23 (2022-11-25, due 2022-11-23)\n\
+             - file taxes due:2022-11-24 (2022-11-24, due 2022-11-24)\n\
+             \nTomorrow\n\
+             - buy paint due:2022-11-26 (errands)\n\
+             \nMon 2022-11-28\n\
+             - standup due:2022-11-28 at:09:30 (2022-11-25)\n\
+             - call vendor due:2022-11-28 at:15:00 (2022-11-25)\n"
+        );
+        assert_eq!(
+            Agenda::load(&todo_dir, &today, day, 0)
+                .unwrap()
+                .groups()
+                .len(),
+            1
+        );
+        let later = NaiveDate::from_ymd_opt(2022, 10, 1).unwrap();
+        assert_eq!(
+            Agenda::load(&todo_dir, &today, later, 7).unwrap().print(),
+            "\nNothing due\n"
+        );
+    }
+}
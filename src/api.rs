@@ -0,0 +1,415 @@
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use crate::agenda::Agenda;
+use crate::error::{Result, TodoerError};
+use crate::interactive::day_name;
+use crate::todoer::{Todo, Todoer, Todos, DAY_FORMAT, TIME_FORMAT};
+use crate::todoers::Todoers;
+
+// The JSON shapes used by the web UI and anything else talking to the server
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub struct TodoItem {
+    pub index: u32,
+    pub name: String,
+    pub done: bool,
+    // open, in-progress, blocked, deferred, done or cancelled
+    #[serde(default)]
+    pub status: String,
+    // Local times as `YYYY-MM-DDTHH:MM` and a `YYYY-MM-DD` day, as in the day file
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub created: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub completed: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub since: Option<String>,
+    // Time spent, as `HH:MM-HH:MM` on the list's day
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tracked: Vec<String>,
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub pomodoros: u32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+    // IDs of the todos this one waits on, which may be on other days
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub blocked_by: Vec<String>,
+}
+
+fn is_zero(count: &u32) -> bool {
+    *count == 0
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub struct DayList {
+    pub day: String,
+    pub done_count: u32,
+    pub size: u32,
+    pub todos: Vec<TodoItem>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub struct DaySummary {
+    pub name: String,
+    pub done_count: u32,
+    pub size: u32,
+}
+
+// An open todo with a due date, see `agenda`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub struct AgendaEntry {
+    // Overdue, Today, Tomorrow or the day such as `Mon 2022-11-28`
+    pub heading: String,
+    pub name: String,
+    // The day or named list the todo is on
+    pub list: String,
+    pub status: String,
+    // `YYYY-MM-DD` and `HH:MM`
+    pub due: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub time: Option<String>,
+}
+
+// Query of `GET /api/agenda`, a week when left out
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AgendaQuery {
+    pub days: Option<u32>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub struct AgendaList {
+    pub today: String,
+    pub days: u32,
+    pub entries: Vec<AgendaEntry>,
+}
+
+// Request body for adding or renaming a todo
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub struct TodoName {
+    pub name: String,
+}
+
+// Request body for moving todos so the first one lands at `to`, keeping their order
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub struct MoveTodos {
+    pub indices: Vec<u32>,
+    pub to: u32,
+}
+
+// Request body for setting a todo's status, one of those in `TodoItem`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub struct SetStatus {
+    pub status: String,
+}
+
+// Request body for sorting, `by` is priority, due, tag or alpha
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub struct SortTodos {
+    pub by: String,
+}
+
+// A todo by its ID, or without one by its name and when it was added, so it is still found after
+// other todos moved or went away
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub struct TodoKey {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub created: Option<String>,
+}
+
+impl From<&Todo> for TodoKey {
+    fn from(todo: &Todo) -> Self {
+        TodoKey {
+            id: todo.id.clone(),
+            name: todo.name.clone(),
+            created: todo
+                .created
+                .map(|time| time.format(TIME_FORMAT).to_string()),
+        }
+    }
+}
+
+impl TodoKey {
+    fn matches(&self, todo: &Todo) -> bool {
+        match &self.id {
+            Some(id) => todo.id.as_ref() == Some(id),
+            None => {
+                let created = TodoKey::from(todo).created;
+                self.name == todo.name && (self.created.is_none() || self.created == created)
+            }
+        }
+    }
+
+    // Where each key's todo is now, two equal keys finding two different todos
+    pub fn find_all(keys: &[TodoKey], todoer: &Todoer) -> Result<Vec<u32>> {
+        let Todos(todos) = &todoer.data;
+        let mut indices: Vec<u32> = Vec::new();
+        for key in keys {
+            let found: Vec<u32> = (0..todoer.size)
+                .filter(|index| !indices.contains(index) && key.matches(&todos[index]))
+                .collect();
+            // Without an ID or a time it was added after the list was last seen, so it is the
+            // newest todo of that name
+            let index = match (&key.id, &key.created) {
+                (None, None) => found.last(),
+                _ => found.first(),
+            };
+            let index = index.ok_or_else(|| {
+                TodoerError::Conflict(format!("{} is no longer on the list", key.name))
+            })?;
+            indices.push(*index);
+        }
+        Ok(indices)
+    }
+}
+
+// Request body for changing several todos in one go, either all of them change or none do.
+// `action` is done, remove, status or tag with `value`, or move with `to`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub struct BulkChange {
+    pub action: String,
+    pub todos: Vec<TodoKey>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub value: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub to: Option<u32>,
+}
+
+impl BulkChange {
+    // Every todo is found before anything changes, so a missing one leaves the list as it was
+    pub fn apply(&self, todoer: &mut Todoer) -> Result<()> {
+        let indices = TodoKey::find_all(&self.todos, todoer)?;
+        match (self.action.as_str(), &self.value, self.to) {
+            ("done", _, _) => todoer.mark_done_all(&indices),
+            ("remove", _, _) => todoer.remove_values(&indices),
+            ("status", Some(status), _) => todoer.set_status_all(&indices, status.parse()?),
+            ("tag", Some(tag), _) => todoer.tag_values(tag, &indices),
+            ("move", _, Some(to)) => todoer.move_values(&indices, to),
+            _ => Err(TodoerError::Config(format!(
+                "unknown change {}, expected done, remove, status or tag with a value, or move with to",
+                self.action
+            ))),
+        }
+    }
+}
+
+impl From<&Todoer> for DayList {
+    fn from(todoer: &Todoer) -> Self {
+        let Todos(todos) = &todoer.data;
+        let todos = (0..todoer.size)
+            .filter_map(|index| {
+                todos.get(&index).map(|todo| TodoItem {
+                    index,
+                    name: todo.name.clone(),
+                    done: todo.is_done(),
+                    status: todo.status.to_string(),
+                    created: todo
+                        .created
+                        .map(|time| time.format(TIME_FORMAT).to_string()),
+                    completed: todo
+                        .completed
+                        .map(|time| time.format(TIME_FORMAT).to_string()),
+                    since: todo.since.map(|day| day.format(DAY_FORMAT).to_string()),
+                    tracked: todo.tracked.iter().map(ToString::to_string).collect(),
+                    pomodoros: todo.pomodoros,
+                    id: todo.id.clone(),
+                    blocked_by: todo.blocked_by.clone(),
+                })
+            })
+            .collect();
+        DayList {
+            day: day_name(&todoer.config),
+            done_count: todoer.done_count,
+            size: todoer.size,
+            todos,
+        }
+    }
+}
+
+impl From<&Todoer> for DaySummary {
+    fn from(todoer: &Todoer) -> Self {
+        DaySummary {
+            name: day_name(&todoer.config),
+            done_count: todoer.done_count,
+            size: todoer.size,
+        }
+    }
+}
+
+impl AgendaList {
+    pub fn new(agenda: &Agenda, days: u32) -> Self {
+        let entries = agenda
+            .items
+            .iter()
+            .map(|item| AgendaEntry {
+                heading: agenda.heading(item.due),
+                name: item.name.clone(),
+                list: item.list.clone(),
+                status: item.status.to_string(),
+                due: item.due.format(DAY_FORMAT).to_string(),
+                time: item.time.map(|time| time.format("%H:%M").to_string()),
+            })
+            .collect();
+        AgendaList {
+            today: agenda.today.format(DAY_FORMAT).to_string(),
+            days,
+            entries,
+        }
+    }
+}
+
+// The file of another day next to today's, refusing anything that would leave the directory
+pub fn day_config(today: &Path, day: &str) -> Result<PathBuf> {
+    if day.is_empty() || day.starts_with('.') || day.contains(['/', '\\']) {
+        return Err(TodoerError::Config(format!("invalid day {}", day)));
+    }
+    let config = today.with_file_name(format!("{}.md", day));
+    if config != today && std::fs::metadata(&config).is_err() {
+        return Err(TodoerError::Config(format!("day {} does not exist", day)));
+    }
+    Ok(config)
+}
+
+// Every day next to today's newest first, with today's counts taken from `today` as it may not be saved yet
+pub fn list_days(today: &Todoer) -> Result<Vec<DaySummary>> {
+    let todo_dir = today.config.parent().map(PathBuf::from).unwrap_or_default();
+    let mut days: Vec<DaySummary> = if std::fs::metadata(&todo_dir).is_ok() {
+        Todoers::from_todos_dir(todo_dir)?
+            .todoers
+            .iter()
+            .filter(|todoer| todoer.config != today.config)
+            .map(DaySummary::from)
+            .collect()
+    } else {
+        Vec::new()
+    };
+    days.push(DaySummary::from(today));
+    days.sort_by(|a, b| b.name.cmp(&a.name));
+    Ok(days)
+}
+
+#[cfg(test)]
+mod test {
+    use std::path::Path;
+
+    use super::{day_config, list_days, BulkChange, DayList, TodoItem, TodoKey};
+    use crate::error::TodoerError;
+    use crate::testing::todo_dir;
+    use crate::todoer::Todoer;
+
+    #[test]
+    fn day_list() {
+        let todoer = Todoer::from_contents(
+            Path::new("wiki/todo"),
+            "2022-11-25\n1/2\n- [ ] foo\n- [x] bar since:2022-11-24 done:2022-11-25T10:05\n",
+        )
+        .unwrap();
+
+        let list = DayList::from(&todoer);
+        assert_eq!(list.day, "2022-11-25");
+        assert_eq!(list.done_count, 1);
+        assert_eq!(
+            list.todos,
+            vec![
+                TodoItem {
+                    index: 0,
+                    name: String::from("foo"),
+                    done: false,
+                    status: String::from("open"),
+                    created: None,
+                    completed: None,
+                    since: None,
+                    tracked: Vec::new(),
+                    pomodoros: 0,
+                    id: None,
+                    blocked_by: Vec::new(),
+                },
+                TodoItem {
+                    index: 1,
+                    name: String::from("bar"),
+                    done: true,
+                    status: String::from("done"),
+                    created: None,
+                    completed: Some(String::from("2022-11-25T10:05")),
+                    since: Some(String::from("2022-11-24")),
+                    tracked: Vec::new(),
+                    pomodoros: 0,
+                    id: None,
+                    blocked_by: Vec::new(),
+                },
+            ]
+        );
+        assert_eq!(
+            serde_json::to_string(&list.todos[0]).unwrap(),
+            r#"{"index":0,"name":"foo","done":false,"status":"open"}"#
+        );
+    }
+
+    #[test]
+    fn days() {
+        let today = todo_dir("api", "days").join("2022-11-25.md");
+        std::fs::write(
+            today.with_file_name("2022-11-24.md"),
+            "2022-11-24\n1/1\n- [x] foo\n",
+        )
+        .unwrap();
+        let todoer = Todoer::default_todoer(today.clone());
+
+        let days = list_days(&todoer).unwrap();
+        assert_eq!(days.len(), 2);
+        assert_eq!(days[0].name, "2022-11-25");
+        assert_eq!(days[1].done_count, 1);
+
+        assert!(day_config(&today, "2022-11-24").is_ok());
+        assert!(day_config(&today, "2022-11-23").is_err());
+        assert!(day_config(&today, "../secrets").is_err());
+    }
+
+    #[test]
+    fn bulk_change() {
+        let mut todoer = Todoer::from_contents(
+            Path::new("wiki/todo"),
+            "2022-11-25\n0/4\n- [ ] foo created:2022-11-25T09:00\n- [ ] foo created:2022-11-25T09:00\n- [ ] bar id:k3xq9a\n- [ ] baz\n",
+        )
+        .unwrap();
+        let key = |index: u32| TodoKey::from(&todoer.data.0[&index]);
+        let mut change = BulkChange {
+            action: String::from("remove"),
+            todos: vec![key(2), key(0), key(0)],
+            value: None,
+            to: None,
+        };
+
+        // Keys still find their todos once the others moved
+        todoer.move_values(&[3], 0).unwrap();
+        assert_eq!(
+            TodoKey::find_all(&change.todos, &todoer).unwrap(),
+            vec![3, 1, 2]
+        );
+
+        // Nothing changes when one of the todos is gone
+        change.todos.push(TodoKey {
+            id: None,
+            name: String::from("qux"),
+            created: None,
+        });
+        let err = change.apply(&mut todoer).unwrap_err();
+        assert!(matches!(err, TodoerError::Conflict(_)));
+        assert_eq!(todoer.size, 4);
+
+        change.todos.pop();
+        change.apply(&mut todoer).unwrap();
+        assert_eq!(todoer.get_value_names(), vec!["baz"]);
+
+        change.action = String::from("status");
+        change.todos.clear();
+        assert!(matches!(
+            change.apply(&mut todoer),
+            Err(TodoerError::Config(_))
+        ));
+    }
+}
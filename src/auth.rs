@@ -0,0 +1,198 @@
+use std::path::{Path, PathBuf};
+
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::error::{Result, TodoerError};
+
+// Only the salted hash of a token is kept, the token itself is shown once when the user is added
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct User {
+    pub name: String,
+    pub salt: String,
+    pub hash: String,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct Users {
+    pub users: Vec<User>,
+}
+
+pub fn users_file(root: &Path) -> PathBuf {
+    root.join("users.json")
+}
+
+// Each user gets their own todo directory under the server's root
+pub fn user_root(root: &Path, name: &str) -> PathBuf {
+    root.join("users").join(name)
+}
+
+fn random_hex(len: usize) -> String {
+    let mut bytes = vec![0; len];
+    rand::thread_rng().fill_bytes(&mut bytes);
+    to_hex(&bytes)
+}
+
+pub fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+fn hash_secret(salt: &str, secret: &str) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(salt.as_bytes());
+    hasher.update(secret.as_bytes());
+    to_hex(&hasher.finalize())
+}
+
+// Compares without stopping at the first difference, so timing doesn't leak the hash
+fn constant_time_eq(a: &str, b: &str) -> bool {
+    a.len() == b.len()
+        && a.bytes()
+            .zip(b.bytes())
+            .fold(0, |acc, (a, b)| acc | (a ^ b))
+            == 0
+}
+
+fn check_name(name: &str) -> Result<()> {
+    let valid = !name.is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+    if !valid {
+        return Err(TodoerError::Config(format!(
+            "invalid user name {}, use letters, digits, - and _",
+            name
+        )));
+    }
+    Ok(())
+}
+
+// Reads `Authorization: Bearer <name>:<token>` or basic auth with the token as password
+pub fn parse_credentials(authorization: &str) -> Option<(String, String)> {
+    let (scheme, value) = authorization.trim().split_once(' ')?;
+    let credentials = if scheme.eq_ignore_ascii_case("bearer") {
+        value.trim().to_string()
+    } else if scheme.eq_ignore_ascii_case("basic") {
+        String::from_utf8(base64::decode(value.trim()).ok()?).ok()?
+    } else {
+        return None;
+    };
+    let (name, secret) = credentials.split_once(':')?;
+    Some((name.to_string(), secret.to_string()))
+}
+
+impl Users {
+    // A missing file means no users have been added yet
+    pub fn load(path: &Path) -> Result<Self> {
+        if std::fs::metadata(path).is_err() {
+            return Ok(Users::default());
+        }
+        let contents = std::fs::read_to_string(path)?;
+        serde_json::from_str(&contents).map_err(|err| TodoerError::Parse {
+            line: err.line(),
+            message: err.to_string(),
+        })
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(path, serde_json::to_string_pretty(self).unwrap() + "\n")?;
+        Ok(())
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.users.is_empty()
+    }
+
+    pub fn names(&self) -> Vec<&str> {
+        self.users.iter().map(|user| user.name.as_str()).collect()
+    }
+
+    // Returns the new user's token, in the `<name>:<secret>` form clients send
+    pub fn add(&mut self, name: &str) -> Result<String> {
+        check_name(name)?;
+        if self.users.iter().any(|user| user.name == name) {
+            return Err(TodoerError::Conflict(format!(
+                "user {} already exists",
+                name
+            )));
+        }
+        let secret = random_hex(24);
+        let salt = random_hex(16);
+        self.users.push(User {
+            name: name.to_string(),
+            hash: hash_secret(&salt, &secret),
+            salt,
+        });
+        Ok(format!("{}:{}", name, secret))
+    }
+
+    pub fn remove(&mut self, name: &str) -> Result<()> {
+        let before = self.users.len();
+        self.users.retain(|user| user.name != name);
+        if self.users.len() == before {
+            return Err(TodoerError::Config(format!("no user named {}", name)));
+        }
+        Ok(())
+    }
+
+    pub fn authenticate(&self, name: &str, secret: &str) -> Result<&User> {
+        self.users
+            .iter()
+            .find(|user| user.name == name)
+            .filter(|user| constant_time_eq(&user.hash, &hash_secret(&user.salt, secret)))
+            .ok_or_else(|| TodoerError::Unauthorized(String::from("invalid user or token")))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::path::Path;
+
+    use super::{parse_credentials, user_root, Users};
+
+    #[test]
+    fn add_and_authenticate() {
+        let mut users = Users::default();
+        let token = users.add("alice").unwrap();
+        assert!(users.add("alice").is_err());
+        assert!(users.add("../bob").is_err());
+
+        let (name, secret) = token.split_once(':').unwrap();
+        assert_eq!(name, "alice");
+        assert!(!users.users[0].hash.contains(secret));
+        assert_eq!(users.authenticate("alice", secret).unwrap().name, "alice");
+        assert!(users.authenticate("alice", "wrong").is_err());
+        assert!(users.authenticate("bob", secret).is_err());
+
+        users.remove("alice").unwrap();
+        assert!(users.is_empty());
+        assert!(users.remove("alice").is_err());
+    }
+
+    #[test]
+    fn credentials() {
+        assert_eq!(
+            parse_credentials("Bearer alice:s3cret"),
+            Some((String::from("alice"), String::from("s3cret")))
+        );
+        // alice:s3cret
+        assert_eq!(
+            parse_credentials("Basic YWxpY2U6czNjcmV0"),
+            Some((String::from("alice"), String::from("s3cret")))
+        );
+        assert_eq!(parse_credentials("Bearer s3cret"), None);
+        assert_eq!(parse_credentials("Digest foo"), None);
+    }
+
+    #[test]
+    fn roots() {
+        assert_eq!(
+            user_root(Path::new("/srv"), "alice"),
+            Path::new("/srv/users/alice")
+        );
+    }
+}
@@ -0,0 +1,58 @@
+use std::path::Path;
+
+use clap::{Parser, Subcommand};
+use rust::auth::{users_file, Users};
+use rust::error::TodoerError;
+use rust::server;
+use rust::settings::{Settings, SettingsOpts};
+
+#[derive(Parser)]
+struct ServerOpts {
+    #[clap(flatten)]
+    settings: SettingsOpts,
+    #[clap(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    // Manages who can use the server, stored under the server's root
+    #[clap(subcommand)]
+    Users(UsersCommand),
+}
+
+#[derive(Subcommand)]
+enum UsersCommand {
+    // Adds a user and prints their token, which is not stored and can't be shown again
+    Add { name: String },
+    Remove { name: String },
+    List,
+}
+
+fn users(root: &Path, command: UsersCommand) -> Result<(), TodoerError> {
+    let path = users_file(root);
+    let mut users = Users::load(&path)?;
+    match command {
+        UsersCommand::Add { name } => {
+            let token = users.add(&name)?;
+            users.save(&path)?;
+            println!("{}", token);
+        }
+        UsersCommand::Remove { name } => {
+            users.remove(&name)?;
+            users.save(&path)?;
+        }
+        UsersCommand::List => users.names().iter().for_each(|name| println!("{}", name)),
+    }
+    Ok(())
+}
+
+#[actix_web::main]
+async fn main() -> std::io::Result<()> {
+    let opts = ServerOpts::parse();
+    let settings = Settings::try_from(opts.settings).map_err(std::io::Error::other)?;
+    if let Some(Command::Users(command)) = opts.command {
+        return users(&settings.root, command).map_err(std::io::Error::other);
+    }
+    server::run(settings).await
+}
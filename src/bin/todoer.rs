@@ -1,42 +1,180 @@
 use clap::Parser;
 use rust::{
+    agenda::Agenda,
+    calendar::Calendar,
     config::{Config, Operation},
+    dependencies::{self, Dependencies},
+    ics,
+    interactive::day_name,
     opts::Opts,
-    todoer::Todoer,
+    quick_add,
+    remote::{queue_file, Outcome, Remote},
+    sync,
+    timer::{self, timer_file, Timer},
+    todoer::{day_of, format_duration, Todoer},
     todoers::Todoers,
 };
 
 use anyhow::Result;
+use chrono::{Local, Utc};
 
 fn main() -> Result<()> {
     let config: Config = Opts::parse().try_into()?;
-    println!("{:?}", config);
-    let mut proj = Todoer::from_config(config.config.clone(), false);
+    if let Some(settings) = config.remote {
+        let todo_dir = config.config.parent().unwrap().to_path_buf();
+        let remote = Remote::new(settings, queue_file(&todo_dir));
+        for (operation, err) in remote.flush()? {
+            eprintln!("dropped queued {:?}: {}", operation, err);
+        }
+        match remote.run(config.operation)? {
+            Outcome::Print(value) => println!("{}", value),
+            Outcome::Done => {}
+            Outcome::Queued => eprintln!("server unreachable, queued until it can be reached"),
+        }
+        return Ok(());
+    }
+
+    let mut proj = Todoer::from_config(config.config.clone(), false)?;
+    let todo_dir = config.config.parent().unwrap();
 
     match config.operation {
         Operation::Print() => {
-            let value = proj.print_values();
+            let value = proj.print_values_with(&Dependencies::load(todo_dir, &proj)?);
 
             println!("{}", value);
+            if let Some(timer) = Timer::load(&timer_file(todo_dir))? {
+                println!(
+                    "Tracking {} for {}",
+                    timer.name,
+                    format_duration(timer.elapsed())
+                );
+            }
+        }
+        Operation::Stats() => {
+            println!("{}", proj.stats());
         }
         Operation::PrintAll() => {
-            let projs =
-                Todoers::from_todos_dir(config.config.clone().parent().unwrap().to_path_buf())
-                    .unwrap();
+            let projs = Todoers::from_todos_dir(todo_dir.to_path_buf())?;
 
             println!("{}", projs.print_all_todos_together());
         }
         Operation::Add(v) => {
-            proj.set_value(v);
-            proj.save()?;
+            proj.set_value(quick_add::parse(&v, Local::now().date_naive()).to_string());
+            proj.save_and_commit()?;
+        }
+        Operation::Export(days) => {
+            let mut todoers: Vec<Todoer> = Todoers::from_todos_dir(todo_dir.to_path_buf())?
+                .todoers
+                .into_iter()
+                .filter(|todoer| todoer.config != proj.config)
+                .collect();
+            todoers.push(proj);
+            todoers.sort_by(|a, b| a.config.cmp(&b.config));
+            if let Some((from, to)) = days {
+                todoers.retain(|todoer| {
+                    day_of(&todoer.config).is_some_and(|day| from <= day && day <= to)
+                });
+            }
+            print!("{}", ics::export(&todoers, Utc::now()));
+        }
+        Operation::Import(path) => {
+            let contents = std::fs::read_to_string(&path)?;
+            let mut dependencies = Dependencies::load(todo_dir, &proj)?;
+            let (added, skipped) = ics::import(&mut proj, &contents, &mut dependencies)?;
+            proj.save_and_commit()?;
+            println!("Imported {} todos, {} were already there", added, skipped);
+        }
+        Operation::Calendar(month) => {
+            let todoers = Todoers::from_todos_dir(todo_dir.to_path_buf())?.todoers;
+            let days: Vec<(String, u32, u32)> = todoers
+                .iter()
+                .filter(|todoer| todoer.config != proj.config)
+                .chain([&proj])
+                .map(|todoer| (day_name(&todoer.config), todoer.done_count, todoer.size))
+                .collect();
+            let days = days
+                .iter()
+                .map(|(name, done, size)| (name.as_str(), *done, *size));
+            println!("{}", Calendar::new(month, days).print());
+        }
+        Operation::Agenda(days) => {
+            let agenda = Agenda::load(todo_dir, &proj, Local::now().date_naive(), days)?;
+            println!("{}", agenda.print());
+        }
+        Operation::PreviewAdd(v) => {
+            print!(
+                "{}",
+                quick_add::parse(&v, Local::now().date_naive()).describe()
+            );
+        }
+        Operation::Complete(selection) => {
+            let indices = proj.select(&selection)?;
+            proj.mark_done_all(&indices)?;
+            proj.save_and_commit()?;
+        }
+        Operation::Remove(selection) => {
+            let indices = proj.select(&selection)?;
+            proj.remove_values(&indices)?;
+            proj.save_and_commit()?;
+        }
+        Operation::MoveUp(i) => {
+            proj.move_up(i)?;
+            proj.save_and_commit()?;
+        }
+        Operation::MoveDown(i) => {
+            proj.move_down(i)?;
+            proj.save_and_commit()?;
+        }
+        Operation::MoveTo(selection, to) => {
+            let indices = proj.select(&selection)?;
+            proj.move_values(&indices, to)?;
+            proj.save_and_commit()?;
+        }
+        Operation::Sort(by) => {
+            proj.sort_values(by)?;
+            proj.save_and_commit()?;
+        }
+        Operation::Tag(tag, selection) => {
+            let indices = proj.select(&selection)?;
+            proj.tag_values(&tag, &indices)?;
+            proj.save_and_commit()?;
+        }
+        Operation::SetStatus(status, selection) => {
+            let indices = proj.select(&selection)?;
+            proj.set_status_all(&indices, status)?;
+            proj.save_and_commit()?;
+        }
+        Operation::Block(selection, blocker) => {
+            let indices = proj.select(&selection)?;
+            let mut dependencies = Dependencies::load(todo_dir, &proj)?;
+            dependencies::block(&mut proj, &indices, &blocker, &mut dependencies)?;
+            proj.save_and_commit()?;
+        }
+        Operation::Unblock(selection, blocker) => {
+            let indices = proj.select(&selection)?;
+            dependencies::unblock(&mut proj, &indices, blocker.as_deref())?;
+            proj.save_and_commit()?;
+        }
+        Operation::Next() => {
+            let dependencies = Dependencies::load(todo_dir, &proj)?;
+            print!("{}", dependencies::next(&proj, &dependencies)?);
+        }
+        Operation::Sync() => {
+            println!("{}", sync::sync(todo_dir)?);
         }
-        Operation::Complete(i) => {
-            proj.mark_done(i);
-            proj.save()?;
+        Operation::Start(index) => {
+            if let Some((name, took)) = timer::start(&proj, index, &timer_file(todo_dir))? {
+                println!("Stopped {} after {}", name, format_duration(took));
+            }
+            println!("Started {}", proj.data.0[&index].name);
         }
-        Operation::Remove(i) => {
-            proj.remove_value(i);
-            proj.save()?;
+        Operation::Stop() => match timer::stop(&timer_file(todo_dir))? {
+            Some((name, took)) => println!("Stopped {} after {}", name, format_duration(took)),
+            None => println!("No timer is running"),
+        },
+        Operation::Timesheet(from, to) => {
+            let projs = Todoers::from_todos_dir(todo_dir.to_path_buf())?;
+            print!("{}", timer::timesheet(&projs, from, to));
         }
     }
 
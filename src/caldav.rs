@@ -0,0 +1,505 @@
+use std::sync::Arc;
+
+use actix_web::http::header::{self, HeaderMap};
+use actix_web::http::{Method, StatusCode};
+use actix_web::{web, HttpRequest, HttpResponse};
+use chrono::Utc;
+use sha2::{Digest, Sha256};
+
+use crate::api::{day_config, list_days, DayList};
+use crate::auth::to_hex;
+use crate::dependencies::stable_id;
+use crate::error::{Result, TodoerError};
+use crate::events::Event;
+use crate::ics;
+use crate::server::List;
+use crate::todoer::{Todo, Todoer};
+
+// A minimal CalDAV server (RFC 4791) over the same lists as the API, so task apps can subscribe to
+// them. Every day and named list is a calendar of VTODOs at `/caldav/{list}/` and every todo in it
+// is `{id}.ics`. Todos without an ID go by their `stable_id`, as on export, so reading changes nothing
+
+const ROOT: &str = "/caldav/";
+const XML: &str = "application/xml; charset=utf-8";
+const CALENDAR: &str = "text/calendar; charset=utf-8; component=vtodo";
+
+fn propfind() -> Method {
+    Method::from_bytes(b"PROPFIND").expect("PROPFIND is a valid method")
+}
+
+fn report() -> Method {
+    Method::from_bytes(b"REPORT").expect("REPORT is a valid method")
+}
+
+pub fn routes(cfg: &mut web::ServiceConfig) {
+    cfg.service(
+        web::resource(["/caldav", "/caldav/"])
+            .route(web::method(Method::OPTIONS).to(options))
+            .route(web::method(propfind()).to(propfind_home)),
+    )
+    .service(
+        web::resource(["/caldav/{list}", "/caldav/{list}/"])
+            .route(web::method(Method::OPTIONS).to(options))
+            .route(web::method(propfind()).to(propfind_list))
+            .route(web::method(report()).to(report_list)),
+    )
+    .service(
+        web::resource("/caldav/{list}/{item}")
+            .route(web::method(Method::OPTIONS).to(options))
+            .route(web::get().to(get_item))
+            .route(web::put().to(put_item))
+            .route(web::delete().to(delete_item)),
+    );
+}
+
+// Where clients look for the server when given only the host, outside of auth
+pub async fn well_known() -> HttpResponse {
+    HttpResponse::MovedPermanently()
+        .insert_header((header::LOCATION, ROOT))
+        .finish()
+}
+
+async fn options() -> HttpResponse {
+    HttpResponse::Ok()
+        .insert_header(("DAV", "1, calendar-access"))
+        .insert_header((header::ALLOW, "OPTIONS, GET, PUT, DELETE, PROPFIND, REPORT"))
+        .finish()
+}
+
+// Today's list goes through `List` so other clients hear about changes, other lists are read from disk
+fn view<T>(list: &List, name: &str, f: impl FnOnce(&Todoer) -> Result<T>) -> Result<T> {
+    list.view(|today| {
+        let config = day_config(&today.config, name)?;
+        if config == today.config {
+            return f(today);
+        }
+        f(&Todoer::from_config(config, true)?)
+    })
+}
+
+fn hash(value: &str) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(value.as_bytes());
+    to_hex(&hasher.finalize())[..16].to_string()
+}
+
+// Changes with anything about the todo, including what the VTODO leaves out
+fn etag(todo: &Todo) -> String {
+    format!("\"{}\"", hash(&todo.line()))
+}
+
+// Changes with anything on the list, so clients know when to look at the todos again
+fn ctag(todoer: &Todoer) -> Result<String> {
+    Ok(hash(&String::try_from(todoer)?))
+}
+
+fn find(todoer: &Todoer, id: &str) -> Option<u32> {
+    todoer
+        .data
+        .0
+        .iter()
+        .find(|(_, todo)| stable_id(todoer, todo) == id)
+        .map(|(index, _)| *index)
+}
+
+// Every todo on the list with the ID it goes by
+fn items(list: &List, name: &str) -> Result<Vec<(String, Todo)>> {
+    view(list, name, |todoer| {
+        Ok((0..todoer.size)
+            .filter_map(|index| todoer.data.0.get(&index))
+            .map(|todo| (stable_id(todoer, todo), todo.clone()))
+            .collect())
+    })
+}
+
+fn item_id(item: &str) -> Result<String> {
+    item.strip_suffix(".ics")
+        .filter(|id| !id.is_empty())
+        .map(String::from)
+        .ok_or_else(|| TodoerError::Config(format!("expected a .ics file but got {}", item)))
+}
+
+// Anything but unreserved characters is percent-encoded, so any list name or UID makes a path
+fn encode(segment: &str) -> String {
+    segment
+        .bytes()
+        .map(|byte| match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' | b'@' => {
+                char::from(byte).to_string()
+            }
+            _ => format!("%{:02X}", byte),
+        })
+        .collect()
+}
+
+fn decode(segment: &str) -> String {
+    let bytes = segment.as_bytes();
+    let mut res = Vec::new();
+    let mut i = 0;
+    while i < bytes.len() {
+        let hex = segment
+            .get(i + 1..i + 3)
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match (bytes[i], hex) {
+            (b'%', Some(byte)) => {
+                res.push(byte);
+                i += 3;
+            }
+            (byte, _) => {
+                res.push(byte);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&res).into_owned()
+}
+
+fn list_href(name: &str) -> String {
+    format!("{}{}/", ROOT, encode(name))
+}
+
+fn item_href(name: &str, id: &str) -> String {
+    format!("{}{}.ics", list_href(name), encode(id))
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+// Start tags and the text right after them of every element called `local` in any namespace. A
+// scan rather than an XML parser, which is enough for the few bodies clients send
+fn elements<'a>(body: &'a str, local: &str) -> Vec<(&'a str, &'a str)> {
+    let mut found = Vec::new();
+    let mut rest = body;
+    while let Some(start) = rest.find('<') {
+        rest = &rest[start + 1..];
+        let Some(end) = rest.find('>') else {
+            break;
+        };
+        let tag = &rest[..end];
+        rest = &rest[end + 1..];
+        // Closing tags come out empty
+        let name = tag
+            .split(|c: char| c.is_whitespace() || c == '/')
+            .next()
+            .unwrap_or_default();
+        if name.rsplit(':').next() == Some(local) {
+            let text = &rest[..rest.find('<').unwrap_or(rest.len())];
+            found.push((tag, text.trim()));
+        }
+    }
+    found
+}
+
+fn attribute<'a>(tag: &'a str, name: &str) -> Option<&'a str> {
+    let (before, after) = tag.split_once(&format!("{}=", name))?;
+    if !before.ends_with(char::is_whitespace) {
+        return None;
+    }
+    let quote = after.chars().next().filter(|c| *c == '"' || *c == '\'')?;
+    after[1..].split(quote).next()
+}
+
+// Depth 0 is the resource alone, 1 and infinity also what it holds
+fn with_members(req: &HttpRequest) -> bool {
+    req.headers()
+        .get("Depth")
+        .and_then(|value| value.to_str().ok())
+        .is_none_or(|depth| depth.trim() != "0")
+}
+
+// Every property is given whichever were asked for, clients take the ones they know
+fn response(href: &str, props: &str) -> String {
+    format!(
+        "<d:response><d:href>{}</d:href><d:propstat><d:prop>{}</d:prop>\
+         <d:status>HTTP/1.1 200 OK</d:status></d:propstat></d:response>",
+        escape(href),
+        props
+    )
+}
+
+fn missing(href: &str) -> String {
+    format!(
+        "<d:response><d:href>{}</d:href><d:status>HTTP/1.1 404 Not Found</d:status></d:response>",
+        escape(href)
+    )
+}
+
+fn multistatus(responses: &[String]) -> HttpResponse {
+    HttpResponse::build(StatusCode::MULTI_STATUS)
+        .content_type(XML)
+        .body(format!(
+            "<?xml version=\"1.0\" encoding=\"utf-8\"?>\
+             <d:multistatus xmlns:d=\"DAV:\" xmlns:c=\"urn:ietf:params:xml:ns:caldav\" \
+             xmlns:cs=\"http://calendarserver.org/ns/\">{}</d:multistatus>",
+            responses.concat()
+        ))
+}
+
+// The root is the user's principal and calendar home in one
+fn home_props() -> String {
+    format!(
+        "<d:resourcetype><d:collection/></d:resourcetype><d:displayname>todoer</d:displayname>\
+         <d:current-user-principal><d:href>{0}</d:href></d:current-user-principal>\
+         <c:calendar-home-set><d:href>{0}</d:href></c:calendar-home-set>",
+        ROOT
+    )
+}
+
+fn list_props(name: &str, ctag: &str) -> String {
+    format!(
+        "<d:resourcetype><d:collection/><c:calendar/></d:resourcetype>\
+         <d:displayname>{}</d:displayname>\
+         <c:supported-calendar-component-set><c:comp name=\"VTODO\"/></c:supported-calendar-component-set>\
+         <d:current-user-privilege-set><d:privilege><d:read/></d:privilege>\
+         <d:privilege><d:write/></d:privilege></d:current-user-privilege-set>\
+         <cs:getctag>{}</cs:getctag>",
+        escape(name),
+        ctag
+    )
+}
+
+fn item_props(todo: &Todo, data: Option<&str>) -> String {
+    let data = data.map_or_else(String::new, |data| {
+        format!("<c:calendar-data>{}</c:calendar-data>", escape(data))
+    });
+    format!(
+        "<d:resourcetype/><d:getcontenttype>{}</d:getcontenttype><d:getetag>{}</d:getetag>{}",
+        CALENDAR,
+        escape(&etag(todo)),
+        data
+    )
+}
+
+async fn propfind_home(req: HttpRequest, list: web::ReqData<Arc<List>>) -> Result<HttpResponse> {
+    let mut responses = vec![response(ROOT, &home_props())];
+    if with_members(&req) {
+        for day in list.view(list_days)? {
+            let ctag = view(&list, &day.name, ctag)?;
+            responses.push(response(
+                &list_href(&day.name),
+                &list_props(&day.name, &ctag),
+            ));
+        }
+    }
+    Ok(multistatus(&responses))
+}
+
+async fn propfind_list(
+    req: HttpRequest,
+    list: web::ReqData<Arc<List>>,
+    name: web::Path<String>,
+) -> Result<HttpResponse> {
+    let items = if with_members(&req) {
+        items(&list, &name)?
+    } else {
+        Vec::new()
+    };
+    let ctag = view(&list, &name, ctag)?;
+    let mut responses = vec![response(&list_href(&name), &list_props(&name, &ctag))];
+    for (id, todo) in &items {
+        responses.push(response(&item_href(&name, id), &item_props(todo, None)));
+    }
+    Ok(multistatus(&responses))
+}
+
+// calendar-query gives every todo, unless it asks for other components, leaving finer filters to
+// the client. calendar-multiget gives the todos at the hrefs asked for
+async fn report_list(
+    list: web::ReqData<Arc<List>>,
+    name: web::Path<String>,
+    body: String,
+) -> Result<HttpResponse> {
+    let wanted: Option<Vec<String>> = if !elements(&body, "calendar-multiget").is_empty() {
+        let ids = elements(&body, "href")
+            .into_iter()
+            .map(|(_, href)| {
+                let file = href
+                    .trim_end_matches('/')
+                    .rsplit('/')
+                    .next()
+                    .unwrap_or(href);
+                decode(file.strip_suffix(".ics").unwrap_or(file))
+            })
+            .collect();
+        Some(ids)
+    } else if !elements(&body, "calendar-query").is_empty() {
+        let others = elements(&body, "comp-filter")
+            .into_iter()
+            .filter_map(|(tag, _)| attribute(tag, "name"))
+            .any(|component| !matches!(component, "VCALENDAR" | "VTODO"));
+        if others {
+            return Ok(multistatus(&[]));
+        }
+        None
+    } else {
+        return Err(TodoerError::Config(String::from(
+            "only calendar-query and calendar-multiget reports are supported",
+        )));
+    };
+
+    let items = items(&list, &name)?;
+    let stamp = Utc::now();
+    let found = |id: &str| {
+        items.iter().find(|(item, _)| item == id).map(|(_, todo)| {
+            let data = ics::todo_calendar(todo, id, stamp);
+            response(&item_href(&name, id), &item_props(todo, Some(&data)))
+        })
+    };
+    let responses: Vec<String> = match wanted {
+        Some(ids) => ids
+            .iter()
+            .map(|id| found(id).unwrap_or_else(|| missing(&item_href(&name, id))))
+            .collect(),
+        None => items.iter().filter_map(|(id, _)| found(id)).collect(),
+    };
+    Ok(multistatus(&responses))
+}
+
+fn no_todo(id: &str) -> HttpResponse {
+    HttpResponse::NotFound().body(format!("no todo has the ID {}", id))
+}
+
+async fn get_item(
+    list: web::ReqData<Arc<List>>,
+    path: web::Path<(String, String)>,
+) -> Result<HttpResponse> {
+    let (name, item) = path.into_inner();
+    let id = item_id(&item)?;
+    let todo = view(&list, &name, |todoer| {
+        Ok(find(todoer, &id).map(|index| todoer.data.0[&index].clone()))
+    })?;
+    let Some(todo) = todo else {
+        return Ok(no_todo(&id));
+    };
+    Ok(HttpResponse::Ok()
+        .content_type(CALENDAR)
+        .insert_header((header::ETAG, etag(&todo)))
+        .body(ics::todo_calendar(&todo, &id, Utc::now())))
+}
+
+// `If-Match` and `If-None-Match`, which clients send so they don't overwrite changes they haven't
+// seen. A failed one is a conflict, which the handlers answer with 412
+fn check_preconditions(headers: &HeaderMap, todo: Option<&Todo>) -> Result<()> {
+    let current = todo.map(etag);
+    let matches = |value: &str| {
+        value
+            .split(',')
+            .map(str::trim)
+            .any(|tag| (tag == "*" && current.is_some()) || Some(tag) == current.as_deref())
+    };
+    let header = |name| headers.get(name).and_then(|value| value.to_str().ok());
+    if header(header::IF_MATCH).is_some_and(|value| !matches(value)) {
+        return Err(TodoerError::Conflict(String::from(
+            "the todo has changed since it was read",
+        )));
+    }
+    if header(header::IF_NONE_MATCH).is_some_and(matches) {
+        return Err(TodoerError::Conflict(String::from(
+            "the todo already exists",
+        )));
+    }
+    Ok(())
+}
+
+fn precondition_failed<T>(result: Result<T>) -> Result<Option<HttpResponse>> {
+    match result {
+        Ok(_) => Ok(None),
+        Err(TodoerError::Conflict(message)) => {
+            Ok(Some(HttpResponse::PreconditionFailed().body(message)))
+        }
+        Err(err) => Err(err),
+    }
+}
+
+// Adds the todo or writes over the one with its ID. The UID has to match the file name, as the
+// todo is found by it afterwards
+async fn put_item(
+    req: HttpRequest,
+    list: web::ReqData<Arc<List>>,
+    path: web::Path<(String, String)>,
+    body: String,
+) -> Result<HttpResponse> {
+    let (name, item) = path.into_inner();
+    let id = item_id(&item)?;
+    let vtodo = ics::parse(&body)?
+        .into_iter()
+        .next()
+        .ok_or_else(|| TodoerError::Config(String::from("expected a VTODO")))?;
+    if vtodo.is_empty() {
+        return Err(TodoerError::Config(String::from(
+            "the VTODO has no summary",
+        )));
+    }
+    if vtodo.id().is_some_and(|uid| uid != id) {
+        return Err(TodoerError::Config(format!(
+            "the UID of the VTODO doesn't match {}",
+            item
+        )));
+    }
+    let mut created = false;
+    let result = list.update_day(&name, |todoer| {
+        let index = find(todoer, &id);
+        check_preconditions(req.headers(), index.map(|index| &todoer.data.0[&index]))?;
+        let index = match index {
+            Some(index) => {
+                vtodo.apply(todoer, index)?;
+                index
+            }
+            None => {
+                created = true;
+                vtodo.add_to(todoer)?
+            }
+        };
+        // Sent without a UID, the todo still goes by the file name
+        let todo = todoer
+            .data
+            .0
+            .get_mut(&index)
+            .ok_or(TodoerError::NotFound(index))?;
+        todo.id = Some(id.clone());
+        if created {
+            let name = todo.name.clone();
+            return Ok(Event::Added { index, name });
+        }
+        Ok(Event::Reloaded {
+            list: DayList::from(&*todoer),
+        })
+    });
+    if let Some(response) = precondition_failed(result)? {
+        return Ok(response);
+    }
+    // Without an ETag, as the todo as saved isn't exactly what was sent
+    Ok(if created {
+        HttpResponse::Created().finish()
+    } else {
+        HttpResponse::NoContent().finish()
+    })
+}
+
+async fn delete_item(
+    req: HttpRequest,
+    list: web::ReqData<Arc<List>>,
+    path: web::Path<(String, String)>,
+) -> Result<HttpResponse> {
+    let (name, item) = path.into_inner();
+    let id = item_id(&item)?;
+    if view(&list, &name, |todoer| Ok(find(todoer, &id)))?.is_none() {
+        return Ok(no_todo(&id));
+    }
+    let result = list.update_day(&name, |todoer| {
+        let index = find(todoer, &id);
+        check_preconditions(req.headers(), index.map(|index| &todoer.data.0[&index]))?;
+        // Gone since it was looked up
+        let index = index.ok_or_else(|| TodoerError::Conflict(format!("{} was removed", item)))?;
+        todoer.remove_value(index)?;
+        Ok(Event::Removed { index })
+    });
+    if let Some(response) = precondition_failed(result)? {
+        return Ok(response);
+    }
+    Ok(HttpResponse::NoContent().finish())
+}
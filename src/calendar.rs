@@ -0,0 +1,161 @@
+use std::collections::HashMap;
+
+use chrono::{Datelike, Duration, NaiveDate};
+use console::style;
+
+use crate::error::{Result, TodoerError};
+use crate::todoer::DAY_FORMAT;
+
+// A month of day files, each coloured by how much of it got done
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Calendar {
+    // The first of the month
+    pub month: NaiveDate,
+    // Done and total counts of every day with a list, named lists are left out
+    pub days: HashMap<NaiveDate, (u32, u32)>,
+}
+
+// `YYYY-MM` as the first of that month
+pub fn parse_month(value: &str) -> Result<NaiveDate> {
+    NaiveDate::parse_from_str(&format!("{}-01", value), DAY_FORMAT)
+        .map_err(|_| TodoerError::Config(format!("expected a YYYY-MM month but got {}", value)))
+}
+
+pub fn first_of_month(day: NaiveDate) -> NaiveDate {
+    day.with_day(1).expect("every month has a first day")
+}
+
+fn days_in_month(month: NaiveDate) -> u32 {
+    let next = if month.month() == 12 {
+        NaiveDate::from_ymd_opt(month.year() + 1, 1, 1)
+    } else {
+        NaiveDate::from_ymd_opt(month.year(), month.month() + 1, 1)
+    };
+    let next = next.expect("the next month exists");
+    u32::try_from((next - month).num_days()).expect("months are short")
+}
+
+impl Calendar {
+    // Days are given by their file names with their done and total counts
+    pub fn new<'a>(month: NaiveDate, days: impl IntoIterator<Item = (&'a str, u32, u32)>) -> Self {
+        let days = days
+            .into_iter()
+            .filter_map(|(name, done, size)| {
+                let day = NaiveDate::parse_from_str(name, DAY_FORMAT).ok()?;
+                Some((day, (done, size)))
+            })
+            .collect();
+        Calendar {
+            month: first_of_month(month),
+            days,
+        }
+    }
+
+    // Dim without a file or todos, red when nothing was done, yellow when some, green when all
+    fn cell(&self, day: NaiveDate) -> String {
+        let cell = format!("{:>3}", day.day());
+        match self.days.get(&day) {
+            None | Some((_, 0)) => style(cell).dim().to_string(),
+            Some((0, _)) => style(cell).red().to_string(),
+            Some((done, size)) if done < size => style(cell).yellow().to_string(),
+            Some(_) => style(cell).green().to_string(),
+        }
+    }
+
+    // The month as a grid from Monday to Sunday, with `cursor` highlighted
+    pub fn lines(&self, cursor: Option<NaiveDate>) -> Vec<String> {
+        let mut lines = vec![
+            format!(" {}", self.month.format("%B %Y")),
+            String::from(" Mo Tu We Th Fr Sa Su"),
+        ];
+        let mut week =
+            " ".repeat(usize::try_from(self.month.weekday().num_days_from_monday()).unwrap() * 3);
+        for offset in 0..days_in_month(self.month) {
+            let day = self.month + Duration::days(offset.into());
+            week += &if cursor == Some(day) {
+                style(format!("{:>3}", day.day())).reverse().to_string()
+            } else {
+                self.cell(day)
+            };
+            if day.weekday().num_days_from_monday() == 6 {
+                lines.push(std::mem::take(&mut week));
+            }
+        }
+        if !week.is_empty() {
+            lines.push(week);
+        }
+        let (done, size) = self
+            .days
+            .iter()
+            .filter(|(day, _)| first_of_month(**day) == self.month)
+            .fold((0, 0), |(done, size), (_, counts)| {
+                (done + counts.0, size + counts.1)
+            });
+        // Today is listed before its file has any todos
+        let busy = self
+            .days
+            .iter()
+            .filter(|(day, (_, size))| first_of_month(**day) == self.month && *size > 0)
+            .count();
+        let plural = if busy == 1 { "" } else { "s" };
+        lines.push(format!(
+            " {}/{} done over {} day{}",
+            done, size, busy, plural
+        ));
+        lines.push(format!(
+            " {} all done  {} some done  {} none done",
+            style("■").green(),
+            style("■").yellow(),
+            style("■").red()
+        ));
+        lines
+    }
+
+    pub fn print(&self) -> String {
+        let mut res = String::from("\n");
+        for line in self.lines(None) {
+            res += &line;
+            res += "\n";
+        }
+        res
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use chrono::NaiveDate;
+
+    use super::{days_in_month, parse_month, Calendar};
+
+    #[test]
+    fn month_grid() {
+        let month = parse_month("2022-11").unwrap();
+        assert_eq!(month, NaiveDate::from_ymd_opt(2022, 11, 1).unwrap());
+        assert!(parse_month("2022-13").is_err());
+        assert!(parse_month("november").is_err());
+        assert_eq!(days_in_month(month), 30);
+        assert_eq!(days_in_month(parse_month("2022-12").unwrap()), 31);
+        assert_eq!(days_in_month(parse_month("2024-02").unwrap()), 29);
+
+        let days = [
+            ("2022-11-24", 1, 3),
+            ("2022-11-25", 2, 2),
+            ("2022-12-01", 1, 1),
+            ("errands", 0, 4),
+        ];
+        let calendar = Calendar::new(month, days);
+        // Not a terminal, so without colours
+        let lines = [
+            " November 2022",
+            " Mo Tu We Th Fr Sa Su",
+            "     1  2  3  4  5  6",
+            "  7  8  9 10 11 12 13",
+            " 14 15 16 17 18 19 20",
+            " 21 22 23 24 25 26 27",
+            " 28 29 30",
+            " 3/5 done over 2 days",
+            " ■ all done  ■ some done  ■ none done",
+        ];
+        assert_eq!(calendar.print(), format!("\n{}\n", lines.join("\n")));
+    }
+}
@@ -0,0 +1,168 @@
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+use crate::api::{
+    AgendaList, BulkChange, DayList, DaySummary, MoveTodos, SetStatus, SortTodos, TodoName,
+};
+use crate::error::{Result, TodoerError};
+use crate::todoer::{SortKey, Status};
+
+// Talks to a todoer server over the JSON API, see /openapi.json for the routes it uses
+#[derive(Debug, Clone)]
+pub struct Client {
+    base: String,
+    // `<user>:<token>` as printed by `todoer-server users add`
+    token: Option<String>,
+    agent: ureq::Agent,
+}
+
+impl Client {
+    pub fn new(base: &str, token: Option<String>) -> Self {
+        Client {
+            base: base.trim_end_matches('/').to_string(),
+            token,
+            agent: ureq::Agent::new(),
+        }
+    }
+
+    pub fn todos(&self) -> Result<DayList> {
+        self.send("GET", "/api/todos", None::<&()>, None)
+    }
+
+    pub fn add(&self, name: &str) -> Result<DayList> {
+        let body = TodoName {
+            name: name.to_string(),
+        };
+        self.send("POST", "/api/todos", Some(&body), None)
+    }
+
+    pub fn edit(&self, index: u32, name: &str) -> Result<DayList> {
+        let body = TodoName {
+            name: name.to_string(),
+        };
+        let path = format!("/api/todos/{}", index);
+        self.send("PUT", &path, Some(&body), Some(index))
+    }
+
+    pub fn done(&self, index: u32) -> Result<DayList> {
+        let path = format!("/api/todos/{}/done", index);
+        self.send("POST", &path, None::<&()>, Some(index))
+    }
+
+    pub fn undone(&self, index: u32) -> Result<DayList> {
+        let path = format!("/api/todos/{}/undone", index);
+        self.send("POST", &path, None::<&()>, Some(index))
+    }
+
+    pub fn set_status(&self, index: u32, status: Status) -> Result<DayList> {
+        let body = SetStatus {
+            status: status.to_string(),
+        };
+        let path = format!("/api/todos/{}/status", index);
+        self.send("POST", &path, Some(&body), Some(index))
+    }
+
+    pub fn remove(&self, index: u32) -> Result<DayList> {
+        let path = format!("/api/todos/{}", index);
+        self.send("DELETE", &path, None::<&()>, Some(index))
+    }
+
+    pub fn move_todos(&self, indices: &[u32], to: u32) -> Result<DayList> {
+        let body = MoveTodos {
+            indices: indices.to_vec(),
+            to,
+        };
+        self.send("POST", "/api/todos/move", Some(&body), None)
+    }
+
+    pub fn sort(&self, by: SortKey) -> Result<DayList> {
+        let body = SortTodos { by: by.to_string() };
+        self.send("POST", "/api/todos/sort", Some(&body), None)
+    }
+
+    // Changes every todo in `change` or, if any is missing, none of them
+    pub fn bulk(&self, change: &BulkChange) -> Result<DayList> {
+        self.send("POST", "/api/todos/bulk", Some(change), None)
+    }
+
+    pub fn days(&self) -> Result<Vec<DaySummary>> {
+        self.send("GET", "/api/days", None::<&()>, None)
+    }
+
+    pub fn day(&self, day: &str) -> Result<DayList> {
+        let path = format!("/api/days/{}", day);
+        self.send("GET", &path, None::<&()>, None)
+    }
+
+    pub fn agenda(&self, days: u32) -> Result<AgendaList> {
+        let path = format!("/api/agenda?days={}", days);
+        self.send("GET", &path, None::<&()>, None)
+    }
+
+    // `index` is the todo the request is about, so a 404 can be turned back into `NotFound`
+    fn send<B: Serialize, T: DeserializeOwned>(
+        &self,
+        method: &str,
+        path: &str,
+        body: Option<&B>,
+        index: Option<u32>,
+    ) -> Result<T> {
+        let mut request = self
+            .agent
+            .request(method, &format!("{}{}", self.base, path));
+        if let Some(token) = &self.token {
+            request = request.set("Authorization", &format!("Bearer {}", token));
+        }
+        let response = match body {
+            Some(body) => request.send_json(body),
+            None => request.call(),
+        };
+        match response {
+            Ok(response) => Ok(response.into_json()?),
+            Err(ureq::Error::Status(status, response)) => {
+                let message = response
+                    .into_string()
+                    .unwrap_or_else(|_| format!("server answered {}", status));
+                Err(status_error(status, message, index))
+            }
+            Err(ureq::Error::Transport(err)) => Err(TodoerError::Unreachable(err.to_string())),
+        }
+    }
+}
+
+// The error the server turned into `status`, as close as the response allows
+fn status_error(status: u16, message: String, index: Option<u32>) -> TodoerError {
+    match (status, index) {
+        (401, _) => TodoerError::Unauthorized(message),
+        (404, Some(index)) => TodoerError::NotFound(index),
+        (409, _) => TodoerError::Conflict(message),
+        (500..=599, _) => TodoerError::Io(std::io::Error::other(message)),
+        _ => TodoerError::Config(message),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{status_error, Client};
+    use crate::error::TodoerError;
+
+    #[test]
+    fn errors() {
+        assert!(matches!(
+            status_error(404, String::from("todo 3 does not exist"), Some(3)),
+            TodoerError::NotFound(3)
+        ));
+        assert!(matches!(
+            status_error(409, String::from("todo 0 is already done"), Some(0)),
+            TodoerError::Conflict(_)
+        ));
+        assert!(matches!(
+            status_error(400, String::from("day 2022-11-23 does not exist"), None),
+            TodoerError::Config(_)
+        ));
+
+        // Nothing listens on port 9 of localhost
+        let client = Client::new("http://127.0.0.1:9/", None);
+        assert!(matches!(client.todos(), Err(TodoerError::Unreachable(_))));
+    }
+}
@@ -1,40 +1,126 @@
 use std::path::PathBuf;
 
-use anyhow::{anyhow, Result};
-
+use crate::calendar::{first_of_month, parse_month};
+use crate::error::{Result, TodoerError};
 use crate::opts::Opts;
+use crate::remote::{remote_file, RemoteSettings};
+use crate::todoer::{parse_index, Selection, SortKey, Status, DAY_FORMAT};
 
-use chrono::{Datelike, Local};
+use chrono::{Datelike, Local, NaiveDate};
+use serde::{Deserialize, Serialize};
 
 #[derive(Debug)]
 pub struct Config {
     pub operation: Operation,
     pub config: PathBuf,
+    // Set when operations go to a server instead of `config`
+    pub remote: Option<RemoteSettings>,
 }
 
 impl TryFrom<Opts> for Config {
-    type Error = anyhow::Error;
+    type Error = TodoerError;
 
     fn try_from(value: Opts) -> Result<Self> {
-        let operation = value.args.try_into()?;
-        let filename = value.filename;
+        let (args, filename) = split_filename(value.args, value.filename);
+        let operation = args.try_into()?;
         let config = get_config(value.config, filename)?;
+        let todo_dir = config.parent().map(PathBuf::from).unwrap_or_default();
+        let remote = RemoteSettings::resolve(value.remote, value.token, &remote_file(&todo_dir))?;
+
+        Ok(Config {
+            operation,
+            config,
+            remote,
+        })
+    }
+}
 
-        Ok(Config { operation, config })
+// A day file can be named after the operation, as in `todoer done 0 2022-11-25`, or alone to print it
+fn split_filename(
+    mut args: Vec<String>,
+    filename: Option<String>,
+) -> (Vec<String>, Option<String>) {
+    let is_day = args
+        .last()
+        .is_some_and(|last| NaiveDate::parse_from_str(last, DAY_FORMAT).is_ok());
+    if filename.is_none() && is_day && Operation::try_from(args[..args.len() - 1].to_vec()).is_ok()
+    {
+        let filename = args.pop();
+        return (args, filename);
     }
+    (args, filename)
 }
 
-#[derive(Debug, PartialEq, Eq)]
+// Serialized to queue operations for a server that can't be reached, see `remote`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub enum Operation {
     Print(),
     PrintAll(),
+    Stats(),
     Add(String),
-    Complete(u32),
-    Remove(u32),
+    // Prints how `add` would read the text without adding it, see `quick_add`
+    PreviewAdd(String),
+    Complete(Selection),
+    Remove(Selection),
+    MoveUp(u32),
+    MoveDown(u32),
+    MoveTo(Selection, u32),
+    Sort(SortKey),
+    Tag(String, Selection),
+    SetStatus(Status, Selection),
+    // The selected todos wait on the todo at an index or with an ID, see `dependencies`
+    Block(Selection, String),
+    // Without a todo to stop waiting on, the selected todos wait on nothing
+    Unblock(Selection, Option<String>),
+    Next(),
+    // Commits, pulls and pushes the todo directory, see `sync`
+    Sync(),
+    Start(u32),
+    Stop(),
+    // Tracked time from and to the given days, both included
+    Timesheet(NaiveDate, NaiveDate),
+    // Open todos due within this many days from today on every list, and overdue ones, see `agenda`
+    Agenda(u32),
+    // The month starting on this day as a grid of day files, see `calendar`
+    Calendar(NaiveDate),
+    // Todos of the days from and to the given ones, or of every list, as iCalendar, see `ics`
+    Export(Option<(NaiveDate, NaiveDate)>),
+    // Adds the todos of an iCalendar file to the list
+    Import(PathBuf),
+}
+
+impl Operation {
+    pub fn is_read_only(&self) -> bool {
+        matches!(
+            self,
+            Operation::Print()
+                | Operation::PrintAll()
+                | Operation::Stats()
+                | Operation::Next()
+                | Operation::PreviewAdd(_)
+                | Operation::Agenda(_)
+                | Operation::Calendar(_)
+        )
+    }
+
+    // Operations on files next to the todo directory, which a server doesn't have
+    pub fn is_local_only(&self) -> bool {
+        matches!(
+            self,
+            Operation::Sync()
+                | Operation::Start(_)
+                | Operation::Stop()
+                | Operation::Timesheet(..)
+                | Operation::Block(..)
+                | Operation::Unblock(..)
+                | Operation::Export(_)
+                | Operation::Import(_)
+        )
+    }
 }
 
 impl TryFrom<Vec<String>> for Operation {
-    type Error = anyhow::Error;
+    type Error = TodoerError;
 
     fn try_from(value: Vec<String>) -> Result<Self> {
         let mut value = value;
@@ -42,56 +128,269 @@ impl TryFrom<Vec<String>> for Operation {
             return Ok(Operation::Print());
         }
 
-        let term = value.get(0).expect("expect to exist");
+        let term = value.first().expect("expect to exist");
 
         if term == "add" {
+            let dry_run = value.iter().position(|arg| arg == "--dry-run");
+            if let Some(position) = dry_run {
+                value.remove(position);
+            }
             if value.len() != 2 {
-                let err = anyhow!(
+                let err = TodoerError::Config(format!(
                     "operation add expects 1 arguments but got {}",
                     value.len() - 1
-                );
+                ));
                 return Err(err);
             }
 
             let arg = value.pop().expect("to exist");
+            if dry_run.is_some() {
+                return Ok(Operation::PreviewAdd(arg));
+            }
             return Ok(Operation::Add(arg));
         }
 
         if term == "done" {
-            if value.len() != 2 {
-                let err = anyhow!(
-                    "operation done expects 1 arguments but got {}",
+            return Ok(Operation::Complete(value[1..].try_into()?));
+        }
+
+        if term == "remove" {
+            return Ok(Operation::Remove(value[1..].try_into()?));
+        }
+
+        if term == "tag" {
+            if value.len() < 3 {
+                let err = TodoerError::Config(format!(
+                    "operation tag expects a tag and at least 1 index but got {}",
                     value.len() - 1
-                );
+                ));
                 return Err(err);
             }
 
-            let arg = value.pop().expect("to exist");
-            return Ok(Operation::Complete(
-                arg.parse().expect("Please type a valid number"),
+            return Ok(Operation::Tag(value[1].clone(), value[2..].try_into()?));
+        }
+
+        if term == "block" || term == "unblock" {
+            let by = value.iter().position(|arg| arg == "--by");
+            let selection = &value[1..by.unwrap_or(value.len())];
+            let blocker = by.map(|by| value.get(by + 1).cloned());
+            return match (term.as_str(), blocker) {
+                ("block", Some(Some(blocker))) => {
+                    Ok(Operation::Block(selection.try_into()?, blocker))
+                }
+                ("unblock", None) => Ok(Operation::Unblock(selection.try_into()?, None)),
+                ("unblock", Some(Some(blocker))) => {
+                    Ok(Operation::Unblock(selection.try_into()?, Some(blocker)))
+                }
+                _ => Err(TodoerError::Config(format!(
+                    "operation {} expects todos and --by <index or id>",
+                    term
+                ))),
+            };
+        }
+
+        if term == "next" {
+            return Ok(Operation::Next());
+        }
+
+        if term == "status" {
+            if value.len() < 3 {
+                let err = TodoerError::Config(format!(
+                    "operation status expects a status and at least 1 index but got {}",
+                    value.len() - 1
+                ));
+                return Err(err);
+            }
+
+            return Ok(Operation::SetStatus(
+                value[1].parse()?,
+                value[2..].try_into()?,
             ));
         }
 
-        if term == "remove" {
+        if term == "move-up" || term == "move-down" {
             if value.len() != 2 {
-                let err = anyhow!(
-                    "operation remove expects 1 arguments but got {}",
+                let err = TodoerError::Config(format!(
+                    "operation {} expects 1 arguments but got {}",
+                    term,
                     value.len() - 1
-                );
+                ));
                 return Err(err);
             }
 
-            let arg = value.pop().expect("to exist");
-            return Ok(Operation::Remove(
-                arg.parse().expect("Please type a valid number"),
-            ));
+            let index = parse_index(&value[1])?;
+            if term == "move-up" {
+                return Ok(Operation::MoveUp(index));
+            }
+            return Ok(Operation::MoveDown(index));
+        }
+
+        if term == "move-to" {
+            if value.len() < 3 {
+                let err = TodoerError::Config(format!(
+                    "operation move-to expects at least 2 arguments but got {}",
+                    value.len() - 1
+                ));
+                return Err(err);
+            }
+
+            let to = parse_index(&value.pop().expect("to exist"))?;
+            return Ok(Operation::MoveTo(value[1..].try_into()?, to));
+        }
+
+        if term == "start" {
+            if value.len() != 2 {
+                let err = TodoerError::Config(format!(
+                    "operation start expects 1 arguments but got {}",
+                    value.len() - 1
+                ));
+                return Err(err);
+            }
+
+            return Ok(Operation::Start(parse_index(&value[1])?));
+        }
+
+        if term == "timesheet" {
+            let today = Local::now().date_naive();
+            let (mut from, mut to) = (today, today);
+            for pair in value[1..].chunks(2) {
+                let day = match pair {
+                    [_, day] => NaiveDate::parse_from_str(day, "%Y-%m-%d").map_err(|_| {
+                        TodoerError::Config(format!("expected a YYYY-MM-DD day but got {}", day))
+                    })?,
+                    _ => {
+                        return Err(TodoerError::Config(String::from(
+                            "operation timesheet expects --from <day> and --to <day>",
+                        )))
+                    }
+                };
+                match pair[0].as_str() {
+                    "--from" => from = day,
+                    "--to" => to = day,
+                    flag => {
+                        return Err(TodoerError::Config(format!(
+                            "unknown timesheet flag {}, expected --from or --to",
+                            flag
+                        )))
+                    }
+                }
+            }
+            // A start after today reports that day alone
+            return Ok(Operation::Timesheet(from, to.max(from)));
+        }
+
+        if term == "agenda" {
+            return match &value[1..] {
+                [] => Ok(Operation::Agenda(7)),
+                [flag, days] if flag == "--days" => {
+                    days.parse().map(Operation::Agenda).map_err(|_| {
+                        TodoerError::Config(format!("expected a number of days but got {}", days))
+                    })
+                }
+                _ => Err(TodoerError::Config(String::from(
+                    "operation agenda expects nothing or --days <number>",
+                ))),
+            };
+        }
+
+        if term == "export" {
+            let today = Local::now().date_naive();
+            let (mut from, mut to, mut all) = (today, today, false);
+            let mut args = value[1..].iter();
+            while let Some(flag) = args.next() {
+                if flag == "--all" {
+                    all = true;
+                    continue;
+                }
+                match (flag.as_str(), args.next()) {
+                    ("--format", Some(format)) if format == "ics" => {}
+                    ("--format", format) => {
+                        return Err(TodoerError::Config(format!(
+                            "operation export only knows --format ics but got {}",
+                            format.map_or("nothing", String::as_str)
+                        )))
+                    }
+                    ("--from" | "--to", Some(day)) => {
+                        let day = NaiveDate::parse_from_str(day, "%Y-%m-%d").map_err(|_| {
+                            TodoerError::Config(format!(
+                                "expected a YYYY-MM-DD day but got {}",
+                                day
+                            ))
+                        })?;
+                        if flag == "--from" {
+                            from = day;
+                        } else {
+                            to = day;
+                        }
+                    }
+                    (flag, _) => {
+                        return Err(TodoerError::Config(format!(
+                            "unknown export flag {}, expected --format, --from, --to or --all",
+                            flag
+                        )))
+                    }
+                }
+            }
+            return Ok(Operation::Export((!all).then_some((from, to.max(from)))));
+        }
+
+        if term == "import" {
+            if value.len() != 2 {
+                return Err(TodoerError::Config(String::from(
+                    "operation import expects the .ics file to import",
+                )));
+            }
+            return Ok(Operation::Import(PathBuf::from(&value[1])));
+        }
+
+        if term == "calendar" {
+            return match &value[1..] {
+                [] => Ok(Operation::Calendar(first_of_month(
+                    Local::now().date_naive(),
+                ))),
+                [flag, month] if flag == "--month" => Ok(Operation::Calendar(parse_month(month)?)),
+                _ => Err(TodoerError::Config(String::from(
+                    "operation calendar expects nothing or --month <YYYY-MM>",
+                ))),
+            };
+        }
+
+        if term == "sync" || term == "stats" || term == "stop" {
+            if value.len() != 1 {
+                let err = TodoerError::Config(format!(
+                    "operation {} expects 0 arguments but got {}",
+                    term,
+                    value.len() - 1
+                ));
+                return Err(err);
+            }
+
+            if term == "stats" {
+                return Ok(Operation::Stats());
+            }
+            if term == "stop" {
+                return Ok(Operation::Stop());
+            }
+            return Ok(Operation::Sync());
+        }
+
+        if term == "sort" {
+            if value.len() != 3 || value[1] != "--by" {
+                let err = TodoerError::Config(String::from(
+                    "operation sort expects --by priority|due|tag|alpha",
+                ));
+                return Err(err);
+            }
+
+            let by = value.pop().expect("to exist").parse()?;
+            return Ok(Operation::Sort(by));
         }
 
         if value.len() > 1 {
-            let err = anyhow!(
+            let err = TodoerError::Config(format!(
                 "operation print expects 0 or 1 arguments but got {}",
                 value.len()
-            );
+            ));
             return Err(err);
         }
 
@@ -99,36 +398,25 @@ impl TryFrom<Vec<String>> for Operation {
     }
 }
 
+// The directory day files live in, under `wiki/todo` in the given directory, XDG_CONFIG_HOME or HOME
+pub fn get_todo_dir(config: Option<PathBuf>) -> Result<PathBuf> {
+    let mut dir = config
+        .or_else(|| std::env::var("XDG_CONFIG_HOME").ok().map(PathBuf::from))
+        .or_else(|| std::env::var("HOME").ok().map(PathBuf::from))
+        .ok_or_else(|| TodoerError::Config(String::from("unable to find config location")))?;
+    dir.push("wiki");
+    dir.push("todo");
+    Ok(dir)
+}
+
 pub fn get_config(config: Option<PathBuf>, filename: Option<String>) -> Result<PathBuf> {
     let now = Local::now();
     let current_date_filename = format!("{}-{:02}-{:02}", now.year(), now.month(), now.day());
     let f = filename.unwrap_or(current_date_filename);
 
-    if let Some(mut c) = config {
-        c.push("wiki");
-        c.push("todo");
-        c.push(format!("{}.md", f));
-        return Ok(c);
-    }
-
-    if let Ok(home) = std::env::var("XDG_CONFIG_HOME") {
-        let mut home = PathBuf::from(home);
-        home.push("wiki");
-        home.push("todo");
-        home.push(format!("{}.md", f));
-        return Ok(home);
-    }
-
-    if let Ok(home) = std::env::var("HOME") {
-        let mut home = PathBuf::from(home);
-        home.push("wiki");
-        home.push("todo");
-        home.push(format!("{}.md", f));
-        print!("{}", home.display());
-        return Ok(home);
-    }
-
-    Err(anyhow!("unable to find config location"))
+    let mut config = get_todo_dir(config)?;
+    config.push(format!("{}.md", f));
+    Ok(config)
 }
 
 #[cfg(test)]
@@ -136,22 +424,45 @@ mod test {
 
     use anyhow::Result;
 
-    use crate::{config::Operation, opts::Opts};
+    use crate::{
+        config::Operation,
+        error::TodoerError,
+        opts::Opts,
+        todoer::{Selection, SortKey, Status},
+    };
 
     use super::Config;
 
     use std::path::PathBuf;
 
+    // The operation `args` make, with the default config
+    fn parse(args: &[&str]) -> Result<Operation> {
+        let config: Config = Opts {
+            args: args.iter().map(|arg| arg.to_string()).collect(),
+            config: None,
+            filename: None,
+            remote: None,
+            token: None,
+        }
+        .try_into()?;
+        Ok(config.operation)
+    }
+
     #[test]
     fn test_print_all() -> Result<()> {
         let opts: Config = Opts {
             args: vec![],
             config: Some(PathBuf::from("")),
             filename: None,
+            remote: None,
+            token: None,
         }
         .try_into()?;
 
-        assert_eq!(opts.config, PathBuf::from(""));
+        assert_eq!(
+            opts.config.parent(),
+            Some(PathBuf::from("wiki/todo").as_path())
+        );
         assert_eq!(opts.operation, Operation::Print());
         Ok(())
     }
@@ -162,10 +473,30 @@ mod test {
             args: vec![String::from("add"), String::from("foo")],
             config: None,
             filename: None,
+            remote: None,
+            token: None,
         }
         .try_into()?;
 
         assert_eq!(opts.operation, Operation::Add(String::from("foo")));
+
+        let opts: Config = Opts {
+            args: vec![
+                String::from("add"),
+                String::from("--dry-run"),
+                String::from("call vendor tomorrow 3pm"),
+            ],
+            config: None,
+            filename: None,
+            remote: None,
+            token: None,
+        }
+        .try_into()?;
+        assert_eq!(
+            opts.operation,
+            Operation::PreviewAdd(String::from("call vendor tomorrow 3pm"))
+        );
+        assert!(opts.operation.is_read_only());
         Ok(())
     }
 
@@ -175,10 +506,15 @@ mod test {
             args: vec![String::from("done"), String::from("1")],
             config: None,
             filename: None,
+            remote: None,
+            token: None,
         }
         .try_into()?;
 
-        assert_eq!(opts.operation, Operation::Complete(1));
+        assert_eq!(
+            opts.operation,
+            Operation::Complete(Selection::Indices(vec![1]))
+        );
         Ok(())
     }
 
@@ -187,11 +523,315 @@ mod test {
         let opts: Config = Opts {
             args: vec![String::from("remove"), String::from("1")],
             config: None,
-            filename: String::new(),
+            filename: None,
+            remote: None,
+            token: None,
         }
         .try_into()?;
 
-        assert_eq!(opts.operation, Operation::Remove(1));
+        assert_eq!(
+            opts.operation,
+            Operation::Remove(Selection::Indices(vec![1]))
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_filename() -> Result<()> {
+        let config = |args: &[&str]| -> Result<Config> {
+            Ok(Opts {
+                args: args.iter().map(|arg| arg.to_string()).collect(),
+                config: Some(PathBuf::from("")),
+                filename: None,
+                remote: None,
+                token: None,
+            }
+            .try_into()?)
+        };
+
+        let opts = config(&["2022-11-25"])?;
+        assert_eq!(opts.config, PathBuf::from("wiki/todo/2022-11-25.md"));
+        assert_eq!(opts.operation, Operation::Print());
+
+        let opts = config(&["add", "bar", "2022-11-25"])?;
+        assert_eq!(opts.config, PathBuf::from("wiki/todo/2022-11-25.md"));
+        assert_eq!(opts.operation, Operation::Add(String::from("bar")));
+
+        // Taken by the operation itself
+        let opts = config(&["add", "2022-11-25"])?;
+        assert_eq!(opts.operation, Operation::Add(String::from("2022-11-25")));
+        assert_ne!(opts.config, PathBuf::from("wiki/todo/2022-11-25.md"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_move_todo() -> Result<()> {
+        let opts: Config = Opts {
+            args: vec![String::from("move-up"), String::from("2")],
+            config: None,
+            filename: None,
+            remote: None,
+            token: None,
+        }
+        .try_into()?;
+        assert_eq!(opts.operation, Operation::MoveUp(2));
+
+        let opts: Config = Opts {
+            args: vec![
+                String::from("move-to"),
+                String::from("3"),
+                String::from("0"),
+            ],
+            config: None,
+            filename: None,
+            remote: None,
+            token: None,
+        }
+        .try_into()?;
+        assert_eq!(
+            opts.operation,
+            Operation::MoveTo(Selection::Indices(vec![3]), 0)
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_sort_todos() -> Result<()> {
+        let opts: Config = Opts {
+            args: vec![
+                String::from("sort"),
+                String::from("--by"),
+                String::from("due"),
+            ],
+            config: None,
+            filename: None,
+            remote: None,
+            token: None,
+        }
+        .try_into()?;
+        assert_eq!(opts.operation, Operation::Sort(SortKey::Due));
+
+        let opts: Result<Config, TodoerError> = Opts {
+            args: vec![
+                String::from("sort"),
+                String::from("--by"),
+                String::from("size"),
+            ],
+            config: None,
+            filename: None,
+            remote: None,
+            token: None,
+        }
+        .try_into();
+        assert!(opts.is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_set_status() -> Result<()> {
+        let opts: Config = Opts {
+            args: vec![
+                String::from("status"),
+                String::from("blocked"),
+                String::from("0-1"),
+            ],
+            config: None,
+            filename: None,
+            remote: None,
+            token: None,
+        }
+        .try_into()?;
+        assert_eq!(
+            opts.operation,
+            Operation::SetStatus(Status::Blocked, Selection::Indices(vec![0, 1]))
+        );
+
+        let opts: Result<Config, TodoerError> = Opts {
+            args: vec![
+                String::from("status"),
+                String::from("waiting"),
+                String::from("0"),
+            ],
+            config: None,
+            filename: None,
+            remote: None,
+            token: None,
+        }
+        .try_into();
+        assert!(opts.is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_dependencies() -> Result<()> {
+        assert_eq!(
+            parse(&["block", "1-2", "--by", "0"])?,
+            Operation::Block(Selection::Indices(vec![1, 2]), String::from("0"))
+        );
+        assert_eq!(
+            parse(&["unblock", "2", "--by", "k3xq9a"])?,
+            Operation::Unblock(Selection::Indices(vec![2]), Some(String::from("k3xq9a")))
+        );
+        assert_eq!(
+            parse(&["unblock", "2"])?,
+            Operation::Unblock(Selection::Indices(vec![2]), None)
+        );
+        assert_eq!(parse(&["next"])?, Operation::Next());
+        assert!(parse(&["block", "2"]).is_err());
+        assert!(parse(&["block", "2", "--by"]).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_timers() -> Result<()> {
+        assert_eq!(parse(&["start", "2"])?, Operation::Start(2));
+        assert_eq!(parse(&["stop"])?, Operation::Stop());
+
+        let day = |day| chrono::NaiveDate::from_ymd_opt(2022, 11, day).unwrap();
+        assert_eq!(
+            parse(&["timesheet", "--from", "2022-11-01", "--to", "2022-11-30"])?,
+            Operation::Timesheet(day(1), day(30))
+        );
+        assert!(parse(&["timesheet", "--from"]).is_err());
+        assert!(parse(&["timesheet", "--since", "2022-11-01"]).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_agenda() -> Result<()> {
+        assert_eq!(parse(&["agenda"])?, Operation::Agenda(7));
+        assert_eq!(parse(&["agenda", "--days", "14"])?, Operation::Agenda(14));
+        assert!(parse(&["agenda", "--days", "soon"]).is_err());
+        assert!(parse(&["agenda", "14"]).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_calendar() -> Result<()> {
+        assert_eq!(
+            parse(&["calendar", "--month", "2022-11"])?,
+            Operation::Calendar(chrono::NaiveDate::from_ymd_opt(2022, 11, 1).unwrap())
+        );
+        assert!(matches!(parse(&["calendar"])?, Operation::Calendar(_)));
+        assert!(parse(&["calendar", "--month", "2022-11-25"]).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_export_and_import() -> Result<()> {
+        let day = |day| chrono::NaiveDate::from_ymd_opt(2022, 11, day).unwrap();
+        assert_eq!(
+            parse(&[
+                "export",
+                "--format",
+                "ics",
+                "--from",
+                "2022-11-01",
+                "--to",
+                "2022-11-30"
+            ])?,
+            Operation::Export(Some((day(1), day(30))))
+        );
+        assert_eq!(parse(&["export", "--all"])?, Operation::Export(None));
+        assert!(matches!(parse(&["export"])?, Operation::Export(Some(_))));
+        assert!(parse(&["export", "--format", "csv"]).is_err());
+        assert!(parse(&["export", "--since", "2022-11-01"]).is_err());
+        assert_eq!(
+            parse(&["import", "todos.ics"])?,
+            Operation::Import(PathBuf::from("todos.ics"))
+        );
+        assert!(parse(&["import"]).is_err());
+        assert!(Operation::Import(PathBuf::from("todos.ics")).is_local_only());
+        Ok(())
+    }
+
+    #[test]
+    fn test_sync() -> Result<()> {
+        let opts: Config = Opts {
+            args: vec![String::from("sync")],
+            config: None,
+            filename: None,
+            remote: None,
+            token: None,
+        }
+        .try_into()?;
+        assert_eq!(opts.operation, Operation::Sync());
+        Ok(())
+    }
+
+    #[test]
+    fn test_bulk_todos() -> Result<()> {
+        let opts: Config = Opts {
+            args: vec![
+                String::from("done"),
+                String::from("1"),
+                String::from("3"),
+                String::from("5-7"),
+            ],
+            config: None,
+            filename: None,
+            remote: None,
+            token: None,
+        }
+        .try_into()?;
+        assert_eq!(
+            opts.operation,
+            Operation::Complete(Selection::Indices(vec![1, 3, 5, 6, 7]))
+        );
+
+        let opts: Config = Opts {
+            args: vec![
+                String::from("done"),
+                String::from("--tag"),
+                String::from("standup"),
+            ],
+            config: None,
+            filename: None,
+            remote: None,
+            token: None,
+        }
+        .try_into()?;
+        assert_eq!(
+            opts.operation,
+            Operation::Complete(Selection::Tag(String::from("standup")))
+        );
+
+        let opts: Config = Opts {
+            args: vec![String::from("remove"), String::from("--all-done")],
+            config: None,
+            filename: None,
+            remote: None,
+            token: None,
+        }
+        .try_into()?;
+        assert_eq!(opts.operation, Operation::Remove(Selection::AllDone));
+
+        let opts: Config = Opts {
+            args: vec![
+                String::from("tag"),
+                String::from("standup"),
+                String::from("0-1"),
+            ],
+            config: None,
+            filename: None,
+            remote: None,
+            token: None,
+        }
+        .try_into()?;
+        assert_eq!(
+            opts.operation,
+            Operation::Tag(String::from("standup"), Selection::Indices(vec![0, 1]))
+        );
+
+        let opts: Result<Config, TodoerError> = Opts {
+            args: vec![String::from("done")],
+            config: None,
+            filename: None,
+            remote: None,
+            token: None,
+        }
+        .try_into();
+        assert!(opts.is_err());
         Ok(())
     }
 }
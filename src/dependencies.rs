@@ -0,0 +1,455 @@
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+
+use rand::Rng;
+use sha2::{Digest, Sha256};
+
+use crate::error::{Result, TodoerError};
+use crate::interactive::day_name;
+use crate::todoer::{parse_index, Status, Todo, Todoer, Todos, DAY_FORMAT, TIME_FORMAT};
+use crate::todoers::Todoers;
+
+// Without look-alikes such as `l` and `1`, so IDs can be typed from a printout
+const ID_CHARS: &[u8] = b"abcdefghijkmnpqrstuvwxyz23456789";
+const ID_LENGTH: usize = 6;
+
+// Whether the ID was made here rather than taken from elsewhere, such as a calendar's UID
+pub fn is_generated(id: &str) -> bool {
+    id.len() == ID_LENGTH && id.bytes().all(|byte| ID_CHARS.contains(&byte))
+}
+
+// The todo's ID, or for a todo without one an ID made from the day it was first on, when it was
+// added and its name. The same every time it is asked for, so lists can be read without saving IDs
+pub fn stable_id(todoer: &Todoer, todo: &Todo) -> String {
+    if let Some(id) = &todo.id {
+        return id.clone();
+    }
+    let first_day = todo.since.map_or_else(
+        || day_name(&todoer.config),
+        |day| day.format(DAY_FORMAT).to_string(),
+    );
+    let created = todo
+        .created
+        .map(|created| created.format(TIME_FORMAT).to_string())
+        .unwrap_or_default();
+    let mut hasher = Sha256::new();
+    hasher.update(format!("{} {} {}", first_day, created, todo.name).as_bytes());
+    // 32 characters divide 256 evenly, so every one is as likely
+    hasher.finalize()[..ID_LENGTH]
+        .iter()
+        .map(|byte| char::from(ID_CHARS[usize::from(*byte) % ID_CHARS.len()]))
+        .collect()
+}
+
+#[derive(Debug, Clone)]
+struct Node {
+    name: String,
+    status: Status,
+    blocked_by: Vec<String>,
+}
+
+// Every todo with an ID as it is on the latest list it appears on, which is where it was carried to
+#[derive(Debug, Clone, Default)]
+pub struct Dependencies {
+    nodes: HashMap<String, Node>,
+    // What the todos without an ID go by, see `stable_id`
+    stable: HashSet<String>,
+}
+
+impl Dependencies {
+    // Later lists win over earlier ones
+    pub fn new<'a>(todoers: impl IntoIterator<Item = &'a Todoer>) -> Self {
+        let mut dependencies = Dependencies::default();
+        for todoer in todoers {
+            dependencies.update(todoer);
+        }
+        dependencies
+    }
+
+    // Every list in the todo directory, with `current` in place of its file as saved
+    pub fn load(todo_dir: &Path, current: &Todoer) -> Result<Self> {
+        let todoers = if std::fs::metadata(todo_dir).is_ok() {
+            Todoers::from_todos_dir(todo_dir.to_path_buf())?.todoers
+        } else {
+            Vec::new()
+        };
+        let mut dependencies = Dependencies::default();
+        let mut seen = false;
+        for todoer in &todoers {
+            if todoer.config == current.config {
+                dependencies.update(current);
+                seen = true;
+            } else {
+                dependencies.update(todoer);
+            }
+        }
+        if !seen {
+            dependencies.update(current);
+        }
+        Ok(dependencies)
+    }
+
+    pub fn update(&mut self, todoer: &Todoer) {
+        let Todos(todos) = &todoer.data;
+        for todo in todos.values() {
+            if let Some(id) = &todo.id {
+                let node = Node {
+                    name: todo.name.clone(),
+                    status: todo.status,
+                    blocked_by: todo.blocked_by.clone(),
+                };
+                self.nodes.insert(id.clone(), node);
+            } else {
+                self.stable.insert(stable_id(todoer, todo));
+            }
+        }
+    }
+
+    pub fn contains(&self, id: &str) -> bool {
+        self.nodes.contains_key(id)
+    }
+
+    // Also the IDs todos without one go by, such as on a calendar they were exported to
+    pub fn knows(&self, id: &str) -> bool {
+        self.contains(id) || self.stable.contains(id)
+    }
+
+    fn name<'a>(&'a self, id: &'a str) -> &'a str {
+        self.nodes.get(id).map_or(id, |node| node.name.as_str())
+    }
+
+    // Names of the todos `todo` is still waiting on. IDs no list has any more don't hold it up
+    pub fn waiting_on(&self, todo: &Todo) -> Vec<&str> {
+        todo.blocked_by
+            .iter()
+            .filter_map(|id| self.nodes.get(id))
+            .filter(|node| !node.status.is_closed())
+            .map(|node| node.name.as_str())
+            .collect()
+    }
+
+    // Open or in progress and not waiting on another todo
+    pub fn is_actionable(&self, todo: &Todo) -> bool {
+        matches!(todo.status, Status::Open | Status::InProgress) && self.waiting_on(todo).is_empty()
+    }
+
+    // The IDs from `from` to `to` following what each waits on, both included
+    fn path(&self, from: &str, to: &str) -> Option<Vec<String>> {
+        let mut seen = HashSet::new();
+        let mut stack = vec![vec![from.to_string()]];
+        while let Some(path) = stack.pop() {
+            let last = path.last().expect("paths are never empty");
+            if last == to {
+                return Some(path);
+            }
+            if !seen.insert(last.clone()) {
+                continue;
+            }
+            for next in self
+                .nodes
+                .get(last)
+                .map_or(&[][..], |node| &node.blocked_by)
+            {
+                let mut longer = path.clone();
+                longer.push(next.clone());
+                stack.push(longer);
+            }
+        }
+        None
+    }
+
+    // `a -> b -> a` for the first todos found waiting on each other, such as after editing by hand
+    fn find_cycle(&self) -> Option<String> {
+        let mut ids: Vec<&String> = self.nodes.keys().collect();
+        ids.sort();
+        ids.into_iter().find_map(|id| {
+            let node = &self.nodes[id];
+            node.blocked_by.iter().find_map(|next| {
+                let path = self.path(next, id)?;
+                let names: Vec<&str> = std::iter::once(id.as_str())
+                    .chain(path.iter().map(String::as_str))
+                    .map(|id| self.name(id))
+                    .collect();
+                Some(names.join(" -> "))
+            })
+        })
+    }
+
+    fn new_id(&self, todoer: &Todoer) -> String {
+        let mut rng = rand::thread_rng();
+        loop {
+            let id: String = (0..ID_LENGTH)
+                .map(|_| char::from(ID_CHARS[rng.gen_range(0..ID_CHARS.len())]))
+                .collect();
+            let taken = self.nodes.contains_key(&id)
+                || todoer
+                    .data
+                    .0
+                    .values()
+                    .any(|todo| todo.id.as_deref() == Some(id.as_str()));
+            if !taken {
+                return id;
+            }
+        }
+    }
+
+    // The todo's ID, giving it one the first time it is needed
+    pub fn id_of(&mut self, todoer: &mut Todoer, index: u32) -> Result<String> {
+        let new_id = self.new_id(todoer);
+        let todo = todoer
+            .data
+            .0
+            .get_mut(&index)
+            .ok_or(TodoerError::NotFound(index))?;
+        let id = todo.id.get_or_insert(new_id).clone();
+        self.update(todoer);
+        Ok(id)
+    }
+}
+
+// Makes the todos at `indices` wait on `blocker`, which is the ID of a todo on any day or else an
+// index in `todoer`. IDs are looked up first, as generated and imported ones may be all digits.
+// Fails before any todo changes, or is given an ID, if that would make a cycle
+pub fn block(
+    todoer: &mut Todoer,
+    indices: &[u32],
+    blocker: &str,
+    dependencies: &mut Dependencies,
+) -> Result<()> {
+    let blocker_index = if dependencies.contains(blocker) {
+        None
+    } else {
+        let index = parse_index(blocker)
+            .map_err(|_| TodoerError::Config(format!("no todo has the ID {}", blocker)))?;
+        Some(index)
+    };
+    let blocker_id = match blocker_index {
+        Some(index) => todoer
+            .data
+            .0
+            .get(&index)
+            .ok_or(TodoerError::NotFound(index))?
+            .id
+            .clone(),
+        None => Some(blocker.to_string()),
+    };
+    // Checked for all of them first. Blocking more todos on the same one can't make a cycle through
+    // another of them that checking each on its own would miss. Todos without an ID yet can only
+    // make one by waiting on themselves, as nothing can wait on them
+    for index in indices {
+        let todo = todoer
+            .data
+            .0
+            .get(index)
+            .ok_or(TodoerError::NotFound(*index))?;
+        if Some(*index) == blocker_index || (todo.id.is_some() && todo.id == blocker_id) {
+            return Err(TodoerError::Conflict(format!(
+                "{} can't wait on itself",
+                todo.name
+            )));
+        }
+        let (Some(id), Some(blocker)) = (&todo.id, &blocker_id) else {
+            continue;
+        };
+        if let Some(path) = dependencies.path(blocker, id) {
+            let names: Vec<&str> = path.iter().map(|id| dependencies.name(id)).collect();
+            return Err(TodoerError::Conflict(format!(
+                "{} can't wait on {}, that would be a cycle: {}",
+                dependencies.name(id),
+                dependencies.name(blocker),
+                names.join(" -> ")
+            )));
+        }
+    }
+
+    let blocker = match blocker_index {
+        Some(index) => dependencies.id_of(todoer, index)?,
+        None => blocker.to_string(),
+    };
+    for index in indices {
+        // Waiting todos get an ID too, so a later block that would close a cycle through them is seen
+        dependencies.id_of(todoer, *index)?;
+        let todo = todoer
+            .data
+            .0
+            .get_mut(index)
+            .ok_or(TodoerError::NotFound(*index))?;
+        if !todo.blocked_by.contains(&blocker) {
+            todo.blocked_by.push(blocker.clone());
+        }
+    }
+    dependencies.update(todoer);
+    Ok(())
+}
+
+// Stops the todos at `indices` waiting on `blocker`, or on anything when it is None. As with
+// `block`, an ID one of them waits on is taken over an index
+pub fn unblock(todoer: &mut Todoer, indices: &[u32], blocker: Option<&str>) -> Result<()> {
+    let Todos(todos) = &todoer.data;
+    let is_id = |blocker: &str| {
+        indices
+            .iter()
+            .filter_map(|index| todos.get(index))
+            .any(|todo| todo.blocked_by.iter().any(|id| id == blocker))
+    };
+    let blocker = match blocker {
+        Some(blocker) if is_id(blocker) => Some(blocker.to_string()),
+        Some(blocker) => match parse_index(blocker) {
+            Ok(index) => Some(
+                todos
+                    .get(&index)
+                    .ok_or(TodoerError::NotFound(index))?
+                    .id
+                    .clone()
+                    .unwrap_or_default(),
+            ),
+            Err(_) => Some(blocker.to_string()),
+        },
+        None => None,
+    };
+    for index in indices {
+        let todo = todoer
+            .data
+            .0
+            .get_mut(index)
+            .ok_or(TodoerError::NotFound(*index))?;
+        match &blocker {
+            Some(blocker) => todo.blocked_by.retain(|id| id != blocker),
+            None => todo.blocked_by.clear(),
+        }
+    }
+    Ok(())
+}
+
+// Only the todos that can be worked on now, see `Dependencies::is_actionable`
+pub fn next(todoer: &Todoer, dependencies: &Dependencies) -> Result<String> {
+    if let Some(cycle) = dependencies.find_cycle() {
+        return Err(TodoerError::Conflict(format!(
+            "todos wait on each other: {}",
+            cycle
+        )));
+    }
+    let Todos(todos) = &todoer.data;
+    let mut res = String::from("\nNext\n");
+    for index in 0..todoer.size {
+        let Some(todo) = todos.get(&index) else {
+            continue;
+        };
+        if dependencies.is_actionable(todo) {
+            res += &format!("{}). {}\n", index, todo.name);
+        }
+    }
+    Ok(res)
+}
+
+#[cfg(test)]
+mod test {
+    use std::path::PathBuf;
+
+    use super::{block, next, unblock, Dependencies};
+    use crate::error::TodoerError;
+    use crate::todoer::Todoer;
+
+    fn get_todoer(config: &str, names: &[&str]) -> Todoer {
+        let mut todoer = Todoer::default_todoer(PathBuf::from(config));
+        for name in names {
+            todoer.set_value(name.to_string());
+        }
+        todoer
+    }
+
+    #[test]
+    fn waiting_and_next() {
+        let mut todoer = get_todoer(
+            "wiki/todo/2022-11-25.md",
+            &["write CLI", "review PR", "ship"],
+        );
+        let mut dependencies = Dependencies::new([&todoer]);
+        block(&mut todoer, &[2], "0", &mut dependencies).unwrap();
+        block(&mut todoer, &[2], "1", &mut dependencies).unwrap();
+        block(&mut todoer, &[1], "0", &mut dependencies).unwrap();
+        let id = todoer.data.0[&0].id.clone().unwrap();
+        assert_eq!(todoer.data.0[&2].blocked_by.len(), 2);
+        assert_eq!(
+            dependencies.waiting_on(&todoer.data.0[&2]),
+            vec!["write CLI", "review PR"]
+        );
+        assert_eq!(
+            next(&todoer, &dependencies).unwrap(),
+            "\nNext\n0). write CLI\n"
+        );
+
+        // The blocker written to the file is read back from another day by its ID
+        let contents = String::try_from(&todoer).unwrap();
+        assert!(contents.contains("- [ ] write CLI created:"));
+        assert!(contents.contains(&format!(" id:{}\n", id)));
+        assert!(contents.contains(&format!(" blocked-by:{}", id)));
+        let mut tomorrow = get_todoer("wiki/todo/2022-11-26.md", &["deploy"]);
+        todoer.mark_done(0).unwrap();
+        let mut dependencies = Dependencies::new([&todoer, &tomorrow]);
+        block(&mut tomorrow, &[0], &id, &mut dependencies).unwrap();
+        assert!(dependencies.is_actionable(&tomorrow.data.0[&0]));
+        assert_eq!(
+            next(&todoer, &dependencies).unwrap(),
+            "\nNext\n1). review PR\n"
+        );
+
+        unblock(&mut todoer, &[2], Some("1")).unwrap();
+        assert_eq!(todoer.data.0[&2].blocked_by, vec![id]);
+        unblock(&mut todoer, &[2], None).unwrap();
+        assert!(todoer.data.0[&2].blocked_by.is_empty());
+        assert!(block(&mut todoer, &[2], "nope", &mut dependencies).is_err());
+    }
+
+    #[test]
+    fn numeric_ids() {
+        let mut yesterday = get_todoer("wiki/todo/2022-11-24.md", &["call bank"]);
+        yesterday.data.0.get_mut(&0).unwrap().id = Some(String::from("234567"));
+        let mut todoer = get_todoer("wiki/todo/2022-11-25.md", &["write CLI", "ship"]);
+        let mut dependencies = Dependencies::new([&yesterday, &todoer]);
+
+        block(&mut todoer, &[1], "234567", &mut dependencies).unwrap();
+        assert_eq!(todoer.data.0[&1].blocked_by, vec!["234567"]);
+        unblock(&mut todoer, &[1], Some("234567")).unwrap();
+        assert!(todoer.data.0[&1].blocked_by.is_empty());
+    }
+
+    #[test]
+    fn cycles() {
+        let mut todoer = get_todoer("wiki/todo/2022-11-25.md", &["a", "b", "c"]);
+        let mut dependencies = Dependencies::new([&todoer]);
+        block(&mut todoer, &[1], "0", &mut dependencies).unwrap();
+        block(&mut todoer, &[2], "1", &mut dependencies).unwrap();
+
+        let err = block(&mut todoer, &[0], "2", &mut dependencies).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            TodoerError::Conflict(String::from(
+                "a can't wait on c, that would be a cycle: c -> b -> a"
+            ))
+            .to_string()
+        );
+        assert!(todoer.data.0[&0].blocked_by.is_empty());
+        assert!(block(&mut todoer, &[0], "0", &mut dependencies).is_err());
+
+        // A refused block gives no todo an ID
+        let mut fresh = get_todoer("wiki/todo/2022-11-26.md", &["d"]);
+        let mut dependencies = Dependencies::new([&fresh]);
+        assert!(block(&mut fresh, &[0], "0", &mut dependencies).is_err());
+        assert_eq!(fresh.data.0[&0].id, None);
+
+        // Made by editing the file by hand
+        let c = todoer.data.0[&2].id.clone().unwrap();
+        todoer.data.0.get_mut(&0).unwrap().blocked_by.push(c);
+        let dependencies = Dependencies::new([&todoer]);
+        let err = next(&todoer, &dependencies).unwrap_err().to_string();
+        assert!(err.contains("todos wait on each other: "), "{}", err);
+        assert!(
+            ["a -> c -> b -> a", "b -> a -> c -> b", "c -> b -> a -> c"]
+                .iter()
+                .any(|cycle| err.ends_with(cycle)),
+            "{}",
+            err
+        );
+    }
+}
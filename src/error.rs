@@ -0,0 +1,72 @@
+use std::fmt;
+
+#[derive(Debug)]
+pub enum TodoerError {
+    // No todo exists at the given index
+    NotFound(u32),
+    // A todo file could not be read, `line` is 1-based
+    Parse { line: usize, message: String },
+    Io(std::io::Error),
+    // The arguments or configuration are invalid, or no todo directory could be found
+    Config(String),
+    // The operation does not apply to the todo in its current state
+    Conflict(String),
+    // The request carries no credentials or ones that don't match a user
+    Unauthorized(String),
+    // A server given with `--remote` could not be reached
+    Unreachable(String),
+}
+
+pub type Result<T> = std::result::Result<T, TodoerError>;
+
+impl fmt::Display for TodoerError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TodoerError::NotFound(index) => write!(f, "todo {} does not exist", index),
+            TodoerError::Parse { line, message } => {
+                write!(f, "could not parse line {}: {}", line, message)
+            }
+            TodoerError::Io(err) => write!(f, "{}", err),
+            TodoerError::Config(message) => write!(f, "{}", message),
+            TodoerError::Conflict(message) => write!(f, "{}", message),
+            TodoerError::Unauthorized(message) => write!(f, "{}", message),
+            TodoerError::Unreachable(message) => write!(f, "server unreachable: {}", message),
+        }
+    }
+}
+
+impl std::error::Error for TodoerError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            TodoerError::Io(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<std::io::Error> for TodoerError {
+    fn from(err: std::io::Error) -> Self {
+        TodoerError::Io(err)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::TodoerError;
+
+    #[test]
+    fn messages() {
+        assert_eq!(
+            TodoerError::NotFound(3).to_string(),
+            "todo 3 does not exist"
+        );
+        assert_eq!(
+            TodoerError::Parse {
+                line: 4,
+                message: String::from("expected - [ ] or - [x]")
+            }
+            .to_string(),
+            "could not parse line 4: expected - [ ] or - [x]"
+        );
+    }
+}
@@ -0,0 +1,188 @@
+use std::convert::Infallible;
+use std::path::PathBuf;
+
+use actix_web::web::Bytes;
+use futures_util::Stream;
+use serde::Serialize;
+use tokio::sync::broadcast::{self, error::RecvError};
+
+use crate::api::DayList;
+use crate::error::Result;
+use crate::todoer::Todoer;
+
+// Events that haven't been read by a slow client yet before it starts missing some
+const CAPACITY: usize = 64;
+
+// A change to the shared list, pushed to every client listening on /events
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Event {
+    Added { index: u32, name: String },
+    Completed { index: u32 },
+    Uncompleted { index: u32 },
+    Removed { index: u32 },
+    Edited { index: u32, name: String },
+    // `status` is written as in the API, such as `blocked`
+    StatusChanged { index: u32, status: String },
+    // Todos were moved or sorted, so every index may have changed
+    Reordered { list: DayList },
+    // Several todos were changed in one go, see `BulkChange`
+    Changed { list: DayList },
+    // The list of another day was changed, such as from a calendar app. Indices in other events
+    // are of today's list
+    DayChanged { list: DayList },
+    // A new day started, todos left open yesterday have been carried over
+    DayRolledOver { list: DayList },
+    // The file was changed by something other than the server, such as the CLI
+    Reloaded { list: DayList },
+}
+
+impl Event {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Event::Added { .. } => "added",
+            Event::Completed { .. } => "completed",
+            Event::Uncompleted { .. } => "uncompleted",
+            Event::Removed { .. } => "removed",
+            Event::Edited { .. } => "edited",
+            Event::StatusChanged { .. } => "status_changed",
+            Event::Reordered { .. } => "reordered",
+            Event::Changed { .. } => "changed",
+            Event::DayChanged { .. } => "day_changed",
+            Event::DayRolledOver { .. } => "day_rolled_over",
+            Event::Reloaded { .. } => "reloaded",
+        }
+    }
+
+    // Formats the event as a server-sent event, the JSON also carries the type
+    pub fn to_sse(&self) -> String {
+        format!(
+            "event: {}\ndata: {}\n\n",
+            self.name(),
+            serde_json::to_string(self).unwrap()
+        )
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Events {
+    sender: broadcast::Sender<Event>,
+}
+
+impl Default for Events {
+    fn default() -> Self {
+        Events::new()
+    }
+}
+
+impl Events {
+    pub fn new() -> Self {
+        let (sender, _) = broadcast::channel(CAPACITY);
+        Events { sender }
+    }
+
+    // Nobody listening is not an error, the event is just dropped
+    pub fn send(&self, event: Event) {
+        let _ = self.sender.send(event);
+    }
+
+    pub fn subscribe(&self) -> broadcast::Receiver<Event> {
+        self.sender.subscribe()
+    }
+
+    // Every event from now on as a server-sent event body, skipping any a slow client missed
+    pub fn stream(&self) -> impl Stream<Item = std::result::Result<Bytes, Infallible>> {
+        let hello = Bytes::from_static(b": connected\n\n");
+        futures_util::stream::unfold(
+            (Some(hello), self.subscribe()),
+            |(hello, mut receiver)| async move {
+                if let Some(hello) = hello {
+                    return Some((Ok(hello), (None, receiver)));
+                }
+                loop {
+                    match receiver.recv().await {
+                        Ok(event) => {
+                            return Some((Ok(Bytes::from(event.to_sse())), (None, receiver)))
+                        }
+                        Err(RecvError::Lagged(_)) => continue,
+                        Err(RecvError::Closed) => return None,
+                    }
+                }
+            },
+        )
+    }
+}
+
+// Brings `todoer` in line with the disk, switching to `today` once the day has changed
+pub fn sync_with_disk(todoer: &mut Todoer, today: PathBuf) -> Result<Option<Event>> {
+    if todoer.config != today {
+        *todoer = Todoer::from_config(today, false)?;
+        return Ok(Some(Event::DayRolledOver {
+            list: DayList::from(&*todoer),
+        }));
+    }
+    if std::fs::metadata(&todoer.config).is_err() {
+        return Ok(None);
+    }
+
+    let contents = std::fs::read_to_string(&todoer.config)?;
+    let current: String = (&*todoer).try_into()?;
+    if contents == current {
+        return Ok(None);
+    }
+    *todoer = Todoer::from_config(todoer.config.clone(), true)?;
+    Ok(Some(Event::Reloaded {
+        list: DayList::from(&*todoer),
+    }))
+}
+
+#[cfg(test)]
+mod test {
+    use super::{sync_with_disk, Event, Events};
+    use crate::testing::todo_dir;
+    use crate::todoer::Todoer;
+
+    #[test]
+    fn sse_format() {
+        let event = Event::Added {
+            index: 2,
+            name: String::from("foo"),
+        };
+        assert_eq!(
+            event.to_sse(),
+            "event: added\ndata: {\"type\":\"added\",\"index\":2,\"name\":\"foo\"}\n\n"
+        );
+    }
+
+    #[test]
+    fn subscribers_get_events() {
+        let events = Events::new();
+        events.send(Event::Removed { index: 0 });
+
+        let mut receiver = events.subscribe();
+        events.send(Event::Completed { index: 1 });
+        assert_eq!(receiver.try_recv().unwrap(), Event::Completed { index: 1 });
+        assert!(receiver.try_recv().is_err());
+    }
+
+    #[test]
+    fn changes_on_disk() {
+        let today = todo_dir("events", "disk").join("2022-11-25.md");
+        let mut todoer = Todoer::default_todoer(today.clone());
+        todoer.set_value(String::from("foo"));
+        todoer.save().unwrap();
+        assert_eq!(sync_with_disk(&mut todoer, today.clone()).unwrap(), None);
+
+        std::fs::write(&today, "2022-11-25\n1/2\n- [x] foo\n- [ ] bar\n").unwrap();
+        let event = sync_with_disk(&mut todoer, today.clone()).unwrap().unwrap();
+        assert_eq!(event.name(), "reloaded");
+        assert_eq!(todoer.size, 2);
+
+        let tomorrow = today.with_file_name("2022-11-26.md");
+        let event = sync_with_disk(&mut todoer, tomorrow.clone())
+            .unwrap()
+            .unwrap();
+        assert_eq!(event.name(), "day_rolled_over");
+        assert_eq!(todoer.config, tomorrow);
+    }
+}
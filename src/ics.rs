@@ -0,0 +1,597 @@
+use std::collections::HashMap;
+
+use chrono::{DateTime, Local, NaiveDate, NaiveDateTime, NaiveTime, TimeZone, Utc};
+
+use crate::dependencies::{is_generated, stable_id, Dependencies};
+use crate::error::{Result, TodoerError};
+use crate::interactive::day_name;
+use crate::quick_add::QuickAdd;
+use crate::todoer::{
+    format_estimate, parse_estimate, parse_priority, Priority, Status, Todo, Todoer, DAY_FORMAT,
+};
+
+// RFC 5545 calendars of VTODO entries, so todos can go to and come from calendar apps
+
+const PRODID: &str = "-//todoer//todoer//EN";
+// UIDs of todos with an ID made by todoer are the ID at this domain, see `dependencies`
+const UID_DOMAIN: &str = "@todoer";
+const DATE: &str = "%Y%m%d";
+const LOCAL_TIME: &str = "%Y%m%dT%H%M%S";
+const UTC_TIME: &str = "%Y%m%dT%H%M%SZ";
+// Statuses VTODO has no word for keep ours next to the closest one
+const STATUS_PROPERTY: &str = "X-TODOER-STATUS";
+const ESTIMATE_PROPERTY: &str = "X-TODOER-ESTIMATE";
+
+fn escape(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace(';', "\\;")
+        .replace(',', "\\,")
+        .replace('\n', "\\n")
+}
+
+fn unescape(text: &str) -> String {
+    let mut res = String::new();
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            res.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n' | 'N') => res.push('\n'),
+            Some(c) => res.push(c),
+            None => res.push('\\'),
+        }
+    }
+    res
+}
+
+// Lines are at most 75 octets, longer ones go on in lines starting with a space
+fn fold(line: &str) -> String {
+    let mut res = String::new();
+    let mut width = 0;
+    for c in line.chars() {
+        if width + c.len_utf8() > 75 {
+            res += "\r\n ";
+            width = 1;
+        }
+        res.push(c);
+        width += c.len_utf8();
+    }
+    res + "\r\n"
+}
+
+fn to_utc(time: NaiveDateTime) -> NaiveDateTime {
+    Local
+        .from_local_datetime(&time)
+        .earliest()
+        .map_or(time, |time| time.with_timezone(&Utc).naive_utc())
+}
+
+// Words of the name that other properties carry, as read by `Todo`
+fn is_token(word: &str) -> bool {
+    let parses =
+        |prefix: &str, parse: &dyn Fn(&str) -> bool| word.strip_prefix(prefix).is_some_and(parse);
+    word.len() > 1 && (word.starts_with('#') || word.starts_with('@'))
+        || parse_priority(word).is_some()
+        || parses("due:", &|value| {
+            NaiveDate::parse_from_str(value, DAY_FORMAT).is_ok()
+        })
+        || parses("at:", &|value| {
+            NaiveTime::parse_from_str(value, "%H:%M").is_ok()
+        })
+        || parses("est:", &|value| parse_estimate(value).is_some())
+}
+
+fn priority_number(priority: Priority) -> u8 {
+    match priority {
+        Priority::High => 1,
+        Priority::Medium => 5,
+        Priority::Low => 9,
+    }
+}
+
+// Other IDs came from a UID, which is given back as it was
+fn uid(id: &str) -> String {
+    if is_generated(id) {
+        format!("{}{}", id, UID_DOMAIN)
+    } else {
+        id.to_string()
+    }
+}
+
+// Wraps VTODO entries into a whole calendar
+pub fn calendar(vtodos: &str) -> String {
+    format!(
+        "{}{}{}{}{}",
+        fold("BEGIN:VCALENDAR"),
+        fold("VERSION:2.0"),
+        fold(&format!("PRODID:{}", PRODID)),
+        vtodos,
+        fold("END:VCALENDAR")
+    )
+}
+
+// A calendar of this todo alone, as CalDAV serves each todo
+pub fn todo_calendar(todo: &Todo, id: &str, stamp: DateTime<Utc>) -> String {
+    calendar(&vtodo(todo, id, stamp.naive_utc()))
+}
+
+fn vtodo(todo: &Todo, id: &str, stamp: NaiveDateTime) -> String {
+    let summary: Vec<&str> = todo
+        .name
+        .split_whitespace()
+        .filter(|word| !is_token(word))
+        .collect();
+    let mut lines = vec![
+        String::from("BEGIN:VTODO"),
+        format!("UID:{}", escape(&uid(id))),
+        format!("DTSTAMP:{}", stamp.format(UTC_TIME)),
+        format!("SUMMARY:{}", escape(&summary.join(" "))),
+    ];
+    let status = match todo.status {
+        Status::Open | Status::Blocked | Status::Deferred => "NEEDS-ACTION",
+        Status::InProgress => "IN-PROCESS",
+        Status::Done => "COMPLETED",
+        Status::Cancelled => "CANCELLED",
+    };
+    lines.push(format!("STATUS:{}", status));
+    if matches!(todo.status, Status::Blocked | Status::Deferred) {
+        lines.push(format!("{}:{}", STATUS_PROPERTY, todo.status));
+    }
+    if let Some(priority) = todo.priority() {
+        lines.push(format!("PRIORITY:{}", priority_number(priority)));
+    }
+    let categories: Vec<String> = todo
+        .tags()
+        .into_iter()
+        .map(String::from)
+        .chain(
+            todo.contexts()
+                .into_iter()
+                .map(|context| format!("@{}", context)),
+        )
+        .map(|category| escape(&category))
+        .collect();
+    if !categories.is_empty() {
+        lines.push(format!("CATEGORIES:{}", categories.join(",")));
+    }
+    match (todo.due(), todo.due_time()) {
+        (Some(due), Some(time)) => {
+            lines.push(format!("DUE:{}", due.and_time(time).format(LOCAL_TIME)))
+        }
+        (Some(due), None) => lines.push(format!("DUE;VALUE=DATE:{}", due.format(DATE))),
+        _ => {}
+    }
+    if let Some(estimate) = todo.estimate() {
+        lines.push(format!(
+            "{}:{}",
+            ESTIMATE_PROPERTY,
+            format_estimate(estimate)
+        ));
+    }
+    if let Some(created) = todo.created {
+        lines.push(format!("CREATED:{}", to_utc(created).format(UTC_TIME)));
+    }
+    if let Some(completed) = todo.completed {
+        lines.push(format!("COMPLETED:{}", to_utc(completed).format(UTC_TIME)));
+    }
+    for blocker in &todo.blocked_by {
+        lines.push(format!(
+            "RELATED-TO;RELTYPE=DEPENDS-ON:{}",
+            escape(&uid(blocker))
+        ));
+    }
+    lines.push(String::from("END:VTODO"));
+    lines.iter().map(|line| fold(line)).collect()
+}
+
+// The latest copy of every todo on `todoers`, which are oldest first, with carried over todos told
+// apart as in `agenda`. Todos without an ID go by their `stable_id`, so their UID stays the same on
+// every export without the lists changing
+pub fn export(todoers: &[Todoer], stamp: DateTime<Utc>) -> String {
+    let mut latest: HashMap<(String, String), (usize, u32)> = HashMap::new();
+    let mut order: Vec<(String, String)> = Vec::new();
+    for (position, todoer) in todoers.iter().enumerate() {
+        let list = day_name(&todoer.config);
+        for index in 0..todoer.size {
+            let Some(todo) = todoer.data.0.get(&index) else {
+                continue;
+            };
+            let first_day = todo
+                .since
+                .map_or_else(|| list.clone(), |day| day.format(DAY_FORMAT).to_string());
+            let key = (first_day, todo.name.clone());
+            if latest.insert(key.clone(), (position, index)).is_none() {
+                order.push(key);
+            }
+        }
+    }
+    let mut vtodos = String::new();
+    for key in order {
+        let (position, index) = latest[&key];
+        let todoer = &todoers[position];
+        let todo = &todoer.data.0[&index];
+        vtodos += &vtodo(todo, &stable_id(todoer, todo), stamp.naive_utc());
+    }
+    calendar(&vtodos)
+}
+
+// One property line, unfolded, with its parameters
+#[derive(Debug)]
+struct Property {
+    name: String,
+    params: Vec<(String, String)>,
+    value: String,
+}
+
+fn parse_property(line: &str, number: usize) -> Result<Property> {
+    let parse_error = || TodoerError::Parse {
+        line: number,
+        message: format!("expected NAME:value but got {}", line),
+    };
+    // The value starts at the first colon outside a quoted parameter value
+    let mut quoted = false;
+    let colon = line
+        .char_indices()
+        .find(|(_, c)| {
+            if *c == '"' {
+                quoted = !quoted;
+            }
+            *c == ':' && !quoted
+        })
+        .map(|(colon, _)| colon)
+        .ok_or_else(parse_error)?;
+    let mut parts = line[..colon].split(';');
+    let name = parts
+        .next()
+        .filter(|name| !name.is_empty())
+        .ok_or_else(parse_error)?;
+    let params = parts
+        .filter_map(|param| {
+            let (key, value) = param.split_once('=')?;
+            Some((key.to_uppercase(), value.trim_matches('"').to_string()))
+        })
+        .collect();
+    Ok(Property {
+        name: name.to_uppercase(),
+        params,
+        value: line[colon + 1..].to_string(),
+    })
+}
+
+// Times in UTC become local ones, floating times are taken as local already
+fn parse_time(value: &str) -> Option<NaiveDateTime> {
+    if let Ok(time) = NaiveDateTime::parse_from_str(value, UTC_TIME) {
+        return Some(
+            Utc.from_utc_datetime(&time)
+                .with_timezone(&Local)
+                .naive_local(),
+        );
+    }
+    NaiveDateTime::parse_from_str(value, LOCAL_TIME).ok()
+}
+
+// A VTODO read back, before it becomes a todo
+#[derive(Debug, Default)]
+pub struct VTodo {
+    uid: Option<String>,
+    quick: QuickAdd,
+    status: Option<Status>,
+    created: Option<NaiveDateTime>,
+    completed: Option<NaiveDateTime>,
+    depends_on: Vec<String>,
+}
+
+impl VTodo {
+    fn set(&mut self, property: Property) {
+        let value = property.value.as_str();
+        match property.name.as_str() {
+            "UID" => self.uid = Some(unescape(value)),
+            "SUMMARY" => {
+                self.quick.name = unescape(value)
+                    .split_whitespace()
+                    .collect::<Vec<_>>()
+                    .join(" ")
+            }
+            // Ours wins over the standard one it was written next to
+            "STATUS" if self.status.is_none() => {
+                self.status = match value {
+                    "NEEDS-ACTION" => Some(Status::Open),
+                    "IN-PROCESS" => Some(Status::InProgress),
+                    "COMPLETED" => Some(Status::Done),
+                    "CANCELLED" => Some(Status::Cancelled),
+                    _ => None,
+                }
+            }
+            STATUS_PROPERTY => self.status = value.parse().ok().or(self.status),
+            "PRIORITY" => {
+                self.quick.priority = match value.parse::<u8>() {
+                    Ok(1..=4) => Some(Priority::High),
+                    Ok(5) => Some(Priority::Medium),
+                    Ok(6..=9) => Some(Priority::Low),
+                    _ => None,
+                }
+            }
+            "CATEGORIES" => {
+                for category in value.split(',').map(unescape) {
+                    let category: String =
+                        category.split_whitespace().collect::<Vec<_>>().join("-");
+                    match category.strip_prefix('@') {
+                        Some(context) if !context.is_empty() => {
+                            self.quick.contexts.push(context.to_string())
+                        }
+                        Some(_) => {}
+                        None if !category.is_empty() => self.quick.tags.push(category),
+                        None => {}
+                    }
+                }
+            }
+            "DUE" => {
+                if let Ok(day) = NaiveDate::parse_from_str(value, DATE) {
+                    self.quick.due = Some(day);
+                } else if let Some(time) = parse_time(value) {
+                    self.quick.due = Some(time.date());
+                    self.quick.time = Some(time.time());
+                }
+            }
+            ESTIMATE_PROPERTY => self.quick.estimate = parse_estimate(value),
+            "CREATED" => self.created = parse_time(value),
+            "COMPLETED" => self.completed = parse_time(value),
+            "RELATED-TO" => {
+                let depends = property.params.iter().any(|(key, value)| {
+                    key == "RELTYPE" && value.eq_ignore_ascii_case("DEPENDS-ON")
+                });
+                if depends {
+                    self.depends_on.push(id_from_uid(&unescape(value)));
+                }
+            }
+            _ => {}
+        }
+    }
+
+    // The ID the todo keeps its UID as. IDs are single words in the day file
+    pub fn id(&self) -> Option<String> {
+        self.uid
+            .as_deref()
+            .map(id_from_uid)
+            .filter(|id| !id.is_empty() && !id.contains(char::is_whitespace))
+    }
+
+    // Without a summary or anything else to name a todo by
+    pub fn is_empty(&self) -> bool {
+        self.quick.to_string().is_empty()
+    }
+
+    // Writes everything the VTODO says over the todo at `index`
+    pub fn apply(self, todoer: &mut Todoer, index: u32) -> Result<()> {
+        let id = self.id();
+        let name = self.quick.to_string();
+        todoer.set_status(index, self.status.unwrap_or_default())?;
+        let todo = todoer
+            .data
+            .0
+            .get_mut(&index)
+            .ok_or(TodoerError::NotFound(index))?;
+        if !name.is_empty() {
+            todo.name = name;
+        }
+        todo.created = self.created.or(todo.created);
+        if todo.is_done() {
+            todo.completed = self.completed.or(todo.completed);
+        }
+        todo.id = id.or(todo.id.take());
+        todo.blocked_by = self.depends_on;
+        Ok(())
+    }
+
+    // Adds it last on the list and returns its index
+    pub fn add_to(self, todoer: &mut Todoer) -> Result<u32> {
+        todoer.set_value(self.quick.to_string());
+        let index = todoer.size - 1;
+        self.apply(todoer, index)?;
+        Ok(index)
+    }
+}
+
+// Our own UIDs go back to the ID they were made from, others are kept whole
+fn id_from_uid(uid: &str) -> String {
+    uid.strip_suffix(UID_DOMAIN).unwrap_or(uid).to_string()
+}
+
+// Every VTODO in `contents`, other components are left out
+pub fn parse(contents: &str) -> Result<Vec<VTodo>> {
+    // Unfolded lines with the number of the line they start on
+    let mut lines: Vec<(usize, String)> = Vec::new();
+    for (number, line) in contents.lines().enumerate() {
+        let line = line.trim_end_matches('\r');
+        match (line.strip_prefix([' ', '\t']), lines.last_mut()) {
+            (Some(rest), Some((_, last))) => *last += rest,
+            _ if line.is_empty() => {}
+            _ => lines.push((number + 1, line.to_string())),
+        }
+    }
+    let mut entries = Vec::new();
+    let mut entry: Option<VTodo> = None;
+    let mut nested = 0;
+    for (number, line) in lines {
+        let property = parse_property(&line, number)?;
+        let (name, value) = (property.name.clone(), property.value.to_uppercase());
+        match (name.as_str(), value.as_str(), &mut entry) {
+            ("BEGIN", "VTODO", None) => entry = Some(VTodo::default()),
+            ("BEGIN", "VTODO", Some(_)) => {
+                return Err(TodoerError::Parse {
+                    line: number,
+                    message: String::from("a VTODO can't be inside another"),
+                })
+            }
+            ("END", "VTODO", Some(_)) => entries.extend(entry.take()),
+            // Such as the alarms of a todo, whose properties aren't the todo's
+            ("BEGIN", _, Some(_)) => nested += 1,
+            ("END", _, Some(_)) if nested > 0 => nested -= 1,
+            (_, _, Some(entry)) if nested == 0 => entry.set(property),
+            _ => {}
+        }
+    }
+    if entry.is_some() {
+        return Err(TodoerError::Parse {
+            line: contents.lines().count(),
+            message: String::from("the last VTODO has no END:VTODO"),
+        });
+    }
+    Ok(entries)
+}
+
+// Adds a todo for every VTODO in `contents` whose UID no list has as an ID yet, nor goes by as a
+// `stable_id`, so importing the same file again adds nothing. Returns how many were added and how many were already there
+pub fn import(
+    todoer: &mut Todoer,
+    contents: &str,
+    dependencies: &mut Dependencies,
+) -> Result<(u32, u32)> {
+    let (mut added, mut skipped) = (0, 0);
+    for vtodo in parse(contents)? {
+        if vtodo.id().is_some_and(|id| dependencies.knows(&id)) {
+            skipped += 1;
+            continue;
+        }
+        if vtodo.is_empty() {
+            continue;
+        }
+        vtodo.add_to(todoer)?;
+        dependencies.update(todoer);
+        added += 1;
+    }
+    Ok((added, skipped))
+}
+
+#[cfg(test)]
+mod test {
+    use std::path::PathBuf;
+
+    use chrono::{NaiveDate, TimeZone, Utc};
+
+    use super::{export, fold, import, unescape};
+    use crate::dependencies::{stable_id, Dependencies};
+    use crate::todoer::{Status, Todoer};
+
+    #[test]
+    fn export_and_import() {
+        let mut todoer = Todoer::default_todoer(PathBuf::from("wiki/todo/2022-11-25.md"));
+        todoer.set_value(String::from(
+            "call vendor, then email !high #ops @phone due:2022-11-26 at:15:00 est:30m",
+        ));
+        todoer.set_value(String::from("ship release due:2022-12-01"));
+        todoer.set_value(String::from("write notes"));
+        todoer.set_status(1, Status::Blocked).unwrap();
+        todoer.mark_done(2).unwrap();
+        let mut todoers = vec![todoer];
+        let mut dependencies = Dependencies::new(&todoers);
+        let stamp = Utc.from_utc_datetime(
+            &NaiveDate::from_ymd_opt(2022, 11, 25)
+                .unwrap()
+                .and_hms_opt(12, 0, 0)
+                .unwrap(),
+        );
+        let ics = export(&todoers, stamp);
+        // Without giving the todos IDs, and the same again
+        assert!(todoers[0].data.0.values().all(|todo| todo.id.is_none()));
+        assert_eq!(export(&todoers, stamp), ics);
+        let id = stable_id(&todoers[0], &todoers[0].data.0[&0]);
+
+        assert!(ics.starts_with("BEGIN:VCALENDAR\r\nVERSION:2.0\r\n"));
+        assert!(ics.ends_with("END:VCALENDAR\r\n"));
+        assert!(ics.lines().all(|line| line.len() <= 76));
+        assert_eq!(ics.matches("BEGIN:VTODO").count(), 3);
+        for line in [
+            format!("UID:{}@todoer", id),
+            String::from("DTSTAMP:20221125T120000Z"),
+            String::from("SUMMARY:call vendor\\, then email"),
+            String::from("PRIORITY:1"),
+            String::from("CATEGORIES:ops,@phone"),
+            String::from("DUE:20221126T150000"),
+            String::from("X-TODOER-ESTIMATE:30m"),
+            String::from("STATUS:NEEDS-ACTION\r\nX-TODOER-STATUS:blocked"),
+            String::from("DUE;VALUE=DATE:20221201"),
+            String::from("STATUS:COMPLETED"),
+        ] {
+            assert!(ics.contains(&format!("{}\r\n", line)), "{}\n{}", line, ics);
+        }
+
+        // Into another list, which comes out the same
+        let mut other = Todoer::default_todoer(PathBuf::from("wiki/todo/2022-11-26.md"));
+        let mut empty = Dependencies::default();
+        assert_eq!(import(&mut other, &ics, &mut empty).unwrap(), (3, 0));
+        for index in 0..3 {
+            let (a, b) = (&todoers[0].data.0[&index], &other.data.0[&index]);
+            assert_eq!(a.name, b.name);
+            assert_eq!(a.status, b.status);
+            assert_eq!(Some(stable_id(&todoers[0], a)), b.id);
+            assert_eq!(a.created, b.created);
+            assert_eq!(a.completed, b.completed);
+        }
+        assert_eq!(other.done_count, 1);
+
+        // Again, and into the list it came from, without duplicates
+        assert_eq!(import(&mut other, &ics, &mut empty).unwrap(), (0, 3));
+        assert_eq!(
+            import(&mut todoers[0], &ics, &mut dependencies).unwrap(),
+            (0, 3)
+        );
+        assert_eq!(todoers[0].size, 3);
+    }
+
+    #[test]
+    fn foreign_calendars() {
+        let ics = "BEGIN:VCALENDAR\r\n\
+                   PRODID:-//Example//EN\r\n\
+                   BEGIN:VTODO\r\n\
+                   UID:0f8fad5b-d9cb-469f-a165-70867728950e\r\n\
+                   SUMMARY:Renew pass\r\n port\r\n\
+                   STATUS:IN-PROCESS\r\n\
+                   PRIORITY:7\r\n\
+                   CATEGORIES:Home Office,@car\r\n\
+                   DUE;VALUE=DATE:20221130\r\n\
+                   BEGIN:VALARM\r\n\
+                   SUMMARY:Reminder\r\n\
+                   END:VALARM\r\n\
+                   END:VTODO\r\n\
+                   BEGIN:VEVENT\r\n\
+                   SUMMARY:not a todo\r\n\
+                   END:VEVENT\r\n\
+                   END:VCALENDAR\r\n";
+        let mut todoer = Todoer::default_todoer(PathBuf::from("wiki/todo/2022-11-25.md"));
+        let mut dependencies = Dependencies::default();
+        assert_eq!(import(&mut todoer, ics, &mut dependencies).unwrap(), (1, 0));
+        let todo = &todoer.data.0[&0];
+        assert_eq!(
+            todo.name,
+            "Renew passport !low #Home-Office @car due:2022-11-30"
+        );
+        assert_eq!(todo.status, Status::InProgress);
+        assert_eq!(todo.due(), NaiveDate::from_ymd_opt(2022, 11, 30));
+        assert_eq!(
+            todo.id.as_deref(),
+            Some("0f8fad5b-d9cb-469f-a165-70867728950e")
+        );
+        // Given back as it came, rather than as one of ours
+        let ics = export(std::slice::from_ref(&todoer), Utc::now());
+        assert!(ics.contains("UID:0f8fad5b-d9cb-469f-a165-70867728950e\r\n"));
+
+        assert!(import(
+            &mut todoer,
+            "BEGIN:VTODO\r\nSUMMARY:x\r\n",
+            &mut dependencies
+        )
+        .is_err());
+        assert!(import(
+            &mut todoer,
+            "BEGIN:VTODO\r\nnonsense\r\n",
+            &mut dependencies
+        )
+        .is_err());
+        assert_eq!(unescape("a\\, b\\;c\\\\d\\ne"), "a, b;c\\d\ne");
+        let long = "x".repeat(200);
+        assert!(fold(&long).lines().all(|line| line.len() <= 75));
+    }
+}
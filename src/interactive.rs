@@ -0,0 +1,431 @@
+use std::path::{Path, PathBuf};
+
+use chrono::NaiveDateTime;
+
+use crate::dependencies::Dependencies;
+use crate::error::Result;
+use crate::pomodoro::{pomodoro_file, Phase, Pomodoro, PomodoroSettings};
+use crate::timer::{self, timer_file, Timer};
+use crate::todoer::{SortKey, Status, Todoer};
+use crate::todoers::Todoers;
+
+// Which file a session starts on. Named lists are opened by name like any other day
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Mode {
+    Today,
+    Day(String),
+}
+
+// Everything a user can do to a session, independent of how it was asked for
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Action {
+    CursorUp,
+    CursorDown,
+    CursorTop,
+    CursorBottom,
+    ToggleMark,
+    Add(String),
+    Edit(String),
+    Done,
+    Undone,
+    Delete,
+    MoveUp,
+    MoveDown,
+    Tag(String),
+    Sort(SortKey),
+    SetStatus(Status),
+    OpenDay(usize),
+    // Starts timing the todo under the cursor, or stops the timer if it is already running on it
+    ToggleTimer,
+    // Focus mode on the todo under the cursor, see `pomodoro`
+    StartFocus(NaiveDateTime),
+    StopFocus,
+    // Marks the focused todo done and leaves focus mode
+    FinishFocus,
+}
+
+#[derive(Debug)]
+pub struct Day {
+    pub name: String,
+    pub done_count: u32,
+    pub size: u32,
+}
+
+#[derive(Debug)]
+pub struct Session {
+    pub todoer: Todoer,
+    pub days: Vec<Day>,
+    pub day_cursor: usize,
+    pub cursor: u32,
+    pub marked: Vec<u32>,
+    pub timer: Option<Timer>,
+    pub pomodoro: Option<Pomodoro>,
+    // For every day, so todos waiting on todos from other days are shown as such
+    pub dependencies: Dependencies,
+    pomodoro_settings: PomodoroSettings,
+    today: PathBuf,
+}
+
+pub fn day_name(config: &Path) -> String {
+    config
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().into_owned())
+        .unwrap_or_default()
+}
+
+impl Session {
+    // `today` is the path of today's day file, every other day is looked up next to it
+    pub fn new(today: PathBuf, mode: Mode) -> Result<Self> {
+        let todoer = Todoer::from_config(today.clone(), false)?;
+        let mut session = Session {
+            todoer,
+            days: Vec::new(),
+            day_cursor: 0,
+            cursor: 0,
+            marked: Vec::new(),
+            timer: None,
+            pomodoro: None,
+            dependencies: Dependencies::default(),
+            pomodoro_settings: PomodoroSettings::default(),
+            today,
+        };
+        let todo_dir = session.todo_dir();
+        session.timer = Timer::load(&timer_file(&todo_dir))?;
+        session.pomodoro_settings = PomodoroSettings::load(&pomodoro_file(&todo_dir))?;
+        session.load_days()?;
+
+        if let Mode::Day(name) = mode {
+            match session.days.iter().position(|day| day.name == name) {
+                Some(index) => session.open_day(index)?,
+                None => session.open_file(name)?,
+            }
+        }
+        session.dependencies = Dependencies::load(&session.todo_dir(), &session.todoer)?;
+        Ok(session)
+    }
+
+    // Newest day first, with today listed even before it has been saved
+    fn load_days(&mut self) -> Result<()> {
+        let todo_dir = self.todo_dir();
+        let todoers = if std::fs::metadata(&todo_dir).is_ok() {
+            Todoers::from_todos_dir(todo_dir)?.todoers
+        } else {
+            Vec::new()
+        };
+
+        let mut days: Vec<Day> = todoers
+            .iter()
+            .filter(|todoer| todoer.config != self.today)
+            .map(|todoer| Day {
+                name: day_name(&todoer.config),
+                done_count: todoer.done_count,
+                size: todoer.size,
+            })
+            .collect();
+        days.push(Day {
+            name: day_name(&self.today),
+            done_count: 0,
+            size: 0,
+        });
+        days.sort_by(|a, b| b.name.cmp(&a.name));
+
+        let current = day_name(&self.todoer.config);
+        self.day_cursor = days.iter().position(|day| day.name == current).unwrap_or(0);
+        self.days = days;
+        self.refresh_day();
+        Ok(())
+    }
+
+    fn refresh_day(&mut self) {
+        if let Some(day) = self.days.get_mut(self.day_cursor) {
+            day.done_count = self.todoer.done_count;
+            day.size = self.todoer.size;
+        }
+    }
+
+    fn open_file(&mut self, name: String) -> Result<()> {
+        let config = self.today.with_file_name(format!("{}.md", name));
+        let is_past = config != self.today;
+        self.todoer = Todoer::from_config(config, is_past)?;
+        self.cursor = 0;
+        self.marked.clear();
+        self.dependencies.update(&self.todoer);
+        Ok(())
+    }
+
+    fn open_day(&mut self, index: usize) -> Result<()> {
+        let Some(day) = self.days.get(index) else {
+            return Ok(());
+        };
+        self.open_file(day.name.clone())?;
+        self.day_cursor = index;
+        Ok(())
+    }
+
+    pub fn todo_dir(&self) -> PathBuf {
+        self.today.parent().map(PathBuf::from).unwrap_or_default()
+    }
+
+    // Rereads the shown day after something else wrote to it
+    fn reload(&mut self) -> Result<()> {
+        self.todoer = Todoer::from_config(self.todoer.config.clone(), true)?;
+        self.clamp_cursor();
+        self.refresh_day();
+        self.dependencies.update(&self.todoer);
+        Ok(())
+    }
+
+    // Stopping a timer writes the time to its day file, which may be the one shown
+    fn toggle_timer(&mut self) -> Result<()> {
+        let path = timer_file(&self.todo_dir());
+        let running = self
+            .timer
+            .as_ref()
+            .is_some_and(|timer| timer.is_on(&self.todoer, self.cursor));
+        if running {
+            timer::stop(&path)?;
+        } else {
+            timer::start(&self.todoer, self.cursor, &path)?;
+        }
+        self.reload()?;
+        self.timer = Timer::load(&path)?;
+        Ok(())
+    }
+
+    // Moves focus mode on to its next period, logging finished work periods. Returns what
+    // changed for the status bar
+    pub fn tick(&mut self, now: NaiveDateTime) -> Result<Option<String>> {
+        let Some(pomodoro) = &mut self.pomodoro else {
+            return Ok(None);
+        };
+        let Some(ended) = pomodoro.tick(now) else {
+            return Ok(None);
+        };
+        let message = match ended {
+            Phase::Work => {
+                pomodoro.log()?;
+                let message = format!("pomodoro {} done, take a break", pomodoro.finished);
+                self.reload()?;
+                message
+            }
+            Phase::ShortBreak | Phase::LongBreak => String::from("break over, back to work"),
+        };
+        Ok(Some(message))
+    }
+
+    pub fn is_today(&self) -> bool {
+        self.todoer.config == self.today
+    }
+
+    // The marked todos, or the one under the cursor when nothing is marked
+    pub fn targets(&self) -> Vec<u32> {
+        if !self.marked.is_empty() {
+            let mut marked = self.marked.clone();
+            marked.sort_unstable();
+            return marked;
+        }
+        if self.todoer.size == 0 {
+            return Vec::new();
+        }
+        vec![self.cursor]
+    }
+
+    fn clamp_cursor(&mut self) {
+        self.cursor = self.cursor.min(self.todoer.size.saturating_sub(1));
+    }
+
+    fn save(&mut self) -> Result<()> {
+        self.todoer.save_and_commit()?;
+        self.refresh_day();
+        self.dependencies.update(&self.todoer);
+        Ok(())
+    }
+
+    // Moves the targets one step up or down as a block, keeping the cursor and marks on them
+    fn shift(&mut self, down: bool) -> Result<()> {
+        let targets = self.targets();
+        let Some(first) = targets.first().copied() else {
+            return Ok(());
+        };
+        let to = if down {
+            (first + 1).min(self.todoer.size - u32::try_from(targets.len()).unwrap())
+        } else {
+            first.saturating_sub(1)
+        };
+        self.todoer.move_values(&targets, to)?;
+
+        let moved: Vec<u32> = (to..).take(targets.len()).collect();
+        if let Some(position) = targets.iter().position(|index| *index == self.cursor) {
+            self.cursor = moved[position];
+        }
+        if !self.marked.is_empty() {
+            self.marked = moved;
+        }
+        self.save()
+    }
+
+    pub fn apply(&mut self, action: Action) -> Result<()> {
+        match action {
+            Action::CursorUp => self.cursor = self.cursor.saturating_sub(1),
+            Action::CursorDown => {
+                self.cursor = (self.cursor + 1).min(self.todoer.size.saturating_sub(1))
+            }
+            Action::CursorTop => self.cursor = 0,
+            Action::CursorBottom => self.cursor = self.todoer.size.saturating_sub(1),
+            Action::ToggleMark => {
+                if self.todoer.size == 0 {
+                    return Ok(());
+                }
+                match self.marked.iter().position(|index| *index == self.cursor) {
+                    Some(position) => {
+                        self.marked.remove(position);
+                    }
+                    None => self.marked.push(self.cursor),
+                }
+            }
+            Action::Add(name) => {
+                if name.trim().is_empty() {
+                    return Ok(());
+                }
+                self.todoer.set_value(name);
+                self.cursor = self.todoer.size - 1;
+                self.save()?;
+            }
+            Action::Edit(name) => {
+                self.todoer.edit_value(self.cursor, name)?;
+                self.save()?;
+            }
+            Action::Done => {
+                let targets = self.targets();
+                self.todoer.mark_done_all(&targets)?;
+                self.marked.clear();
+                self.save()?;
+            }
+            Action::Undone => {
+                for index in self.targets() {
+                    if self.todoer.data.0[&index].is_done() {
+                        self.todoer.mark_undone(index)?;
+                    }
+                }
+                self.marked.clear();
+                self.save()?;
+            }
+            Action::Delete => {
+                let targets = self.targets();
+                self.todoer.remove_values(&targets)?;
+                self.marked.clear();
+                self.clamp_cursor();
+                self.save()?;
+            }
+            Action::MoveUp => self.shift(false)?,
+            Action::MoveDown => self.shift(true)?,
+            Action::Tag(tag) => {
+                let targets = self.targets();
+                self.todoer.tag_values(tag.trim(), &targets)?;
+                self.save()?;
+            }
+            Action::Sort(by) => {
+                self.todoer.sort_values(by)?;
+                self.marked.clear();
+                self.save()?;
+            }
+            Action::SetStatus(status) => {
+                let targets = self.targets();
+                self.todoer.set_status_all(&targets, status)?;
+                self.marked.clear();
+                self.save()?;
+            }
+            Action::OpenDay(index) => self.open_day(index)?,
+            Action::ToggleTimer => self.toggle_timer()?,
+            Action::StartFocus(now) => {
+                let pomodoro =
+                    Pomodoro::start(&self.todoer, self.cursor, self.pomodoro_settings, now)?;
+                self.pomodoro = Some(pomodoro);
+            }
+            Action::StopFocus => self.pomodoro = None,
+            Action::FinishFocus => {
+                if let Some(pomodoro) = self.pomodoro.take() {
+                    pomodoro.finish()?;
+                    self.reload()?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Action, Mode, Session};
+    use crate::testing::todo_dir;
+    use crate::todoer::SortKey;
+
+    #[test]
+    fn add_done_and_delete() {
+        let today = todo_dir("interactive", "add").join("2022-11-25.md");
+        let mut session = Session::new(today.clone(), Mode::Today).unwrap();
+        session.apply(Action::Add(String::from("foo"))).unwrap();
+        session.apply(Action::Add(String::from("bar"))).unwrap();
+        session.apply(Action::CursorTop).unwrap();
+        session.apply(Action::Done).unwrap();
+
+        let contents = std::fs::read_to_string(&today).unwrap();
+        let lines: Vec<&str> = contents.lines().collect();
+        assert_eq!(lines[..2], ["2022-11-25", "1/2"]);
+        assert!(lines[2].starts_with("- [x] foo created:"));
+        assert!(lines[2].contains(" done:"));
+        assert!(lines[3].starts_with("- [ ] bar created:"));
+
+        session.apply(Action::Delete).unwrap();
+        assert_eq!(session.todoer.get_value_names(), vec!["bar"]);
+        assert_eq!(session.days[0].size, 1);
+    }
+
+    #[test]
+    fn marked_todos_move_together() {
+        let today = todo_dir("interactive", "move").join("2022-11-25.md");
+        let mut session = Session::new(today, Mode::Today).unwrap();
+        for name in ["foo", "bar", "baz", "fam"] {
+            session.apply(Action::Add(String::from(name))).unwrap();
+        }
+        session.apply(Action::CursorTop).unwrap();
+        session.apply(Action::ToggleMark).unwrap();
+        session.apply(Action::CursorDown).unwrap();
+        session.apply(Action::CursorDown).unwrap();
+        session.apply(Action::ToggleMark).unwrap();
+        session.apply(Action::MoveDown).unwrap();
+
+        assert_eq!(
+            session.todoer.get_value_names(),
+            vec!["bar", "foo", "baz", "fam"]
+        );
+        assert_eq!(session.marked, vec![1, 2]);
+        assert_eq!(session.cursor, 2);
+
+        session.apply(Action::Sort(SortKey::Alpha)).unwrap();
+        assert_eq!(
+            session.todoer.get_value_names(),
+            vec!["bar", "baz", "fam", "foo"]
+        );
+    }
+
+    #[test]
+    fn open_past_day() {
+        let today = todo_dir("interactive", "past").join("2022-11-25.md");
+        std::fs::write(
+            today.with_file_name("2022-11-24.md"),
+            "2022-11-24\n1/2\n- [x] foo\n- [ ] bar\n",
+        )
+        .unwrap();
+
+        let session = Session::new(today.clone(), Mode::Day(String::from("2022-11-24"))).unwrap();
+        assert!(!session.is_today());
+        assert_eq!(session.day_cursor, 1);
+        assert_eq!(session.todoer.get_value_names(), vec!["foo", "bar"]);
+
+        let mut session = Session::new(today, Mode::Today).unwrap();
+        assert_eq!(session.days.len(), 2);
+        session.apply(Action::OpenDay(1)).unwrap();
+        assert_eq!(session.todoer.done_count, 1);
+    }
+}
@@ -1,4 +1,26 @@
+pub mod agenda;
+pub mod api;
+pub mod auth;
+pub mod caldav;
+pub mod calendar;
+pub mod client;
 pub mod config;
+pub mod dependencies;
+pub mod error;
+pub mod events;
+pub mod ics;
+pub mod interactive;
+pub mod metrics;
 pub mod opts;
+pub mod pomodoro;
+pub mod quick_add;
+pub mod remote;
+pub mod server;
+pub mod settings;
+pub mod sync;
+#[cfg(test)]
+mod testing;
+pub mod timer;
 pub mod todoer;
 pub mod todoers;
+pub mod tui;
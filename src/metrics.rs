@@ -0,0 +1,154 @@
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::Duration;
+
+// Upper bounds of the request latency buckets, in seconds
+const BUCKETS: [f64; 10] = [0.001, 0.0025, 0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0];
+
+#[derive(Debug, Default)]
+struct Histogram {
+    buckets: [u64; BUCKETS.len()],
+    sum: f64,
+    count: u64,
+}
+
+// Counters kept by the server and rendered in the Prometheus text format on /metrics
+#[derive(Debug, Default)]
+pub struct Metrics {
+    // Keyed by method, route and status
+    requests: Mutex<BTreeMap<(String, String, u16), u64>>,
+    // Keyed by method and route
+    latencies: Mutex<BTreeMap<(String, String), Histogram>>,
+    save_failures: AtomicU64,
+}
+
+fn escape(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+impl Metrics {
+    pub fn record_request(&self, method: &str, route: &str, status: u16, elapsed: Duration) {
+        *self
+            .requests
+            .lock()
+            .unwrap()
+            .entry((method.to_string(), route.to_string(), status))
+            .or_default() += 1;
+
+        let seconds = elapsed.as_secs_f64();
+        let mut latencies = self.latencies.lock().unwrap();
+        let histogram = latencies
+            .entry((method.to_string(), route.to_string()))
+            .or_default();
+        for (bucket, le) in histogram.buckets.iter_mut().zip(BUCKETS) {
+            if seconds <= le {
+                *bucket += 1;
+            }
+        }
+        histogram.sum += seconds;
+        histogram.count += 1;
+    }
+
+    pub fn record_save_failure(&self) {
+        self.save_failures.fetch_add(1, Ordering::Relaxed);
+    }
+
+    // `open` and `done` are the todos in every list the server currently holds
+    pub fn render(&self, open: u32, done: u32) -> String {
+        let mut out = String::new();
+
+        out += "# HELP todoer_http_requests_total Requests handled, by method, route and status.\n";
+        out += "# TYPE todoer_http_requests_total counter\n";
+        for ((method, route, status), count) in self.requests.lock().unwrap().iter() {
+            let _ = writeln!(
+                out,
+                "todoer_http_requests_total{{method=\"{}\",route=\"{}\",status=\"{}\"}} {}",
+                escape(method),
+                escape(route),
+                status,
+                count
+            );
+        }
+
+        out += "# HELP todoer_http_request_duration_seconds Time taken to handle requests.\n";
+        out += "# TYPE todoer_http_request_duration_seconds histogram\n";
+        for ((method, route), histogram) in self.latencies.lock().unwrap().iter() {
+            let labels = format!("method=\"{}\",route=\"{}\"", escape(method), escape(route));
+            for (count, le) in histogram.buckets.iter().zip(BUCKETS) {
+                let _ = writeln!(
+                    out,
+                    "todoer_http_request_duration_seconds_bucket{{{},le=\"{}\"}} {}",
+                    labels, le, count
+                );
+            }
+            let _ = writeln!(
+                out,
+                "todoer_http_request_duration_seconds_bucket{{{},le=\"+Inf\"}} {}",
+                labels, histogram.count
+            );
+            let _ = writeln!(
+                out,
+                "todoer_http_request_duration_seconds_sum{{{}}} {}",
+                labels, histogram.sum
+            );
+            let _ = writeln!(
+                out,
+                "todoer_http_request_duration_seconds_count{{{}}} {}",
+                labels, histogram.count
+            );
+        }
+
+        out += "# HELP todoer_todos_open Todos not done yet.\n";
+        out += "# TYPE todoer_todos_open gauge\n";
+        let _ = writeln!(out, "todoer_todos_open {}", open);
+        out += "# HELP todoer_todos_done Todos done.\n";
+        out += "# TYPE todoer_todos_done gauge\n";
+        let _ = writeln!(out, "todoer_todos_done {}", done);
+        out += "# HELP todoer_save_failures_total Lists that could not be written to disk.\n";
+        out += "# TYPE todoer_save_failures_total counter\n";
+        let _ = writeln!(
+            out,
+            "todoer_save_failures_total {}",
+            self.save_failures.load(Ordering::Relaxed)
+        );
+        out
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::Duration;
+
+    use super::Metrics;
+
+    #[test]
+    fn render() {
+        let metrics = Metrics::default();
+        metrics.record_request("GET", "/api/todos", 200, Duration::from_millis(3));
+        metrics.record_request("GET", "/api/todos", 200, Duration::from_millis(30));
+        metrics.record_request("POST", "/add", 401, Duration::from_millis(1));
+        metrics.record_save_failure();
+
+        let out = metrics.render(2, 1);
+        assert!(out.contains(
+            "todoer_http_requests_total{method=\"GET\",route=\"/api/todos\",status=\"200\"} 2\n"
+        ));
+        assert!(out.contains(
+            "todoer_http_requests_total{method=\"POST\",route=\"/add\",status=\"401\"} 1\n"
+        ));
+        assert!(out.contains(
+            "todoer_http_request_duration_seconds_bucket{method=\"GET\",route=\"/api/todos\",le=\"0.005\"} 1\n"
+        ));
+        assert!(out.contains(
+            "todoer_http_request_duration_seconds_bucket{method=\"GET\",route=\"/api/todos\",le=\"+Inf\"} 2\n"
+        ));
+        assert!(out.contains("todoer_todos_open 2\n"));
+        assert!(out.contains("todoer_todos_done 1\n"));
+        assert!(out.contains("todoer_save_failures_total 1\n"));
+    }
+}
@@ -3,12 +3,21 @@ use std::path::PathBuf;
 use clap::Parser;
 
 #[derive(Parser, Debug)]
-#[clap()]
+#[clap(trailing_var_arg = true)]
 pub struct Opts {
     pub args: Vec<String>,
 
     #[clap(short = 'c', long = "config")]
     pub config: Option<PathBuf>,
 
+    // Taken from the end of `args` when it names a day file, see `Config`
+    #[clap(skip)]
     pub filename: Option<String>,
+
+    // Sends every operation to a todoer server instead of writing files
+    #[clap(long = "remote")]
+    pub remote: Option<String>,
+
+    #[clap(long = "token")]
+    pub token: Option<String>,
 }
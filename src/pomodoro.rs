@@ -0,0 +1,243 @@
+use std::path::{Path, PathBuf};
+
+use chrono::{Duration, NaiveDateTime};
+use serde::{Deserialize, Serialize};
+
+use crate::error::{Result, TodoerError};
+use crate::todoer::Todoer;
+
+// Lengths of focus mode periods in minutes, read from `pomodoro.json` next to the todo directory
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct PomodoroSettings {
+    pub work: u32,
+    pub short_break: u32,
+    pub long_break: u32,
+    // Every this many pomodoros the break is a long one
+    pub long_break_every: u32,
+}
+
+impl Default for PomodoroSettings {
+    fn default() -> Self {
+        PomodoroSettings {
+            work: 25,
+            short_break: 5,
+            long_break: 15,
+            long_break_every: 4,
+        }
+    }
+}
+
+pub fn pomodoro_file(todo_dir: &Path) -> PathBuf {
+    todo_dir.with_file_name("pomodoro.json")
+}
+
+impl PomodoroSettings {
+    // The defaults when there is no file
+    pub fn load(path: &Path) -> Result<Self> {
+        if std::fs::metadata(path).is_err() {
+            return Ok(PomodoroSettings::default());
+        }
+        let contents = std::fs::read_to_string(path)?;
+        let settings: PomodoroSettings =
+            serde_json::from_str(&contents).map_err(|err| TodoerError::Parse {
+                line: err.line(),
+                message: err.to_string(),
+            })?;
+        if settings.work == 0 || settings.long_break_every == 0 {
+            return Err(TodoerError::Config(String::from(
+                "work and long_break_every must be at least 1",
+            )));
+        }
+        Ok(settings)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Phase {
+    Work,
+    ShortBreak,
+    LongBreak,
+}
+
+// Focus on one todo, alternating work periods and breaks until stopped
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Pomodoro {
+    // The day file of the todo, which is found again by name as for timers
+    pub config: PathBuf,
+    pub name: String,
+    pub phase: Phase,
+    pub ends: NaiveDateTime,
+    // Work periods finished since focus mode started
+    pub finished: u32,
+    settings: PomodoroSettings,
+}
+
+fn minutes(minutes: u32) -> Duration {
+    Duration::minutes(minutes.into())
+}
+
+impl Pomodoro {
+    pub fn start(
+        todoer: &Todoer,
+        index: u32,
+        settings: PomodoroSettings,
+        now: NaiveDateTime,
+    ) -> Result<Self> {
+        let todo = todoer
+            .data
+            .0
+            .get(&index)
+            .ok_or(TodoerError::NotFound(index))?;
+        if todo.status.is_closed() {
+            return Err(TodoerError::Conflict(format!(
+                "todo {} is already {}",
+                index, todo.status
+            )));
+        }
+        Ok(Pomodoro {
+            config: todoer.config.clone(),
+            name: todo.name.clone(),
+            phase: Phase::Work,
+            ends: now + minutes(settings.work),
+            finished: 0,
+            settings,
+        })
+    }
+
+    pub fn remaining(&self, now: NaiveDateTime) -> Duration {
+        (self.ends - now).max(Duration::zero())
+    }
+
+    // Moves on to the next period once the current one is over, returning the one that ended.
+    // Periods missed while nobody was looking are not caught up on, the next starts now
+    pub fn tick(&mut self, now: NaiveDateTime) -> Option<Phase> {
+        if now < self.ends {
+            return None;
+        }
+        let ended = self.phase;
+        let (phase, length) = match ended {
+            Phase::Work => {
+                self.finished += 1;
+                if self.finished.is_multiple_of(self.settings.long_break_every) {
+                    (Phase::LongBreak, self.settings.long_break)
+                } else {
+                    (Phase::ShortBreak, self.settings.short_break)
+                }
+            }
+            Phase::ShortBreak | Phase::LongBreak => (Phase::Work, self.settings.work),
+        };
+        self.phase = phase;
+        self.ends = now + minutes(length);
+        Some(ended)
+    }
+
+    // Counts a finished work period against the todo in its day file
+    pub fn log(&self) -> Result<()> {
+        let mut todoer = Todoer::from_config(self.config.clone(), true)?;
+        let index = todoer.find_value(&self.name).ok_or_else(|| {
+            TodoerError::Conflict(format!(
+                "{} was renamed or removed, the pomodoro was not recorded",
+                self.name
+            ))
+        })?;
+        if let Some(todo) = todoer.data.0.get_mut(&index) {
+            todo.pomodoros += 1;
+        }
+        todoer.save_and_commit()
+    }
+
+    // Ends focus mode by marking the todo done
+    pub fn finish(&self) -> Result<()> {
+        let mut todoer = Todoer::from_config(self.config.clone(), true)?;
+        let index = todoer.find_value(&self.name).ok_or_else(|| {
+            TodoerError::Conflict(format!("{} was renamed or removed", self.name))
+        })?;
+        todoer.mark_done(index)?;
+        todoer.save_and_commit()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::path::PathBuf;
+
+    use chrono::{Duration, NaiveDate, NaiveDateTime};
+
+    use super::{pomodoro_file, Phase, Pomodoro, PomodoroSettings};
+    use crate::testing::todo_dir;
+    use crate::todoer::Todoer;
+
+    fn at(hour: u32, minute: u32) -> NaiveDateTime {
+        NaiveDate::from_ymd_opt(2022, 11, 25)
+            .unwrap()
+            .and_hms_opt(hour, minute, 0)
+            .unwrap()
+    }
+
+    #[test]
+    fn settings() {
+        let todo_dir = todo_dir("pomodoro", "settings");
+        let path = pomodoro_file(&todo_dir);
+        assert_eq!(
+            PomodoroSettings::load(&path).unwrap(),
+            PomodoroSettings::default()
+        );
+        std::fs::write(&path, r#"{"work": 50, "short_break": 10}"#).unwrap();
+        let settings = PomodoroSettings::load(&path).unwrap();
+        assert_eq!((settings.work, settings.short_break), (50, 10));
+        assert_eq!(settings.long_break, 15);
+        std::fs::write(&path, r#"{"work": 0}"#).unwrap();
+        assert!(PomodoroSettings::load(&path).is_err());
+    }
+
+    #[test]
+    fn periods() {
+        let mut todoer = Todoer::default_todoer(PathBuf::from("wiki/todo/2022-11-25.md"));
+        todoer.set_value(String::from("write CLI"));
+        let settings = PomodoroSettings {
+            long_break_every: 2,
+            ..PomodoroSettings::default()
+        };
+        let mut pomodoro = Pomodoro::start(&todoer, 0, settings, at(9, 0)).unwrap();
+        assert_eq!(pomodoro.remaining(at(9, 10)), Duration::minutes(15));
+        assert_eq!(pomodoro.tick(at(9, 10)), None);
+
+        assert_eq!(pomodoro.tick(at(9, 25)), Some(Phase::Work));
+        assert_eq!(pomodoro.phase, Phase::ShortBreak);
+        assert_eq!(pomodoro.tick(at(9, 30)), Some(Phase::ShortBreak));
+        assert_eq!(pomodoro.tick(at(9, 55)), Some(Phase::Work));
+        assert_eq!(pomodoro.phase, Phase::LongBreak);
+        assert_eq!(pomodoro.ends, at(10, 10));
+        assert_eq!(pomodoro.finished, 2);
+
+        todoer.mark_done(0).unwrap();
+        assert!(Pomodoro::start(&todoer, 0, settings, at(9, 0)).is_err());
+    }
+
+    #[test]
+    fn log_and_finish() {
+        let todo_dir = todo_dir("pomodoro", "log");
+        let config = todo_dir.join("2022-11-25.md");
+        let mut todoer = Todoer::default_todoer(config.clone());
+        todoer.set_value(String::from("foo"));
+        todoer.set_value(String::from("bar"));
+        todoer.save().unwrap();
+
+        let pomodoro = Pomodoro::start(&todoer, 1, PomodoroSettings::default(), at(9, 0)).unwrap();
+        pomodoro.log().unwrap();
+        pomodoro.log().unwrap();
+        let todoer = Todoer::from_config(config.clone(), true).unwrap();
+        assert_eq!(todoer.data.0[&1].pomodoros, 2);
+        assert!(std::fs::read_to_string(&config)
+            .unwrap()
+            .contains(" pomodoros:2\n"));
+
+        pomodoro.finish().unwrap();
+        let todoer = Todoer::from_config(config, true).unwrap();
+        assert!(todoer.data.0[&1].is_done());
+        assert!(todoer
+            .print_values()
+            .contains("\n1). bar [2 pomodoros] (done "));
+    }
+}
@@ -0,0 +1,323 @@
+use std::fmt::{self, Display};
+
+use chrono::{Datelike, Duration, NaiveDate, NaiveTime, Weekday};
+
+use crate::todoer::{format_estimate, parse_estimate, parse_priority, Priority, DAY_FORMAT};
+
+// A todo written as free text, such as `call vendor tomorrow 3pm !high #ops @phone ~30m`, split
+// into its name and the structure it mentions. Written back as the name followed by the inline
+// tokens `Todo` reads, so parsing what it writes gives the same todo again
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct QuickAdd {
+    pub name: String,
+    pub due: Option<NaiveDate>,
+    pub time: Option<NaiveTime>,
+    pub priority: Option<Priority>,
+    pub tags: Vec<String>,
+    pub contexts: Vec<String>,
+    pub estimate: Option<Duration>,
+}
+
+// Words that only lead into a date or time and go with it, as in `pay rent by friday`
+const DATE_FILLERS: [&str; 3] = ["on", "by", "due"];
+const TIME_FILLERS: [&str; 1] = ["at"];
+
+fn parse_weekday(word: &str) -> Option<Weekday> {
+    match word {
+        "mon" | "monday" => Some(Weekday::Mon),
+        "tue" | "tues" | "tuesday" => Some(Weekday::Tue),
+        "wed" | "wednesday" => Some(Weekday::Wed),
+        "thu" | "thur" | "thurs" | "thursday" => Some(Weekday::Thu),
+        "fri" | "friday" => Some(Weekday::Fri),
+        "sat" | "saturday" => Some(Weekday::Sat),
+        "sun" | "sunday" => Some(Weekday::Sun),
+        _ => None,
+    }
+}
+
+// The first `weekday` after `today`, or `today` itself when `or_today`
+fn following(today: NaiveDate, weekday: Weekday, or_today: bool) -> NaiveDate {
+    let ahead = (7 + weekday.num_days_from_monday() - today.weekday().num_days_from_monday()) % 7;
+    let ahead = if ahead == 0 && !or_today { 7 } else { ahead };
+    today + Duration::days(ahead.into())
+}
+
+// `Duration::days` without its panic on counts out of range, as the chrono in use has no
+// `Duration::try_days`. Milliseconds cover the whole of `i64` without one
+fn try_days(days: i64) -> Option<Duration> {
+    days.checked_mul(86_400_000).map(Duration::milliseconds)
+}
+
+// A date starting at `words[0]` and how many words it takes
+fn parse_date(words: &[String], today: NaiveDate) -> Option<(NaiveDate, usize)> {
+    let word = words.first()?.to_lowercase();
+    let next = words.get(1).map(|word| word.to_lowercase());
+    match (word.as_str(), next.as_deref()) {
+        ("today" | "tonight", _) => return Some((today, 1)),
+        ("tomorrow" | "tmrw" | "tmr", _) => return today.succ_opt().map(|tomorrow| (tomorrow, 1)),
+        ("next", Some("week")) => return Some((following(today, Weekday::Mon, false), 2)),
+        ("next", Some(next)) => {
+            return parse_weekday(next).map(|weekday| (following(today, weekday, false), 2))
+        }
+        ("in", Some(count)) => {
+            let count: i64 = count.parse().ok()?;
+            let unit = words.get(2)?.to_lowercase();
+            let days = match unit.as_str() {
+                "day" | "days" => count,
+                "week" | "weeks" => count.checked_mul(7)?,
+                _ => return None,
+            };
+            // Too far off for a date, the words stay in the name
+            return today
+                .checked_add_signed(try_days(days)?)
+                .map(|date| (date, 3));
+        }
+        _ => {}
+    }
+    if let Some(weekday) = parse_weekday(&word) {
+        return Some((following(today, weekday, true), 1));
+    }
+    let date = word.strip_prefix("due:").unwrap_or(&word);
+    NaiveDate::parse_from_str(date, DAY_FORMAT)
+        .ok()
+        .map(|date| (date, 1))
+}
+
+// A time starting at `words[0]` and how many words it takes: `3pm`, `3:30 pm`, `15:00` or `noon`
+fn parse_time(words: &[String]) -> Option<(NaiveTime, usize)> {
+    let word = words.first()?.to_lowercase();
+    if word == "noon" {
+        return Some((NaiveTime::from_hms_opt(12, 0, 0)?, 1));
+    }
+    if let Some(time) = word.strip_prefix("at:") {
+        return NaiveTime::parse_from_str(time, "%H:%M")
+            .ok()
+            .map(|time| (time, 1));
+    }
+    let next = words.get(1).map(|word| word.to_lowercase());
+    let (clock, meridiem, taken) = if let Some(clock) = word.strip_suffix("am") {
+        (clock, Some(0), 1)
+    } else if let Some(clock) = word.strip_suffix("pm") {
+        (clock, Some(12), 1)
+    } else {
+        match next.as_deref() {
+            Some("am") => (word.as_str(), Some(0), 2),
+            Some("pm") => (word.as_str(), Some(12), 2),
+            // Without am or pm only `HH:MM` is a time, a bare number is more likely a count
+            _ if word.contains(':') => (word.as_str(), None, 1),
+            _ => return None,
+        }
+    };
+    let (hour, minute) = match clock.split_once(':') {
+        Some((hour, minute)) if minute.len() == 2 => (hour.parse().ok()?, minute.parse().ok()?),
+        Some(_) => return None,
+        None => (clock.parse::<u32>().ok()?, 0),
+    };
+    let hour = match meridiem {
+        Some(offset) if (1..=12).contains(&hour) => hour % 12 + offset,
+        Some(_) => return None,
+        None => hour,
+    };
+    NaiveTime::from_hms_opt(hour, minute, 0).map(|time| (time, taken))
+}
+
+// Dates are relative to `today`. Only the first date, time, priority and estimate count, later
+// ones stay in the name. A time without a date is due today
+pub fn parse(text: &str, today: NaiveDate) -> QuickAdd {
+    let words: Vec<String> = text.split_whitespace().map(String::from).collect();
+    let mut quick = QuickAdd::default();
+    let mut kept: Vec<&str> = Vec::new();
+    let mut i = 0;
+    while i < words.len() {
+        let word = words[i].as_str();
+        let filler = kept.last().map(|last| last.to_lowercase());
+        let filler = filler.as_deref();
+        if quick.due.is_none() {
+            if let Some((date, taken)) = parse_date(&words[i..], today) {
+                if filler.is_some_and(|filler| DATE_FILLERS.contains(&filler)) {
+                    kept.pop();
+                }
+                quick.due = Some(date);
+                i += taken;
+                continue;
+            }
+        }
+        if quick.time.is_none() {
+            if let Some((time, taken)) = parse_time(&words[i..]) {
+                if filler.is_some_and(|filler| TIME_FILLERS.contains(&filler)) {
+                    kept.pop();
+                }
+                quick.time = Some(time);
+                i += taken;
+                continue;
+            }
+        }
+        i += 1;
+        if quick.priority.is_none() {
+            if let Some(priority) = parse_priority(word) {
+                quick.priority = Some(priority);
+                continue;
+            }
+        }
+        if quick.estimate.is_none() {
+            let estimate = word.strip_prefix('~').or_else(|| word.strip_prefix("est:"));
+            if let Some(estimate) = estimate.and_then(parse_estimate) {
+                quick.estimate = Some(estimate);
+                continue;
+            }
+        }
+        match (word.strip_prefix('#'), word.strip_prefix('@')) {
+            (Some(tag), _) if !tag.is_empty() => quick.tags.push(tag.to_string()),
+            (_, Some(context)) if !context.is_empty() => quick.contexts.push(context.to_string()),
+            _ => kept.push(word),
+        }
+    }
+    if quick.time.is_some() && quick.due.is_none() {
+        quick.due = Some(today);
+    }
+    quick.name = kept.join(" ");
+    quick
+}
+
+impl QuickAdd {
+    // What `todoer add --dry-run` prints
+    pub fn describe(&self) -> String {
+        let mut res = format!("Would add: {}\n  name: {}\n", self, self.name);
+        match (self.due, self.time) {
+            (Some(due), Some(time)) => {
+                res += &format!(
+                    "  due: {} {}\n",
+                    due.format("%a %Y-%m-%d"),
+                    time.format("%H:%M")
+                )
+            }
+            (Some(due), None) => res += &format!("  due: {}\n", due.format("%a %Y-%m-%d")),
+            _ => {}
+        }
+        if let Some(priority) = self.priority {
+            res += &format!("  priority: {}\n", priority);
+        }
+        if !self.tags.is_empty() {
+            res += &format!("  tags: {}\n", self.tags.join(", "));
+        }
+        if !self.contexts.is_empty() {
+            res += &format!("  context: {}\n", self.contexts.join(", "));
+        }
+        if let Some(estimate) = self.estimate {
+            res += &format!("  estimate: {}\n", format_estimate(estimate));
+        }
+        res
+    }
+}
+
+// The name followed by `!priority #tag @context due:YYYY-MM-DD at:HH:MM est:1h30m`
+impl Display for QuickAdd {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut words: Vec<String> = Vec::new();
+        if !self.name.is_empty() {
+            words.push(self.name.clone());
+        }
+        if let Some(priority) = self.priority {
+            words.push(format!("!{}", priority));
+        }
+        words.extend(self.tags.iter().map(|tag| format!("#{}", tag)));
+        words.extend(self.contexts.iter().map(|context| format!("@{}", context)));
+        if let Some(due) = self.due {
+            words.push(format!("due:{}", due.format(DAY_FORMAT)));
+        }
+        if let Some(time) = self.time {
+            words.push(format!("at:{}", time.format("%H:%M")));
+        }
+        if let Some(estimate) = self.estimate {
+            words.push(format!("est:{}", format_estimate(estimate)));
+        }
+        write!(f, "{}", words.join(" "))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use chrono::{Duration, NaiveDate, NaiveTime};
+
+    use super::parse;
+    use crate::todoer::{Priority, Todo};
+
+    fn today() -> NaiveDate {
+        // A Friday
+        NaiveDate::from_ymd_opt(2022, 11, 25).unwrap()
+    }
+
+    fn date(day: u32) -> Option<NaiveDate> {
+        NaiveDate::from_ymd_opt(2022, 11, day)
+    }
+
+    fn time(hour: u32, minute: u32) -> Option<NaiveTime> {
+        NaiveTime::from_hms_opt(hour, minute, 0)
+    }
+
+    #[test]
+    fn quick_add() {
+        let quick = parse("call vendor tomorrow 3pm !high #ops @phone ~30m", today());
+        assert_eq!(quick.name, "call vendor");
+        assert_eq!(quick.due, date(26));
+        assert_eq!(quick.time, time(15, 0));
+        assert_eq!(quick.priority, Some(Priority::High));
+        assert_eq!(quick.tags, vec!["ops"]);
+        assert_eq!(quick.contexts, vec!["phone"]);
+        assert_eq!(quick.estimate, Some(Duration::minutes(30)));
+        let written = quick.to_string();
+        assert_eq!(
+            written,
+            "call vendor !high #ops @phone due:2022-11-26 at:15:00 est:30m"
+        );
+        assert_eq!(parse(&written, today()), quick);
+
+        // What the todo reads back from its name
+        let todo = Todo {
+            name: written,
+            ..Todo::default()
+        };
+        assert_eq!(todo.due(), date(26));
+        assert_eq!(todo.due_time(), time(15, 0));
+        assert_eq!(todo.priority(), Some(Priority::High));
+        assert_eq!(todo.tags(), vec!["ops"]);
+        assert_eq!(todo.contexts(), vec!["phone"]);
+        assert_eq!(todo.estimate(), Some(Duration::minutes(30)));
+    }
+
+    #[test]
+    fn dates_and_times() {
+        let due = |text: &str| parse(text, today()).due;
+        assert_eq!(due("pay rent by friday"), date(25));
+        assert_eq!(
+            due("pay rent next friday"),
+            NaiveDate::from_ymd_opt(2022, 12, 2)
+        );
+        assert_eq!(due("plan next week"), date(28));
+        assert_eq!(due("review on mon"), date(28));
+        assert_eq!(
+            due("renew in 2 weeks"),
+            NaiveDate::from_ymd_opt(2022, 12, 9)
+        );
+        assert_eq!(due("ship 2022-12-01"), NaiveDate::from_ymd_opt(2022, 12, 1));
+        assert_eq!(due("read 3 chapters"), None);
+        let quick = parse("renew in 100000000 days", today());
+        assert_eq!(
+            (quick.name.as_str(), quick.due),
+            ("renew in 100000000 days", None)
+        );
+        assert_eq!(due("renew in 9223372036854775807 weeks"), None);
+
+        let quick = parse("standup at 9:30 am", today());
+        assert_eq!((quick.name.as_str(), quick.due), ("standup", date(25)));
+        assert_eq!(quick.time, time(9, 30));
+        assert_eq!(parse("lunch noon", today()).time, time(12, 0));
+        assert_eq!(parse("sync 16:45", today()).time, time(16, 45));
+        assert_eq!(parse("buy 13pm cables", today()).time, None);
+
+        let quick = parse("write CLI ~1h30m !low", today());
+        assert_eq!(quick.estimate, Some(Duration::minutes(90)));
+        assert_eq!(quick.to_string(), "write CLI !low est:1h30m");
+        assert_eq!(parse("mail a@b.com", today()).name, "mail a@b.com");
+    }
+}
@@ -0,0 +1,533 @@
+use std::path::{Path, PathBuf};
+
+use chrono::{Local, NaiveDate, NaiveDateTime, NaiveTime};
+use serde::{Deserialize, Serialize};
+
+use crate::agenda::{Agenda, AgendaItem};
+use crate::api::{AgendaList, BulkChange, DayList, TodoKey};
+use crate::calendar::Calendar;
+use crate::client::Client;
+use crate::config::Operation;
+use crate::dependencies::{next, Dependencies};
+use crate::error::{Result, TodoerError};
+use crate::quick_add;
+use crate::timer::Interval;
+use crate::todoer::{Selection, Status, Todo, Todoer, DAY_FORMAT, TIME_FORMAT};
+use crate::todoers::Todoers;
+
+// The server the CLI talks to instead of writing files, from `--remote` and `--token` or `remote.json`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RemoteSettings {
+    pub url: String,
+    #[serde(default)]
+    pub token: Option<String>,
+}
+
+// Next to the todo directory, so `wiki/remote.json`
+pub fn remote_file(todo_dir: &Path) -> PathBuf {
+    todo_dir.with_file_name("remote.json")
+}
+
+// Operations waiting for the server to be reachable again
+pub fn queue_file(todo_dir: &Path) -> PathBuf {
+    todo_dir.with_file_name("remote-queue.json")
+}
+
+// Today's list as the server last sent it with the queued changes made, next to the queue
+fn copy_file(queue: &Path) -> PathBuf {
+    queue.with_file_name("remote-list.json")
+}
+
+fn read_json<T: serde::de::DeserializeOwned>(path: &Path) -> Result<T> {
+    let contents = std::fs::read_to_string(path)?;
+    serde_json::from_str(&contents).map_err(|err| TodoerError::Parse {
+        line: err.line(),
+        message: err.to_string(),
+    })
+}
+
+impl RemoteSettings {
+    // Flags win over the file, with neither the CLI works on local files
+    pub fn resolve(
+        url: Option<String>,
+        token: Option<String>,
+        path: &Path,
+    ) -> Result<Option<Self>> {
+        let file: Option<RemoteSettings> = if std::fs::metadata(path).is_ok() {
+            Some(read_json(path)?)
+        } else {
+            None
+        };
+        match (url, file) {
+            (Some(url), file) => Ok(Some(RemoteSettings {
+                url,
+                token: token.or(file.and_then(|file| file.token)),
+            })),
+            (None, Some(file)) => Ok(Some(RemoteSettings {
+                token: token.or(file.token),
+                ..file
+            })),
+            (None, None) if token.is_some() => Err(TodoerError::Config(String::from(
+                "--token needs --remote or a remote.json",
+            ))),
+            (None, None) => Ok(None),
+        }
+    }
+}
+
+// What waits in the queue. Operations that pick todos by index or tag are queued as the change
+// they make to the todos picked on the list last seen, so the same todos change however the
+// server's list has moved on by the time it is sent
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum Queued {
+    Change(BulkChange),
+    // Adds and sorts, and anything queued before changes were
+    Operation(Operation),
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum Outcome {
+    Print(String),
+    Done,
+    // The server could not be reached, the operation runs on the next call that reaches it
+    Queued,
+}
+
+// Runs operations through a server's API, queueing those that change the list while it is unreachable
+#[derive(Debug)]
+pub struct Remote {
+    client: Client,
+    queue: PathBuf,
+    copy: PathBuf,
+}
+
+// A copy of a list the server sent, so selections and printing work as they do locally
+fn todoer_from(list: &DayList) -> Todoer {
+    let time = |time: &Option<String>| {
+        time.as_deref()
+            .and_then(|time| NaiveDateTime::parse_from_str(time, TIME_FORMAT).ok())
+    };
+    let mut todoer = Todoer::default_todoer(PathBuf::from(format!("{}.md", list.day)));
+    for todo in &list.todos {
+        let todo = Todo {
+            name: todo.name.clone(),
+            // Servers from before statuses only send done
+            status: todo.status.parse().unwrap_or(if todo.done {
+                Status::Done
+            } else {
+                Status::Open
+            }),
+            created: time(&todo.created),
+            completed: time(&todo.completed),
+            since: todo
+                .since
+                .as_deref()
+                .and_then(|day| NaiveDate::parse_from_str(day, DAY_FORMAT).ok()),
+            tracked: todo
+                .tracked
+                .iter()
+                .filter_map(|interval| Interval::parse(interval))
+                .collect(),
+            pomodoros: todo.pomodoros,
+            id: todo.id.clone(),
+            blocked_by: todo.blocked_by.clone(),
+        };
+        todoer.done_count += u32::from(todo.is_done());
+        todoer.data.0.insert(todoer.size, todo);
+        todoer.size += 1;
+    }
+    todoer
+}
+
+// Entries the server could not have written are left out
+fn agenda_from(list: &AgendaList) -> Agenda {
+    let day = |day: &str| NaiveDate::parse_from_str(day, DAY_FORMAT).ok();
+    let items = list
+        .entries
+        .iter()
+        .filter_map(|entry| {
+            Some(AgendaItem {
+                name: entry.name.clone(),
+                list: entry.list.clone(),
+                status: entry.status.parse().ok()?,
+                due: day(&entry.due)?,
+                time: entry
+                    .time
+                    .as_deref()
+                    .and_then(|time| NaiveTime::parse_from_str(time, "%H:%M").ok()),
+            })
+        })
+        .collect();
+    Agenda {
+        today: day(&list.today).unwrap_or_else(|| Local::now().date_naive()),
+        items,
+    }
+}
+
+fn local_only() -> TodoerError {
+    TodoerError::Config(String::from(
+        "sync, time tracking, blocking, import and export work on local files, not with --remote",
+    ))
+}
+
+fn picks_todos(operation: &Operation) -> bool {
+    matches!(
+        operation,
+        Operation::Complete(_)
+            | Operation::Remove(_)
+            | Operation::SetStatus(..)
+            | Operation::Tag(..)
+            | Operation::MoveTo(..)
+            | Operation::MoveUp(_)
+            | Operation::MoveDown(_)
+    )
+}
+
+// The change an operation makes to the todos it picks on `todoer`, none for those that pick none
+fn change_for(operation: &Operation, todoer: &Todoer) -> Result<Option<BulkChange>> {
+    let (action, selection, value, to) = match operation {
+        Operation::Complete(selection) => ("done", selection.clone(), None, None),
+        Operation::Remove(selection) => ("remove", selection.clone(), None, None),
+        Operation::SetStatus(status, selection) => {
+            ("status", selection.clone(), Some(status.to_string()), None)
+        }
+        Operation::Tag(tag, selection) => ("tag", selection.clone(), Some(tag.clone()), None),
+        Operation::MoveTo(selection, to) => ("move", selection.clone(), None, Some(*to)),
+        Operation::MoveUp(index) => (
+            "move",
+            Selection::Indices(vec![*index]),
+            None,
+            Some(index.saturating_sub(1)),
+        ),
+        Operation::MoveDown(index) => (
+            "move",
+            Selection::Indices(vec![*index]),
+            None,
+            Some(index + 1),
+        ),
+        _ => return Ok(None),
+    };
+    let todos = todoer
+        .select(&selection)?
+        .iter()
+        .map(|index| TodoKey::from(&todoer.data.0[index]))
+        .collect();
+    Ok(Some(BulkChange {
+        action: action.to_string(),
+        todos,
+        value,
+        to,
+    }))
+}
+
+// Makes a queued change to the copy of the list, so operations queued after it pick todos as
+// they will be by then
+fn apply_to_copy(todoer: &mut Todoer, queued: &Queued) -> Result<()> {
+    match queued {
+        Queued::Change(change) => change.apply(todoer),
+        Queued::Operation(Operation::Add(name)) => {
+            todoer.set_value(quick_add::parse(name, Local::now().date_naive()).to_string());
+            // The server times it when the add is sent, so it is found by name until then
+            if let Some(todo) = todoer.data.0.get_mut(&(todoer.size - 1)) {
+                todo.created = None;
+            }
+            Ok(())
+        }
+        Queued::Operation(Operation::Sort(by)) => todoer.sort_values(*by),
+        Queued::Operation(_) => Ok(()),
+    }
+}
+
+impl Remote {
+    pub fn new(settings: RemoteSettings, queue: PathBuf) -> Self {
+        Remote {
+            client: Client::new(&settings.url, settings.token),
+            copy: copy_file(&queue),
+            queue,
+        }
+    }
+
+    pub fn queued(&self) -> Result<Vec<Queued>> {
+        if std::fs::metadata(&self.queue).is_err() {
+            return Ok(Vec::new());
+        }
+        read_json(&self.queue)
+    }
+
+    fn save_queue(&self, queued: &[Queued]) -> Result<()> {
+        if queued.is_empty() {
+            if std::fs::metadata(&self.queue).is_ok() {
+                std::fs::remove_file(&self.queue)?;
+            }
+            return Ok(());
+        }
+        if let Some(parent) = self.queue.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(
+            &self.queue,
+            serde_json::to_string_pretty(queued).unwrap() + "\n",
+        )?;
+        Ok(())
+    }
+
+    fn copy(&self) -> Result<Option<Todoer>> {
+        if std::fs::metadata(&self.copy).is_err() {
+            return Ok(None);
+        }
+        Ok(Some(todoer_from(&read_json(&self.copy)?)))
+    }
+
+    fn remember(&self, list: &DayList) -> Result<()> {
+        if let Some(parent) = self.copy.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(
+            &self.copy,
+            serde_json::to_string_pretty(list).unwrap() + "\n",
+        )?;
+        Ok(())
+    }
+
+    // Today's list from the server, kept as the copy unless queued changes haven't reached it yet
+    fn todos(&self) -> Result<DayList> {
+        let list = self.client.todos()?;
+        if self.queued()?.is_empty() {
+            self.remember(&list)?;
+        }
+        Ok(list)
+    }
+
+    // Sends queued operations in order until one can't reach the server. Those the server
+    // rejects are dropped from the queue and returned with their error
+    pub fn flush(&self) -> Result<Vec<(Queued, TodoerError)>> {
+        let mut queued = self.queued()?;
+        let mut dropped = Vec::new();
+        let mut list = None;
+        while let Some(entry) = queued.first() {
+            let sent = match entry {
+                Queued::Change(change) => self.client.bulk(change),
+                Queued::Operation(operation) => self.change(operation),
+            };
+            match sent {
+                Err(TodoerError::Unreachable(_)) => break,
+                Err(err) => dropped.push((queued.remove(0), err)),
+                Ok(sent) => {
+                    list = Some(sent);
+                    queued.remove(0);
+                }
+            }
+            // Saved after each one so an interrupted flush doesn't send anything twice
+            self.save_queue(&queued)?;
+        }
+        if let (true, Some(list)) = (queued.is_empty(), list) {
+            self.remember(&list)?;
+        }
+        Ok(dropped)
+    }
+
+    // Operations that only print fail when the server is unreachable, others are queued.
+    // While older operations are still queued new ones wait behind them to keep their order
+    pub fn run(&self, operation: Operation) -> Result<Outcome> {
+        let mut queued = self.queued()?;
+        if operation.is_local_only() {
+            return Err(local_only());
+        }
+        if operation.is_read_only() {
+            return self.print(&operation).map(Outcome::Print);
+        }
+        if queued.is_empty() {
+            match self.change(&operation) {
+                Err(TodoerError::Unreachable(_)) => {}
+                Err(err) => return Err(err),
+                Ok(list) => {
+                    self.remember(&list)?;
+                    return Ok(Outcome::Done);
+                }
+            }
+        }
+
+        // Todos are picked on the copy, which holds what was last printed with the queue applied
+        let mut copy = self.copy()?;
+        let change = match &copy {
+            Some(todoer) => change_for(&operation, todoer)?,
+            None if picks_todos(&operation) => {
+                return Err(TodoerError::Unreachable(String::from(
+                    "todos can only be picked once the list has been fetched",
+                )))
+            }
+            None => None,
+        };
+        let entry = change.map_or(Queued::Operation(operation), Queued::Change);
+        if let Some(todoer) = &mut copy {
+            apply_to_copy(todoer, &entry)?;
+            self.remember(&DayList::from(&*todoer))?;
+        }
+        queued.push(entry);
+        self.save_queue(&queued)?;
+        Ok(Outcome::Queued)
+    }
+
+    // Makes a change on the server, picking todos on its list as it is now, and returns the list after
+    fn change(&self, operation: &Operation) -> Result<DayList> {
+        let client = &self.client;
+        match operation {
+            // The server reads the text as a quick add against its own day
+            Operation::Add(name) => client.add(name),
+            Operation::Sort(by) => client.sort(*by),
+            operation => {
+                let todoer = todoer_from(&client.todos()?);
+                match change_for(operation, &todoer)? {
+                    // One request, so a dropped connection can't leave it half done
+                    Some(change) => client.bulk(&change),
+                    None => Err(local_only()),
+                }
+            }
+        }
+    }
+
+    fn print(&self, operation: &Operation) -> Result<String> {
+        let client = &self.client;
+        match operation {
+            Operation::Print() => Ok(todoer_from(&self.todos()?).print_values()),
+            Operation::Stats() => Ok(todoer_from(&self.todos()?).stats()),
+            Operation::Calendar(month) => {
+                let days = client.days()?;
+                let days = days
+                    .iter()
+                    .map(|day| (day.name.as_str(), day.done_count, day.size));
+                Ok(Calendar::new(*month, days).print())
+            }
+            Operation::Agenda(days) => Ok(agenda_from(&client.agenda(*days)?).print()),
+            Operation::PreviewAdd(name) => {
+                Ok(quick_add::parse(name, Local::now().date_naive()).describe())
+            }
+            Operation::Next() => {
+                let today = todoer_from(&self.todos()?);
+                let mut todoers: Vec<Todoer> = Vec::new();
+                for day in client.days()?.iter().rev() {
+                    todoers.push(todoer_from(&client.day(&day.name)?));
+                }
+                let dependencies = Dependencies::new(todoers.iter().chain([&today]));
+                next(&today, &dependencies)
+            }
+            Operation::PrintAll() => {
+                let mut todoers = Vec::new();
+                // Oldest first, as when reading the directory
+                for day in client.days()?.iter().rev() {
+                    todoers.push(todoer_from(&client.day(&day.name)?));
+                }
+                let todoers = Todoers { todoers };
+                Ok(todoers.print_all_todos_together())
+            }
+            _ => Err(local_only()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{queue_file, remote_file, Outcome, Queued, Remote, RemoteSettings};
+    use crate::api::{BulkChange, TodoKey};
+    use crate::config::Operation;
+    use crate::error::TodoerError;
+    use crate::testing::todo_dir;
+    use crate::todoer::Selection;
+
+    #[test]
+    fn settings() {
+        let todo_dir = todo_dir("remote", "settings");
+        let path = remote_file(&todo_dir);
+        assert_eq!(RemoteSettings::resolve(None, None, &path).unwrap(), None);
+        assert!(RemoteSettings::resolve(None, Some(String::from("a:b")), &path).is_err());
+
+        std::fs::write(
+            &path,
+            r#"{"url": "https://todo.example.com", "token": "alice:secret"}"#,
+        )
+        .unwrap();
+        let settings = RemoteSettings::resolve(None, None, &path).unwrap().unwrap();
+        assert_eq!(settings.url, "https://todo.example.com");
+        assert_eq!(settings.token.as_deref(), Some("alice:secret"));
+
+        let settings =
+            RemoteSettings::resolve(Some(String::from("http://localhost:3000")), None, &path)
+                .unwrap()
+                .unwrap();
+        assert_eq!(settings.url, "http://localhost:3000");
+        assert_eq!(settings.token.as_deref(), Some("alice:secret"));
+    }
+
+    #[test]
+    fn queue_while_unreachable() {
+        let todo_dir = todo_dir("remote", "queue");
+        // Nothing listens on port 9 of localhost
+        let settings = RemoteSettings {
+            url: String::from("http://127.0.0.1:9"),
+            token: None,
+        };
+        let remote = Remote::new(settings, queue_file(&todo_dir));
+
+        assert!(matches!(
+            remote.run(Operation::Print()),
+            Err(TodoerError::Unreachable(_))
+        ));
+        assert_eq!(
+            remote.run(Operation::Add(String::from("foo"))).unwrap(),
+            Outcome::Queued
+        );
+        // Without a copy of the list there is nothing to pick todos on
+        assert!(matches!(
+            remote.run(Operation::MoveUp(1)),
+            Err(TodoerError::Unreachable(_))
+        ));
+        assert!(remote.flush().unwrap().is_empty());
+        assert_eq!(
+            remote.queued().unwrap(),
+            vec![Queued::Operation(Operation::Add(String::from("foo")))]
+        );
+
+        std::fs::write(
+            todo_dir.with_file_name("remote-list.json"),
+            r#"{"day": "2022-11-25", "done_count": 0, "size": 2, "todos": [
+                {"index": 0, "name": "foo", "done": false, "created": "2022-11-25T09:00"},
+                {"index": 1, "name": "bar", "done": false, "id": "k3xq9a"}
+            ]}"#,
+        )
+        .unwrap();
+        assert_eq!(remote.run(Operation::MoveUp(1)).unwrap(), Outcome::Queued);
+        assert_eq!(
+            remote
+                .run(Operation::Complete(Selection::Indices(vec![1])))
+                .unwrap(),
+            Outcome::Queued
+        );
+        let queued = remote.queued().unwrap();
+        let key = |name: &str, id: Option<&str>, created: Option<&str>| TodoKey {
+            id: id.map(String::from),
+            name: String::from(name),
+            created: created.map(String::from),
+        };
+        assert_eq!(
+            queued[1],
+            Queued::Change(BulkChange {
+                action: String::from("move"),
+                todos: vec![key("bar", Some("k3xq9a"), None)],
+                value: None,
+                to: Some(0),
+            })
+        );
+        // Index 1 is foo once bar moved up
+        assert_eq!(
+            queued[2],
+            Queued::Change(BulkChange {
+                action: String::from("done"),
+                todos: vec![key("foo", None, Some("2022-11-25T09:00"))],
+                value: None,
+                to: None,
+            })
+        );
+    }
+}
@@ -0,0 +1,944 @@
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::Duration;
+
+use actix_web::http::header::{self, HeaderMap};
+use actix_web::http::StatusCode;
+use actix_web::middleware::Condition;
+use actix_web::{
+    delete, get, post, put, web, App, HttpRequest, HttpResponse, HttpServer, ResponseError,
+};
+use chrono::Local;
+use tracing_subscriber::EnvFilter;
+use utoipa::openapi::security::{HttpAuthScheme, HttpBuilder, SecurityScheme};
+use utoipa::{Modify, OpenApi};
+
+use crate::agenda::Agenda;
+use crate::api::{
+    day_config, list_days, AgendaEntry, AgendaList, AgendaQuery, BulkChange, DayList, DaySummary,
+    MoveTodos, SetStatus, SortTodos, TodoItem, TodoKey, TodoName,
+};
+use crate::auth::{parse_credentials, user_root, users_file, Users};
+use crate::caldav;
+use crate::config::get_config;
+use crate::error::{Result, TodoerError};
+use crate::events::{sync_with_disk, Event, Events};
+use crate::metrics::Metrics;
+use crate::quick_add;
+use crate::settings::{Settings, StateMode};
+use crate::todoer::{parse_index, SortKey, Status, Todoer};
+
+const INDEX_HTML: &str = include_str!("web/index.html");
+
+// One user's list, shared by all of their clients
+pub struct List {
+    pub root: PathBuf,
+    pub events: Events,
+    state: StateMode,
+    metrics: Arc<Metrics>,
+    todoer: Mutex<Option<Todoer>>,
+}
+
+impl List {
+    pub fn new(root: PathBuf, state: StateMode, metrics: Arc<Metrics>) -> Self {
+        List {
+            root,
+            events: Events::new(),
+            state,
+            metrics,
+            todoer: Mutex::new(None),
+        }
+    }
+
+    // Today's list, read from disk when nothing is cached, in file mode, or once the day changes
+    fn lock(&self) -> Result<MutexGuard<'_, Option<Todoer>>> {
+        let today = get_config(Some(self.root.clone()), None)?;
+        let mut todoer = self.todoer.lock().unwrap();
+        let stale = match &*todoer {
+            Some(todoer) => self.state == StateMode::File || todoer.config != today,
+            None => true,
+        };
+        if stale {
+            *todoer = Some(Todoer::from_config(today, false)?);
+        }
+        Ok(todoer)
+    }
+
+    pub fn view<T>(&self, f: impl FnOnce(&Todoer) -> Result<T>) -> Result<T> {
+        let todoer = self.lock()?;
+        f(todoer.as_ref().unwrap())
+    }
+
+    // Applies a change, saves it and tells every client about it. The change is made on a copy,
+    // so one that fails part way or can't be saved leaves the list as it was
+    pub fn update(&self, f: impl FnOnce(&mut Todoer) -> Result<Event>) -> Result<DayList> {
+        let mut cached = self.lock()?;
+        let mut todoer = cached.clone().unwrap();
+        let event = f(&mut todoer)?;
+        self.save(&todoer, event)?;
+        let list = DayList::from(&todoer);
+        *cached = Some(todoer);
+        Ok(list)
+    }
+
+    // As `update`, for the list of any day, named as in `day_config`. Today's list stays locked
+    // meanwhile, so changes to the user's files never overlap. Other days are read from disk and
+    // their change is sent as `day_changed`
+    pub fn update_day(
+        &self,
+        day: &str,
+        f: impl FnOnce(&mut Todoer) -> Result<Event>,
+    ) -> Result<DayList> {
+        let mut cached = self.lock()?;
+        let today = cached.as_ref().unwrap();
+        let config = day_config(&today.config, day)?;
+        if config == today.config {
+            let mut todoer = today.clone();
+            let event = f(&mut todoer)?;
+            self.save(&todoer, event)?;
+            let list = DayList::from(&todoer);
+            *cached = Some(todoer);
+            return Ok(list);
+        }
+        let mut todoer = Todoer::from_config(config, true)?;
+        f(&mut todoer)?;
+        let list = DayList::from(&todoer);
+        self.save(&todoer, Event::DayChanged { list: list.clone() })?;
+        Ok(list)
+    }
+
+    fn save(&self, todoer: &Todoer, event: Event) -> Result<()> {
+        if let Err(err) = todoer.save() {
+            self.metrics.record_save_failure();
+            tracing::error!(config = %todoer.config.display(), error = %err, "could not save");
+            return Err(err);
+        }
+        self.events.send(event);
+        Ok(())
+    }
+
+    // Picks up edits made outside the server and the start of a new day, for lists already read
+    pub fn sync(&self) -> Result<()> {
+        let today = get_config(Some(self.root.clone()), None)?;
+        let mut todoer = self.todoer.lock().unwrap();
+        let Some(todoer) = todoer.as_mut() else {
+            return Ok(());
+        };
+        if let Some(event) = sync_with_disk(todoer, today)? {
+            self.events.send(event);
+        }
+        Ok(())
+    }
+}
+
+// Lists are opened on first use. With no users added every request is refused, or everyone shares
+// the list under the root when anonymous access is allowed
+pub struct Store {
+    pub root: PathBuf,
+    pub metrics: Arc<Metrics>,
+    state: StateMode,
+    allow_anonymous: bool,
+    lists: Mutex<HashMap<String, Arc<List>>>,
+}
+
+impl Store {
+    pub fn new(root: PathBuf, state: StateMode, allow_anonymous: bool) -> Self {
+        Store {
+            root,
+            metrics: Arc::new(Metrics::default()),
+            state,
+            allow_anonymous,
+            lists: Mutex::new(HashMap::new()),
+        }
+    }
+
+    fn list(&self, name: &str, root: PathBuf) -> Arc<List> {
+        self.lists
+            .lock()
+            .unwrap()
+            .entry(name.to_string())
+            .or_insert_with(|| Arc::new(List::new(root, self.state, self.metrics.clone())))
+            .clone()
+    }
+
+    // The users file is read on every request so users can be added and removed while running
+    pub fn authorize(&self, headers: &HeaderMap) -> Result<Arc<List>> {
+        let users = Users::load(&users_file(&self.root))?;
+        if users.is_empty() {
+            if !self.allow_anonymous {
+                return Err(TodoerError::Unauthorized(String::from(
+                    "no users added, see `todoer-server users add` or start with --allow-anonymous",
+                )));
+            }
+            return Ok(self.list("", self.root.clone()));
+        }
+        let (name, secret) = headers
+            .get(header::AUTHORIZATION)
+            .and_then(|value| value.to_str().ok())
+            .and_then(parse_credentials)
+            .ok_or_else(|| TodoerError::Unauthorized(String::from("missing credentials")))?;
+        let user = users.authenticate(&name, &secret)?;
+        Ok(self.list(&user.name, user_root(&self.root, &user.name)))
+    }
+
+    pub fn sync(&self) {
+        let lists: Vec<Arc<List>> = self.lists.lock().unwrap().values().cloned().collect();
+        for list in lists {
+            if let Err(err) = list.sync() {
+                tracing::warn!(root = %list.root.display(), error = %err, "could not reload");
+            }
+        }
+    }
+
+    // Open and done todos over every list read so far
+    pub fn totals(&self) -> (u32, u32) {
+        let lists: Vec<Arc<List>> = self.lists.lock().unwrap().values().cloned().collect();
+        lists
+            .iter()
+            .filter_map(|list| {
+                let todoer = list.todoer.lock().unwrap();
+                todoer
+                    .as_ref()
+                    .map(|todoer| (todoer.open_count(), todoer.done_count))
+            })
+            .fold((0, 0), |(open, done), (o, d)| (open + o, done + d))
+    }
+
+    // Ready once the root can be read and the users file parses
+    pub fn check(&self) -> Result<()> {
+        std::fs::read_dir(&self.root)?;
+        Users::load(&users_file(&self.root))?;
+        Ok(())
+    }
+}
+
+// How errors reach HTTP clients, kept here so the library itself doesn't need actix
+impl ResponseError for TodoerError {
+    fn status_code(&self) -> StatusCode {
+        match self {
+            TodoerError::NotFound(_) => StatusCode::NOT_FOUND,
+            TodoerError::Parse { .. } => StatusCode::UNPROCESSABLE_ENTITY,
+            TodoerError::Io(_) => StatusCode::INTERNAL_SERVER_ERROR,
+            TodoerError::Config(_) => StatusCode::BAD_REQUEST,
+            TodoerError::Conflict(_) => StatusCode::CONFLICT,
+            TodoerError::Unauthorized(_) => StatusCode::UNAUTHORIZED,
+            TodoerError::Unreachable(_) => StatusCode::BAD_GATEWAY,
+        }
+    }
+
+    // Asks browsers for a user name and token on 401
+    fn error_response(&self) -> HttpResponse {
+        let mut response = HttpResponse::build(self.status_code());
+        if let TodoerError::Unauthorized(_) = self {
+            response.insert_header((header::WWW_AUTHENTICATE, "Basic realm=\"todoer\""));
+        }
+        response.body(self.to_string())
+    }
+}
+
+mod middleware {
+    use std::time::Instant;
+
+    use actix_web::body::EitherBody;
+    use actix_web::dev::{forward_ready, Service, ServiceRequest, ServiceResponse, Transform};
+    use actix_web::{web, HttpMessage};
+    use futures_util::future::{ready, LocalBoxFuture, Ready};
+
+    use super::Store;
+    use crate::error::TodoerError;
+
+    // Rejects requests without valid credentials and hands the caller's list to the handlers
+    pub struct Auth;
+
+    impl<S, B> Transform<S, ServiceRequest> for Auth
+    where
+        S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = actix_web::Error>,
+        S::Future: 'static,
+        B: 'static,
+    {
+        type Response = ServiceResponse<EitherBody<B>>;
+        type Error = actix_web::Error;
+        type Transform = AuthMiddleware<S>;
+        type InitError = ();
+        type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+        fn new_transform(&self, service: S) -> Self::Future {
+            ready(Ok(AuthMiddleware { service }))
+        }
+    }
+
+    pub struct AuthMiddleware<S> {
+        service: S,
+    }
+
+    impl<S, B> Service<ServiceRequest> for AuthMiddleware<S>
+    where
+        S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = actix_web::Error>,
+        S::Future: 'static,
+        B: 'static,
+    {
+        type Response = ServiceResponse<EitherBody<B>>;
+        type Error = actix_web::Error;
+        type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+        forward_ready!(service);
+
+        fn call(&self, req: ServiceRequest) -> Self::Future {
+            let authorized = match req.app_data::<web::Data<Store>>() {
+                Some(store) => store.authorize(req.headers()),
+                None => Err(TodoerError::Config(String::from("no store configured"))),
+            };
+            match authorized {
+                Ok(list) => {
+                    req.extensions_mut().insert(list);
+                    let response = self.service.call(req);
+                    Box::pin(async move { response.await.map(ServiceResponse::map_into_left_body) })
+                }
+                Err(err) => {
+                    let response = req.error_response(err).map_into_right_body();
+                    Box::pin(ready(Ok(response)))
+                }
+            }
+        }
+    }
+
+    // Logs every request and counts it in the metrics, including ones auth turned away
+    pub struct Telemetry;
+
+    impl<S, B> Transform<S, ServiceRequest> for Telemetry
+    where
+        S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = actix_web::Error>,
+        S::Future: 'static,
+        B: 'static,
+    {
+        type Response = ServiceResponse<B>;
+        type Error = actix_web::Error;
+        type Transform = TelemetryMiddleware<S>;
+        type InitError = ();
+        type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+        fn new_transform(&self, service: S) -> Self::Future {
+            ready(Ok(TelemetryMiddleware { service }))
+        }
+    }
+
+    pub struct TelemetryMiddleware<S> {
+        service: S,
+    }
+
+    impl<S, B> Service<ServiceRequest> for TelemetryMiddleware<S>
+    where
+        S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = actix_web::Error>,
+        S::Future: 'static,
+        B: 'static,
+    {
+        type Response = ServiceResponse<B>;
+        type Error = actix_web::Error;
+        type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+        forward_ready!(service);
+
+        fn call(&self, req: ServiceRequest) -> Self::Future {
+            let start = Instant::now();
+            let metrics = req
+                .app_data::<web::Data<Store>>()
+                .map(|store| store.metrics.clone());
+            let method = req.method().to_string();
+            let path = req.path().to_string();
+            let response = self.service.call(req);
+            Box::pin(async move {
+                let response = response.await?;
+                let elapsed = start.elapsed();
+                let status = response.status().as_u16();
+                let route = response
+                    .request()
+                    .match_pattern()
+                    .unwrap_or_else(|| String::from("unmatched"));
+                if let Some(metrics) = metrics {
+                    metrics.record_request(&method, &route, status, elapsed);
+                }
+                tracing::info!(
+                    method = %method,
+                    path = %path,
+                    route = %route,
+                    status,
+                    latency_ms = elapsed.as_secs_f64() * 1000.0,
+                    "request"
+                );
+                Ok(response)
+            })
+        }
+    }
+}
+
+pub use middleware::{Auth, AuthMiddleware, Telemetry, TelemetryMiddleware};
+
+// Up as long as the process answers
+#[utoipa::path(
+    tag = "health",
+    responses((status = 200, description = "The server is up", body = String, content_type = "text/plain")),
+    security(())
+)]
+#[get("/healthz")]
+async fn healthz() -> HttpResponse {
+    HttpResponse::Ok().body("ok")
+}
+
+#[utoipa::path(
+    tag = "health",
+    responses(
+        (status = 200, description = "The server can serve requests", body = String, content_type = "text/plain"),
+        (status = 503, description = "The root or users file can't be read", body = String, content_type = "text/plain"),
+    ),
+    security(())
+)]
+#[get("/readyz")]
+async fn readyz(store: web::Data<Store>) -> HttpResponse {
+    match store.check() {
+        Ok(()) => HttpResponse::Ok().body("ok"),
+        Err(err) => HttpResponse::ServiceUnavailable().body(err.to_string()),
+    }
+}
+
+#[utoipa::path(
+    tag = "health",
+    responses((status = 200, description = "Metrics in the Prometheus text format", body = String, content_type = "text/plain")),
+    security(())
+)]
+#[get("/metrics")]
+async fn prometheus(store: web::Data<Store>) -> HttpResponse {
+    let (open, done) = store.totals();
+    HttpResponse::Ok()
+        .content_type("text/plain; version=0.0.4")
+        .body(store.metrics.render(open, done))
+}
+
+// Browsers get the web UI, everything else keeps getting the plain list
+#[utoipa::path(
+    tag = "plain",
+    responses((status = 200, description = "Today's todos as text, or the web UI when HTML is accepted", body = String, content_type = "text/plain"))
+)]
+#[get("/")]
+async fn print(req: HttpRequest, list: web::ReqData<Arc<List>>) -> Result<HttpResponse> {
+    let wants_html = req
+        .headers()
+        .get(header::ACCEPT)
+        .and_then(|accept| accept.to_str().ok())
+        .is_some_and(|accept| accept.contains("text/html"));
+    if wants_html {
+        return Ok(HttpResponse::Ok()
+            .content_type("text/html; charset=utf-8")
+            .body(INDEX_HTML));
+    }
+    let value = list.view(|todoer| Ok(todoer.print_values()))?;
+    Ok(HttpResponse::Ok().body(value))
+}
+
+#[utoipa::path(
+    tag = "plain",
+    request_body(content = String, description = "Name of the new todo, read as a quick add", content_type = "text/plain"),
+    responses((status = 200, description = "The todo was added"))
+)]
+#[post("/add")]
+async fn add(list: web::ReqData<Arc<List>>, body: String) -> Result<HttpResponse> {
+    quick_add_todo(&list, &body)?;
+    Ok(HttpResponse::Ok().finish())
+}
+
+// Adds `text` read as a quick add last on today's list
+fn quick_add_todo(list: &List, text: &str) -> Result<DayList> {
+    let name = quick_add::parse(text, Local::now().date_naive()).to_string();
+    list.update(|todoer| {
+        todoer.set_value(name.clone());
+        Ok(Event::Added {
+            index: todoer.size - 1,
+            name,
+        })
+    })
+}
+
+#[utoipa::path(
+    tag = "plain",
+    request_body(content = String, description = "Index of the todo", content_type = "text/plain"),
+    responses(
+        (status = 200, description = "The todo was marked done"),
+        (status = 404, description = "No todo at this index", body = String, content_type = "text/plain"),
+        (status = 409, description = "The todo is already done", body = String, content_type = "text/plain"),
+    )
+)]
+#[post("/done")]
+async fn complete(list: web::ReqData<Arc<List>>, body: String) -> Result<HttpResponse> {
+    let index = parse_index(body.trim())?;
+    list.update(|todoer| {
+        todoer.mark_done(index)?;
+        Ok(Event::Completed { index })
+    })?;
+    Ok(HttpResponse::Ok().finish())
+}
+
+#[utoipa::path(
+    tag = "plain",
+    request_body(content = String, description = "Index of the todo", content_type = "text/plain"),
+    responses(
+        (status = 200, description = "The todo was removed"),
+        (status = 404, description = "No todo at this index", body = String, content_type = "text/plain"),
+    )
+)]
+#[post("/remove")]
+async fn remove(list: web::ReqData<Arc<List>>, body: String) -> Result<HttpResponse> {
+    let index = parse_index(body.trim())?;
+    list.update(|todoer| {
+        todoer.remove_value(index)?;
+        Ok(Event::Removed { index })
+    })?;
+    Ok(HttpResponse::Ok().finish())
+}
+
+#[utoipa::path(
+    tag = "todos",
+    responses((status = 200, description = "Today's todos", body = DayList))
+)]
+#[get("/api/todos")]
+async fn api_todos(list: web::ReqData<Arc<List>>) -> Result<HttpResponse> {
+    let todos = list.view(|todoer| Ok(DayList::from(todoer)))?;
+    Ok(HttpResponse::Ok().json(todos))
+}
+
+#[utoipa::path(
+    tag = "todos",
+    request_body = TodoName,
+    responses((status = 200, description = "Today's todos with the new one last, its name read as a quick add", body = DayList))
+)]
+#[post("/api/todos")]
+async fn api_add(list: web::ReqData<Arc<List>>, body: web::Json<TodoName>) -> Result<HttpResponse> {
+    let todos = quick_add_todo(&list, &body.into_inner().name)?;
+    Ok(HttpResponse::Ok().json(todos))
+}
+
+#[utoipa::path(
+    tag = "todos",
+    params(("index" = u32, Path, description = "Position of the todo")),
+    request_body = TodoName,
+    responses(
+        (status = 200, description = "Today's todos after renaming", body = DayList),
+        (status = 404, description = "No todo at this index", body = String, content_type = "text/plain"),
+    )
+)]
+#[put("/api/todos/{index}")]
+async fn api_edit(
+    list: web::ReqData<Arc<List>>,
+    index: web::Path<u32>,
+    body: web::Json<TodoName>,
+) -> Result<HttpResponse> {
+    let (index, name) = (index.into_inner(), body.into_inner().name);
+    let todos = list.update(|todoer| {
+        todoer.edit_value(index, name.clone())?;
+        Ok(Event::Edited { index, name })
+    })?;
+    Ok(HttpResponse::Ok().json(todos))
+}
+
+#[utoipa::path(
+    tag = "todos",
+    params(("index" = u32, Path, description = "Position of the todo")),
+    responses(
+        (status = 200, description = "Today's todos after marking it done", body = DayList),
+        (status = 404, description = "No todo at this index", body = String, content_type = "text/plain"),
+        (status = 409, description = "The todo is already done", body = String, content_type = "text/plain"),
+    )
+)]
+#[post("/api/todos/{index}/done")]
+async fn api_done(list: web::ReqData<Arc<List>>, index: web::Path<u32>) -> Result<HttpResponse> {
+    let index = index.into_inner();
+    let todos = list.update(|todoer| {
+        todoer.mark_done(index)?;
+        Ok(Event::Completed { index })
+    })?;
+    Ok(HttpResponse::Ok().json(todos))
+}
+
+#[utoipa::path(
+    tag = "todos",
+    params(("index" = u32, Path, description = "Position of the todo")),
+    responses(
+        (status = 200, description = "Today's todos after marking it not done", body = DayList),
+        (status = 404, description = "No todo at this index", body = String, content_type = "text/plain"),
+        (status = 409, description = "The todo is not done", body = String, content_type = "text/plain"),
+    )
+)]
+#[post("/api/todos/{index}/undone")]
+async fn api_undone(list: web::ReqData<Arc<List>>, index: web::Path<u32>) -> Result<HttpResponse> {
+    let index = index.into_inner();
+    let todos = list.update(|todoer| {
+        todoer.mark_undone(index)?;
+        Ok(Event::Uncompleted { index })
+    })?;
+    Ok(HttpResponse::Ok().json(todos))
+}
+
+#[utoipa::path(
+    tag = "todos",
+    params(("index" = u32, Path, description = "Position of the todo")),
+    request_body = SetStatus,
+    responses(
+        (status = 200, description = "Today's todos with the status set", body = DayList),
+        (status = 400, description = "Unknown status", body = String, content_type = "text/plain"),
+        (status = 404, description = "No todo at this index", body = String, content_type = "text/plain"),
+    )
+)]
+#[post("/api/todos/{index}/status")]
+async fn api_status(
+    list: web::ReqData<Arc<List>>,
+    index: web::Path<u32>,
+    body: web::Json<SetStatus>,
+) -> Result<HttpResponse> {
+    let index = index.into_inner();
+    let status: Status = body.status.parse()?;
+    let todos = list.update(|todoer| {
+        todoer.set_status(index, status)?;
+        Ok(Event::StatusChanged {
+            index,
+            status: status.to_string(),
+        })
+    })?;
+    Ok(HttpResponse::Ok().json(todos))
+}
+
+#[utoipa::path(
+    tag = "todos",
+    params(("index" = u32, Path, description = "Position of the todo")),
+    responses(
+        (status = 200, description = "Today's todos after removing it", body = DayList),
+        (status = 404, description = "No todo at this index", body = String, content_type = "text/plain"),
+    )
+)]
+#[delete("/api/todos/{index}")]
+async fn api_remove(list: web::ReqData<Arc<List>>, index: web::Path<u32>) -> Result<HttpResponse> {
+    let index = index.into_inner();
+    let todos = list.update(|todoer| {
+        todoer.remove_value(index)?;
+        Ok(Event::Removed { index })
+    })?;
+    Ok(HttpResponse::Ok().json(todos))
+}
+
+#[utoipa::path(
+    tag = "todos",
+    request_body = MoveTodos,
+    responses(
+        (status = 200, description = "Today's todos with the moved ones starting at `to`", body = DayList),
+        (status = 404, description = "No todo at one of the indices", body = String, content_type = "text/plain"),
+    )
+)]
+#[post("/api/todos/move")]
+async fn api_move(
+    list: web::ReqData<Arc<List>>,
+    body: web::Json<MoveTodos>,
+) -> Result<HttpResponse> {
+    let MoveTodos { indices, to } = body.into_inner();
+    let todos = list.update(|todoer| {
+        todoer.move_values(&indices, to)?;
+        Ok(Event::Reordered {
+            list: DayList::from(&*todoer),
+        })
+    })?;
+    Ok(HttpResponse::Ok().json(todos))
+}
+
+#[utoipa::path(
+    tag = "todos",
+    request_body = BulkChange,
+    responses(
+        (status = 200, description = "Today's todos after the change, made to every todo or to none", body = DayList),
+        (status = 400, description = "Unknown change or status", body = String, content_type = "text/plain"),
+        (status = 409, description = "One of the todos is no longer on the list", body = String, content_type = "text/plain"),
+    )
+)]
+#[post("/api/todos/bulk")]
+async fn api_bulk(
+    list: web::ReqData<Arc<List>>,
+    body: web::Json<BulkChange>,
+) -> Result<HttpResponse> {
+    let change = body.into_inner();
+    let todos = list.update(|todoer| {
+        change.apply(todoer)?;
+        Ok(Event::Changed {
+            list: DayList::from(&*todoer),
+        })
+    })?;
+    Ok(HttpResponse::Ok().json(todos))
+}
+
+#[utoipa::path(
+    tag = "todos",
+    request_body = SortTodos,
+    responses(
+        (status = 200, description = "Today's todos sorted", body = DayList),
+        (status = 400, description = "Unknown sort key", body = String, content_type = "text/plain"),
+    )
+)]
+#[post("/api/todos/sort")]
+async fn api_sort(
+    list: web::ReqData<Arc<List>>,
+    body: web::Json<SortTodos>,
+) -> Result<HttpResponse> {
+    let by: SortKey = body.by.parse()?;
+    let todos = list.update(|todoer| {
+        todoer.sort_values(by)?;
+        Ok(Event::Reordered {
+            list: DayList::from(&*todoer),
+        })
+    })?;
+    Ok(HttpResponse::Ok().json(todos))
+}
+
+#[utoipa::path(
+    tag = "days",
+    responses((status = 200, description = "Every day with a list, newest first", body = [DaySummary]))
+)]
+#[get("/api/days")]
+async fn api_days(list: web::ReqData<Arc<List>>) -> Result<HttpResponse> {
+    let days = list.view(list_days)?;
+    Ok(HttpResponse::Ok().json(days))
+}
+
+// Past days are always read from disk, today comes from the list
+#[utoipa::path(
+    tag = "days",
+    params(("day" = String, Path, description = "Name of the day, such as 2022-11-25")),
+    responses(
+        (status = 200, description = "The todos of that day", body = DayList),
+        (status = 400, description = "There is no list for that day", body = String, content_type = "text/plain"),
+    )
+)]
+#[get("/api/days/{day}")]
+async fn api_day(list: web::ReqData<Arc<List>>, day: web::Path<String>) -> Result<HttpResponse> {
+    let todos = list.view(|todoer| {
+        let config = day_config(&todoer.config, &day)?;
+        if config == todoer.config {
+            return Ok(DayList::from(todoer));
+        }
+        Ok(DayList::from(&Todoer::from_config(config, true)?))
+    })?;
+    Ok(HttpResponse::Ok().json(todos))
+}
+
+// Reads every list in the user's directory, with today's from the list
+#[utoipa::path(
+    tag = "days",
+    params(("days" = Option<u32>, Query, description = "How many days ahead to look, 7 when left out")),
+    responses((status = 200, description = "Overdue todos and todos due in the next days", body = AgendaList))
+)]
+#[get("/api/agenda")]
+async fn api_agenda(
+    list: web::ReqData<Arc<List>>,
+    query: web::Query<AgendaQuery>,
+) -> Result<HttpResponse> {
+    let days = query.days.unwrap_or(7);
+    let agenda = list.view(|todoer| {
+        let todo_dir = todoer
+            .config
+            .parent()
+            .map(PathBuf::from)
+            .unwrap_or_default();
+        let agenda = Agenda::load(&todo_dir, todoer, Local::now().date_naive(), days)?;
+        Ok(AgendaList::new(&agenda, days))
+    })?;
+    Ok(HttpResponse::Ok().json(agenda))
+}
+
+#[utoipa::path(
+    tag = "todos",
+    responses((status = 200, description = "Server-sent events for every change, see the README", body = String, content_type = "text/event-stream"))
+)]
+#[get("/events")]
+async fn event_feed(list: web::ReqData<Arc<List>>) -> HttpResponse {
+    HttpResponse::Ok()
+        .content_type("text/event-stream")
+        .insert_header((header::CACHE_CONTROL, "no-cache"))
+        .streaming(list.events.stream())
+}
+
+#[utoipa::path(
+    tag = "health",
+    responses((status = 200, description = "This document")),
+    security(())
+)]
+#[get("/openapi.json")]
+async fn openapi_json() -> HttpResponse {
+    HttpResponse::Ok().json(ApiDoc::openapi())
+}
+
+struct SecuritySchemes;
+
+impl Modify for SecuritySchemes {
+    fn modify(&self, openapi: &mut utoipa::openapi::OpenApi) {
+        let components = openapi.components.get_or_insert_with(Default::default);
+        components.add_security_scheme(
+            "bearer",
+            SecurityScheme::Http(
+                HttpBuilder::new()
+                    .scheme(HttpAuthScheme::Bearer)
+                    .description(Some(
+                        "`<user>:<token>` as printed by `todoer-server users add`",
+                    ))
+                    .build(),
+            ),
+        );
+        components.add_security_scheme(
+            "basic",
+            SecurityScheme::Http(
+                HttpBuilder::new()
+                    .scheme(HttpAuthScheme::Basic)
+                    .description(Some("The user name with the token as password"))
+                    .build(),
+            ),
+        );
+    }
+}
+
+// Served at /openapi.json, built from the handlers' annotations and the types in `api`
+#[derive(OpenApi)]
+#[openapi(
+    info(title = "todoer", description = "Daily todo lists"),
+    paths(
+        healthz,
+        readyz,
+        prometheus,
+        openapi_json,
+        print,
+        add,
+        complete,
+        remove,
+        api_todos,
+        api_add,
+        api_edit,
+        api_done,
+        api_undone,
+        api_status,
+        api_remove,
+        api_move,
+        api_sort,
+        api_bulk,
+        api_days,
+        api_day,
+        api_agenda,
+        event_feed,
+    ),
+    components(schemas(
+        TodoItem, DayList, DaySummary, TodoName, MoveTodos, SortTodos, SetStatus, TodoKey,
+        BulkChange, AgendaEntry, AgendaList
+    )),
+    modifiers(&SecuritySchemes),
+    security(("bearer" = []), ("basic" = []))
+)]
+pub struct ApiDoc;
+
+// Every route, with a `Store` in the app data. Only health, metrics and the API document are reachable without credentials
+pub fn routes(cfg: &mut web::ServiceConfig) {
+    cfg.service(healthz)
+        .service(readyz)
+        .service(prometheus)
+        .service(openapi_json)
+        .route("/.well-known/caldav", web::to(caldav::well_known))
+        .service(web::scope("").wrap(Auth).configure(user_routes));
+}
+
+fn user_routes(cfg: &mut web::ServiceConfig) {
+    cfg.service(print)
+        .service(add)
+        .service(complete)
+        .service(remove)
+        .service(api_todos)
+        .service(api_add)
+        .service(api_move)
+        .service(api_sort)
+        .service(api_bulk)
+        .service(api_edit)
+        .service(api_done)
+        .service(api_undone)
+        .service(api_status)
+        .service(api_remove)
+        .service(api_days)
+        .service(api_day)
+        .service(api_agenda)
+        .service(event_feed)
+        .configure(caldav::routes);
+}
+
+async fn watch(store: web::Data<Store>) {
+    let mut interval = actix_web::rt::time::interval(Duration::from_secs(1));
+    loop {
+        interval.tick().await;
+        store.sync();
+    }
+}
+
+pub fn init_tracing(settings: &Settings) -> Result<()> {
+    let filter = EnvFilter::try_new(&settings.log_level).map_err(|err| {
+        TodoerError::Config(format!("invalid log level {}: {}", settings.log_level, err))
+    })?;
+    let subscriber = tracing_subscriber::fmt().with_env_filter(filter);
+    let result = if settings.log_json {
+        subscriber.json().try_init()
+    } else {
+        subscriber.try_init()
+    };
+    result.map_err(|err| TodoerError::Config(err.to_string()))
+}
+
+pub async fn run(settings: Settings) -> std::io::Result<()> {
+    init_tracing(&settings).map_err(std::io::Error::other)?;
+    if Users::load(&users_file(&settings.root))
+        .map_err(std::io::Error::other)?
+        .is_empty()
+    {
+        if settings.allow_anonymous {
+            tracing::warn!(
+                "no users added, anyone can reach the list, see `todoer-server users add`"
+            );
+        } else {
+            tracing::warn!(
+                "no users added, every request is refused, see `todoer-server users add`"
+            );
+        }
+    }
+    let store = web::Data::new(Store::new(
+        settings.root.clone(),
+        settings.state,
+        settings.allow_anonymous,
+    ));
+    if settings.state == StateMode::Watch {
+        actix_web::rt::spawn(watch(store.clone()));
+    }
+
+    let app_settings = settings.clone();
+    let server = HttpServer::new(move || {
+        App::new()
+            .app_data(store.clone())
+            .app_data(web::PayloadConfig::new(app_settings.max_body))
+            .app_data(web::JsonConfig::default().limit(app_settings.max_body))
+            // Outside of auth so preflight requests, which carry no credentials, are answered
+            .wrap(Condition::new(
+                !app_settings.cors_origins.is_empty(),
+                app_settings.cors(),
+            ))
+            .wrap(Telemetry)
+            .configure(routes)
+    });
+    let server = match settings.workers {
+        Some(workers) => server.workers(workers),
+        None => server,
+    };
+
+    #[cfg(feature = "tls")]
+    let server = match settings.tls_config().map_err(std::io::Error::other)? {
+        Some(tls) => server.bind_rustls(settings.address(), tls)?,
+        None => server.bind(settings.address())?,
+    };
+    #[cfg(not(feature = "tls"))]
+    let server = server.bind(settings.address())?;
+
+    server.run().await
+}
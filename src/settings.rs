@@ -0,0 +1,302 @@
+use std::path::PathBuf;
+use std::str::FromStr;
+
+use actix_cors::Cors;
+use actix_web::http::Method;
+use clap::Args;
+use serde::{Deserialize, Serialize};
+
+use crate::error::{Result, TodoerError};
+
+// How the server keeps today's list between requests
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum StateMode {
+    // Read from disk on every request, so every other writer is always seen
+    File,
+    // Read once and kept in memory, edits made outside the server are not seen
+    Memory,
+    // Kept in memory and reloaded when the file changes on disk
+    Watch,
+}
+
+impl FromStr for StateMode {
+    type Err = TodoerError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "file" => Ok(StateMode::File),
+            "memory" => Ok(StateMode::Memory),
+            "watch" => Ok(StateMode::Watch),
+            _ => Err(TodoerError::Config(format!(
+                "unknown state {}, expected file, memory or watch",
+                s
+            ))),
+        }
+    }
+}
+
+// How the server is run. Read from a JSON file, then PORT, then the command line, each overriding the last
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Settings {
+    pub bind: String,
+    pub port: u16,
+    // The directory holding wiki/todo, or users/ and users.json once users are added
+    pub root: PathBuf,
+    // One per CPU core when not set
+    pub workers: Option<usize>,
+    // Largest request body accepted, in bytes
+    pub max_body: usize,
+    // Origins allowed to call the API from a browser, `*` allows any
+    pub cors_origins: Vec<String>,
+    pub tls_cert: Option<PathBuf>,
+    pub tls_key: Option<PathBuf>,
+    pub state: StateMode,
+    // A tracing filter such as `info` or `warn,rust=debug`
+    pub log_level: String,
+    // One JSON object per line instead of human readable logs
+    pub log_json: bool,
+    // With no users added, serve the list in `wiki/todo` to anyone instead of refusing every request
+    pub allow_anonymous: bool,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Settings {
+            bind: String::from("0.0.0.0"),
+            port: 3000,
+            root: PathBuf::from("."),
+            workers: None,
+            max_body: 64 * 1024,
+            cors_origins: Vec::new(),
+            tls_cert: None,
+            tls_key: None,
+            state: StateMode::Watch,
+            log_level: String::from("info"),
+            log_json: false,
+            allow_anonymous: false,
+        }
+    }
+}
+
+#[derive(Debug, Default, Args)]
+pub struct SettingsOpts {
+    #[clap(long = "config")]
+    pub config: Option<PathBuf>,
+    #[clap(long = "bind")]
+    pub bind: Option<String>,
+    #[clap(short = 'p', long = "port")]
+    pub port: Option<u16>,
+    #[clap(long = "root")]
+    pub root: Option<PathBuf>,
+    #[clap(long = "workers")]
+    pub workers: Option<usize>,
+    #[clap(long = "max-body")]
+    pub max_body: Option<usize>,
+    #[clap(long = "cors-origin")]
+    pub cors_origins: Vec<String>,
+    #[clap(long = "tls-cert")]
+    pub tls_cert: Option<PathBuf>,
+    #[clap(long = "tls-key")]
+    pub tls_key: Option<PathBuf>,
+    #[clap(long = "state")]
+    pub state: Option<StateMode>,
+    #[clap(long = "log-level")]
+    pub log_level: Option<String>,
+    #[clap(long = "log-json")]
+    pub log_json: bool,
+    #[clap(long = "allow-anonymous")]
+    pub allow_anonymous: bool,
+}
+
+impl Settings {
+    pub fn from_file(path: &PathBuf) -> Result<Self> {
+        let contents = std::fs::read_to_string(path)?;
+        serde_json::from_str(&contents).map_err(|err| TodoerError::Parse {
+            line: err.line(),
+            message: err.to_string(),
+        })
+    }
+
+    pub fn address(&self) -> (&str, u16) {
+        (&self.bind, self.port)
+    }
+
+    pub fn is_tls(&self) -> bool {
+        self.tls_cert.is_some()
+    }
+
+    // Only browsers on the listed origins may call the API from other sites. Any origin may only
+    // make requests without credentials, so other sites can't act as a signed in user
+    pub fn cors(&self) -> Cors {
+        let cors = Cors::default()
+            .allowed_methods([Method::GET, Method::POST, Method::PUT, Method::DELETE])
+            .allow_any_header();
+        if self.cors_origins.iter().any(|origin| origin == "*") {
+            return cors.allow_any_origin();
+        }
+        self.cors_origins
+            .iter()
+            .fold(cors.supports_credentials(), |cors, origin| {
+                cors.allowed_origin(origin)
+            })
+    }
+
+    #[cfg(feature = "tls")]
+    pub fn tls_config(&self) -> Result<Option<rustls::ServerConfig>> {
+        use std::io::BufReader;
+
+        use rustls_pemfile::Item;
+
+        let (Some(cert), Some(key)) = (&self.tls_cert, &self.tls_key) else {
+            return Ok(None);
+        };
+        let certs = rustls_pemfile::certs(&mut BufReader::new(std::fs::File::open(cert)?))?
+            .into_iter()
+            .map(rustls::Certificate)
+            .collect();
+        let key = rustls_pemfile::read_all(&mut BufReader::new(std::fs::File::open(key)?))?
+            .into_iter()
+            .find_map(|item| match item {
+                Item::PKCS8Key(key) | Item::RSAKey(key) | Item::ECKey(key) => Some(key),
+                _ => None,
+            })
+            .ok_or_else(|| {
+                TodoerError::Config(format!("no private key found in {}", key.display()))
+            })?;
+
+        let config = rustls::ServerConfig::builder()
+            .with_safe_defaults()
+            .with_no_client_auth()
+            .with_single_cert(certs, rustls::PrivateKey(key))
+            .map_err(|err| TodoerError::Config(format!("invalid certificate: {}", err)))?;
+        Ok(Some(config))
+    }
+}
+
+impl TryFrom<SettingsOpts> for Settings {
+    type Error = TodoerError;
+
+    fn try_from(opts: SettingsOpts) -> Result<Self> {
+        let mut settings = match &opts.config {
+            Some(config) => Settings::from_file(config)?,
+            None => Settings::default(),
+        };
+
+        if let Ok(port) = std::env::var("PORT") {
+            settings.port = port
+                .parse()
+                .map_err(|_| TodoerError::Config(format!("invalid PORT {}", port)))?;
+        }
+
+        if let Some(bind) = opts.bind {
+            settings.bind = bind;
+        }
+        if let Some(port) = opts.port {
+            settings.port = port;
+        }
+        if let Some(root) = opts.root {
+            settings.root = root;
+        }
+        if opts.workers.is_some() {
+            settings.workers = opts.workers;
+        }
+        if let Some(max_body) = opts.max_body {
+            settings.max_body = max_body;
+        }
+        if !opts.cors_origins.is_empty() {
+            settings.cors_origins = opts.cors_origins;
+        }
+        if opts.tls_cert.is_some() {
+            settings.tls_cert = opts.tls_cert;
+        }
+        if opts.tls_key.is_some() {
+            settings.tls_key = opts.tls_key;
+        }
+        if let Some(state) = opts.state {
+            settings.state = state;
+        }
+        if let Some(log_level) = opts.log_level {
+            settings.log_level = log_level;
+        }
+        if opts.log_json {
+            settings.log_json = true;
+        }
+        if opts.allow_anonymous {
+            settings.allow_anonymous = true;
+        }
+
+        if settings.workers == Some(0) {
+            return Err(TodoerError::Config(String::from(
+                "workers must be at least 1",
+            )));
+        }
+        if settings.tls_cert.is_some() != settings.tls_key.is_some() {
+            return Err(TodoerError::Config(String::from(
+                "tls_cert and tls_key must be set together",
+            )));
+        }
+        if settings.is_tls() && !cfg!(feature = "tls") {
+            return Err(TodoerError::Config(String::from(
+                "TLS needs the server built with --features tls",
+            )));
+        }
+        Ok(settings)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::path::PathBuf;
+
+    use super::{Settings, SettingsOpts, StateMode};
+
+    #[test]
+    fn file_then_flags() {
+        let mut path = std::env::temp_dir();
+        path.push(format!("todoer-settings-{}.json", std::process::id()));
+        std::fs::write(
+            &path,
+            r#"{"bind": "127.0.0.1", "root": "/srv/todo", "workers": 2, "cors_origins": ["https://example.com"]}"#,
+        )
+        .unwrap();
+
+        let settings = Settings::try_from(SettingsOpts {
+            config: Some(path.clone()),
+            port: Some(8080),
+            workers: Some(4),
+            ..SettingsOpts::default()
+        })
+        .unwrap();
+        assert_eq!(settings.address(), ("127.0.0.1", 8080));
+        assert_eq!(settings.root, PathBuf::from("/srv/todo"));
+        assert_eq!(settings.workers, Some(4));
+        assert_eq!(settings.max_body, 64 * 1024);
+        assert_eq!(settings.cors_origins, vec!["https://example.com"]);
+        assert_eq!(settings.state, StateMode::Watch);
+        assert!(!settings.allow_anonymous);
+
+        std::fs::write(&path, r#"{"prot": 80}"#).unwrap();
+        assert!(Settings::try_from(SettingsOpts {
+            config: Some(path),
+            ..SettingsOpts::default()
+        })
+        .is_err());
+    }
+
+    #[test]
+    fn invalid() {
+        assert!(Settings::try_from(SettingsOpts {
+            tls_cert: Some(PathBuf::from("cert.pem")),
+            ..SettingsOpts::default()
+        })
+        .is_err());
+        assert!(Settings::try_from(SettingsOpts {
+            workers: Some(0),
+            ..SettingsOpts::default()
+        })
+        .is_err());
+        assert!("disk".parse::<StateMode>().is_err());
+    }
+}
@@ -0,0 +1,347 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use crate::error::{Result, TodoerError};
+use crate::todoer::{Status, Todo, Todoer, Todos, TIME_FORMAT};
+
+// Runs git in `dir`, failing with what git printed
+fn git(dir: &Path, args: &[&str]) -> Result<String> {
+    let output = Command::new("git").arg("-C").arg(dir).args(args).output()?;
+    if !output.status.success() {
+        return Err(TodoerError::Io(std::io::Error::other(format!(
+            "git {} failed: {}",
+            args.join(" "),
+            String::from_utf8_lossy(&output.stderr).trim()
+        ))));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+// On when the todo directory is in a git work tree with `git config todoer.sync true`
+pub fn is_enabled(dir: &Path) -> bool {
+    // Looking for the repository first saves running git for everyone not using sync
+    if !dir.ancestors().any(|dir| dir.join(".git").exists()) {
+        return false;
+    }
+    git(dir, &["config", "--bool", "todoer.sync"]).is_ok_and(|value| value == "true")
+}
+
+fn entries(todoer: &Todoer) -> Vec<(String, Status)> {
+    let Todos(todos) = &todoer.data;
+    (0..todoer.size)
+        .filter_map(|index| todos.get(&index))
+        .map(|todo| (todo.name.clone(), todo.status))
+        .collect()
+}
+
+// A commit message for the change from `before` to `after`, such as `done: write CLI`
+pub fn describe(before: Option<&Todoer>, after: &Todoer) -> String {
+    let before = before.map(entries).unwrap_or_default();
+    let after = entries(after);
+    let find = |entries: &[(String, Status)], name: &str| {
+        entries
+            .iter()
+            .find(|(other, _)| other == name)
+            .map(|(_, status)| *status)
+    };
+
+    let added: Vec<&str> = after
+        .iter()
+        .filter(|(name, _)| find(&before, name).is_none())
+        .map(|(name, _)| name.as_str())
+        .collect();
+    let removed: Vec<&str> = before
+        .iter()
+        .filter(|(name, _)| find(&after, name).is_none())
+        .map(|(name, _)| name.as_str())
+        .collect();
+    // Named after the new status, reopening a done todo is an undo
+    let changed = |status: Status, undo: bool| -> Vec<&str> {
+        after
+            .iter()
+            .filter(|(name, now)| {
+                let was = find(&before, name);
+                *now == status
+                    && was.is_some_and(|was| was != status)
+                    && (status != Status::Open || (was == Some(Status::Done)) == undo)
+            })
+            .map(|(name, _)| name.as_str())
+            .collect()
+    };
+
+    let mut parts = Vec::new();
+    if let ([old], [new]) = (removed.as_slice(), added.as_slice()) {
+        parts.push(format!("edit: {} -> {}", old, new));
+    } else {
+        if !added.is_empty() {
+            parts.push(format!("add: {}", added.join(", ")));
+        }
+        if !removed.is_empty() {
+            parts.push(format!("remove: {}", removed.join(", ")));
+        }
+    }
+    for status in Status::ALL {
+        let label = match status {
+            Status::Open => String::from("reopen"),
+            status => status.to_string(),
+        };
+        let names = changed(status, false);
+        if !names.is_empty() {
+            parts.push(format!("{}: {}", label, names.join(", ")));
+        }
+    }
+    let undone = changed(Status::Open, true);
+    if !undone.is_empty() {
+        parts.push(format!("undo: {}", undone.join(", ")));
+    }
+    if parts.is_empty() && before != after {
+        parts.push(String::from("reorder"));
+    }
+    if parts.is_empty() {
+        parts.push(String::from("update"));
+    }
+    parts.join("; ")
+}
+
+// Commits the day file on its own, leaving anything else in the repository alone
+pub fn commit(config: &Path, message: &str) -> Result<()> {
+    let dir = config.parent().map(PathBuf::from).unwrap_or_default();
+    let file = config.to_string_lossy();
+    git(&dir, &["add", "--", &file])?;
+    // Nothing to commit when the file was saved unchanged
+    if git(&dir, &["diff", "--cached", "--quiet", "--", &file]).is_ok() {
+        return Ok(());
+    }
+    git(&dir, &["commit", "-q", "-m", message, "--", &file])?;
+    Ok(())
+}
+
+fn parse(contents: &str) -> Result<Vec<Todo>> {
+    if contents.trim().is_empty() {
+        return Ok(Vec::new());
+    }
+    let todoer = Todoer::from_contents(Path::new(""), contents)?;
+    let Todos(mut todos) = todoer.data;
+    Ok((0..todoer.size)
+        .filter_map(|index| todos.remove(&index))
+        .collect())
+}
+
+// Who a todo is in every version of a file: its name and when it was added, which unlike an ID
+// every todo has from the start. Todos sharing both are numbered in order so none of them is lost
+fn keyed(todos: Vec<Todo>) -> Vec<(String, Todo)> {
+    let mut seen: HashMap<String, u32> = HashMap::new();
+    todos
+        .into_iter()
+        .map(|todo| {
+            let created = todo
+                .created
+                .map(|created| created.format(TIME_FORMAT).to_string())
+                .unwrap_or_default();
+            let key = format!("{} {}", created, todo.name);
+            let count = seen.entry(key.clone()).or_default();
+            *count += 1;
+            (format!("{} {}", count, key), todo)
+        })
+        .collect()
+}
+
+// Merges two versions of a day file that both changed since `base`, which is empty when the file
+// was created on both sides. Todos are matched with `keyed`: removals and status changes on either
+// side win, as do edits on their side of todos we left alone, and todos added on either side are
+// kept, ours first
+pub fn merge_day(base: &str, ours: &str, theirs: &str) -> Result<String> {
+    // Notes are kept as they are on our side
+    let Todoer { config, notes, .. } = Todoer::from_contents(Path::new(""), ours)?;
+    let base: HashMap<String, Todo> = keyed(parse(base)?).into_iter().collect();
+    let ours = keyed(parse(ours)?);
+    let theirs_todos = keyed(parse(theirs)?);
+    let theirs: HashMap<&str, &Todo> = theirs_todos
+        .iter()
+        .map(|(key, todo)| (key.as_str(), todo))
+        .collect();
+
+    let mut merged = Vec::new();
+    for (key, todo) in &ours {
+        let todo = match (base.get(key), theirs.get(key.as_str())) {
+            // Removed by them
+            (Some(_), None) => continue,
+            (Some(was), Some(their)) if was == todo || was.status != their.status => {
+                (*their).clone()
+            }
+            (None, Some(their)) if their.is_done() && !todo.is_done() => (*their).clone(),
+            _ => todo.clone(),
+        };
+        merged.push(todo);
+    }
+    for (key, todo) in &theirs_todos {
+        if !base.contains_key(key) && !ours.iter().any(|(other, _)| other == key) {
+            merged.push(todo.clone());
+        }
+    }
+
+    let todoer = Todoer {
+        config,
+        size: merged.len().try_into().unwrap(),
+        done_count: merged
+            .iter()
+            .filter(|todo| todo.is_done())
+            .count()
+            .try_into()
+            .unwrap(),
+        data: Todos((0..).zip(merged).collect()),
+        notes,
+    };
+    String::try_from(&todoer)
+}
+
+// Merges `reference` into the current branch, resolving conflicting day files with `merge_day`
+fn merge(root: &Path, reference: &str) -> Result<()> {
+    // Clones of an empty remote each start their own history
+    let merged = git(
+        root,
+        &[
+            "merge",
+            "-q",
+            "--no-edit",
+            "--allow-unrelated-histories",
+            reference,
+        ],
+    );
+    let Err(err) = merged else {
+        return Ok(());
+    };
+    let conflicted = git(root, &["diff", "--name-only", "--diff-filter=U"])?;
+    if conflicted.is_empty() {
+        return Err(err);
+    }
+    let resolve = |path: &str| -> Result<()> {
+        let stage = |n: u8| git(root, &["show", &format!(":{}:{}", n, path)]).unwrap_or_default();
+        if !path.ends_with(".md") {
+            return Err(TodoerError::Conflict(format!(
+                "{} can't be merged automatically",
+                path
+            )));
+        }
+        let merged = merge_day(&stage(1), &stage(2), &stage(3))?;
+        std::fs::write(root.join(path), merged)?;
+        git(root, &["add", "--", path])?;
+        Ok(())
+    };
+    if let Err(err) = conflicted.lines().try_for_each(resolve) {
+        git(root, &["merge", "--abort"])?;
+        return Err(err);
+    }
+    git(root, &["commit", "-q", "--no-edit"])?;
+    Ok(())
+}
+
+// Commits pending changes in the todo directory, merges the upstream branch and pushes.
+// Without an upstream the first remote is used. Returns what was synced with
+pub fn sync(dir: &Path) -> Result<String> {
+    let root = PathBuf::from(git(dir, &["rev-parse", "--show-toplevel"])?);
+    let pending = git(dir, &["status", "--porcelain", "--", "."])?;
+    if !pending.is_empty() {
+        git(dir, &["add", "-A", "--", "."])?;
+        git(dir, &["commit", "-q", "-m", "sync: local changes"])?;
+    }
+
+    let branch = git(&root, &["rev-parse", "--abbrev-ref", "HEAD"])?;
+    let (remote, reference) = match git(&root, &["rev-parse", "--abbrev-ref", "@{u}"]) {
+        Ok(upstream) => {
+            let remote = upstream.split_once('/').map(|(remote, _)| remote);
+            (remote.unwrap_or("origin").to_string(), upstream)
+        }
+        Err(_) => {
+            let remotes = git(&root, &["remote"])?;
+            let remote = remotes
+                .lines()
+                .next()
+                .ok_or_else(|| TodoerError::Config(String::from("no git remote to sync with")))?;
+            (remote.to_string(), format!("{}/{}", remote, branch))
+        }
+    };
+
+    git(&root, &["fetch", "-q", &remote])?;
+    // The remote branch doesn't exist yet on the first sync
+    if git(&root, &["rev-parse", "-q", "--verify", &reference]).is_ok() {
+        merge(&root, &reference)?;
+    }
+    git(&root, &["push", "-q", "-u", &remote, &branch])?;
+    Ok(format!("synced with {}", reference))
+}
+
+#[cfg(test)]
+mod test {
+    use std::path::PathBuf;
+
+    use super::{describe, merge_day};
+    use crate::todoer::{Status, Todoer};
+
+    #[test]
+    fn messages() {
+        let mut before = Todoer::default_todoer(PathBuf::from("wiki/todo/2022-11-25.md"));
+        before.set_value(String::from("write CLI"));
+        before.set_value(String::from("buy milk"));
+        let mut after = Todoer::default_todoer(PathBuf::from("wiki/todo/2022-11-25.md"));
+        after.set_value(String::from("write CLI"));
+        after.set_value(String::from("buy milk"));
+
+        after.mark_done(0).unwrap();
+        assert_eq!(describe(Some(&before), &after), "done: write CLI");
+        after.set_value(String::from("call bob"));
+        assert_eq!(
+            describe(Some(&before), &after),
+            "add: call bob; done: write CLI"
+        );
+        assert_eq!(describe(None, &after), "add: write CLI, buy milk, call bob");
+        let mut later = Todoer::default_todoer(PathBuf::from("wiki/todo/2022-11-25.md"));
+        later.set_value(String::from("write CLI"));
+        later.set_value(String::from("buy milk"));
+        later.set_value(String::from("call bob"));
+        later.set_status(1, Status::Blocked).unwrap();
+        assert_eq!(
+            describe(Some(&after), &later),
+            "blocked: buy milk; undo: write CLI"
+        );
+
+        let mut after = Todoer::default_todoer(PathBuf::from("wiki/todo/2022-11-25.md"));
+        after.set_value(String::from("buy milk"));
+        after.set_value(String::from("write CLI"));
+        assert_eq!(describe(Some(&before), &after), "reorder");
+        after.edit_value(0, String::from("buy oat milk")).unwrap();
+        assert_eq!(
+            describe(Some(&before), &after),
+            "edit: buy milk -> buy oat milk"
+        );
+    }
+
+    #[test]
+    fn merge() {
+        let base = "2022-11-25\n0/3\n- [ ] foo\n- [ ] bar\n- [ ] baz\n";
+        let ours = "2022-11-25\n1/3\n- [x] foo\n- [ ] bar\n- [ ] baz\n- [ ] ours\n";
+        let theirs = "2022-11-25\n1/3\n- [ ] foo\n- [x] bar\n- [ ] theirs\n";
+        assert_eq!(
+            merge_day(base, ours, theirs).unwrap(),
+            "2022-11-25\n2/4\n- [x] foo\n- [x] bar\n- [ ] ours\n- [ ] theirs\n"
+        );
+
+        // Created on both sides
+        let ours = "2022-11-25\n0/1\n- [ ] foo\n";
+        let theirs = "2022-11-25\n1/2\n- [x] foo\n- [ ] bar\n";
+        assert_eq!(
+            merge_day("", ours, theirs).unwrap(),
+            "2022-11-25\n1/2\n- [x] foo\n- [ ] bar\n"
+        );
+
+        // Todos with the same name stay apart
+        let base = "2022-11-25\n0/2\n- [ ] foo created:2022-11-25T09:00\n- [ ] foo created:2022-11-25T10:00\n";
+        let ours = "2022-11-25\n1/2\n- [x] foo created:2022-11-25T09:00 done:2022-11-25T11:00\n- [ ] foo created:2022-11-25T10:00\n- [ ] foo created:2022-11-25T11:30\n";
+        let theirs = "2022-11-25\n0/2\n- [ ] foo created:2022-11-25T09:00\n- [/] foo created:2022-11-25T10:00\n- [ ] foo created:2022-11-25T11:45\n";
+        assert_eq!(
+            merge_day(base, ours, theirs).unwrap(),
+            "2022-11-25\n1/4\n- [x] foo created:2022-11-25T09:00 done:2022-11-25T11:00\n- [/] foo created:2022-11-25T10:00\n- [ ] foo created:2022-11-25T11:30\n- [ ] foo created:2022-11-25T11:45\n"
+        );
+    }
+}
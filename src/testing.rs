@@ -0,0 +1,13 @@
+use std::path::PathBuf;
+
+// An empty todo directory for one test. `prefix` names the module and `name` the test, so tests
+// running at once never share files. Files kept next to the todo directory, such as `timer.json`,
+// go in the directory above, which is emptied as well
+pub fn todo_dir(prefix: &str, name: &str) -> PathBuf {
+    let mut dir = std::env::temp_dir();
+    dir.push(format!("todoer-{}-{}-{}", prefix, name, std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    let todo_dir = dir.join("todo");
+    std::fs::create_dir_all(&todo_dir).unwrap();
+    todo_dir
+}
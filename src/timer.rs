@@ -0,0 +1,306 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+use chrono::{Duration, NaiveDate, NaiveDateTime, NaiveTime};
+use serde::{Deserialize, Serialize};
+
+use crate::error::{Result, TodoerError};
+use crate::todoer::{day_of, format_duration, now, Todoer, Todos, DAY_FORMAT};
+use crate::todoers::Todoers;
+
+// Time spent on a todo, written as `timer:09:00-10:30` after the name. Both ends are on the
+// day of the file, an end before the start means the timer ran past midnight
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Interval {
+    pub start: NaiveTime,
+    pub end: NaiveTime,
+}
+
+impl Interval {
+    pub fn parse(value: &str) -> Option<Self> {
+        let (start, end) = value.split_once('-')?;
+        Some(Interval {
+            start: NaiveTime::parse_from_str(start, "%H:%M").ok()?,
+            end: NaiveTime::parse_from_str(end, "%H:%M").ok()?,
+        })
+    }
+
+    pub fn duration(&self) -> Duration {
+        let duration = self.end - self.start;
+        if duration < Duration::zero() {
+            duration + Duration::days(1)
+        } else {
+            duration
+        }
+    }
+}
+
+impl fmt::Display for Interval {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}-{}",
+            self.start.format("%H:%M"),
+            self.end.format("%H:%M")
+        )
+    }
+}
+
+// The running timer, kept next to the todo directory in `timer.json` so it outlives the command
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Timer {
+    // The day file of the todo being timed, which is found again by name
+    pub config: PathBuf,
+    pub name: String,
+    pub started: NaiveDateTime,
+}
+
+pub fn timer_file(todo_dir: &Path) -> PathBuf {
+    todo_dir.with_file_name("timer.json")
+}
+
+impl Timer {
+    // None when no timer is running
+    pub fn load(path: &Path) -> Result<Option<Self>> {
+        if std::fs::metadata(path).is_err() {
+            return Ok(None);
+        }
+        let contents = std::fs::read_to_string(path)?;
+        serde_json::from_str(&contents)
+            .map(Some)
+            .map_err(|err| TodoerError::Parse {
+                line: err.line(),
+                message: err.to_string(),
+            })
+    }
+
+    fn save(&self, path: &Path) -> Result<()> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(path, serde_json::to_string_pretty(self).unwrap() + "\n")?;
+        Ok(())
+    }
+
+    pub fn elapsed(&self) -> Duration {
+        now() - self.started
+    }
+
+    pub fn is_on(&self, todoer: &Todoer, index: u32) -> bool {
+        self.config == todoer.config
+            && todoer
+                .data
+                .0
+                .get(&index)
+                .is_some_and(|todo| todo.name == self.name)
+    }
+}
+
+// Starts timing the todo at `index`. Only one timer runs at a time, so a running one is stopped
+// first and returned as by `stop`
+pub fn start(todoer: &Todoer, index: u32, path: &Path) -> Result<Option<(String, Duration)>> {
+    let todo = todoer
+        .data
+        .0
+        .get(&index)
+        .ok_or(TodoerError::NotFound(index))?;
+    if todo.status.is_closed() {
+        return Err(TodoerError::Conflict(format!(
+            "todo {} is already {}",
+            index, todo.status
+        )));
+    }
+    let name = todo.name.clone();
+    let stopped = stop(path)?;
+    let timer = Timer {
+        config: todoer.config.clone(),
+        name,
+        started: now(),
+    };
+    timer.save(path)?;
+    Ok(stopped)
+}
+
+// Stops the running timer and records the time against its todo, returning the todo's name and
+// the time spent. None when no timer was running. A timer that ran a day or more doesn't fit an
+// `Interval`, so nothing is recorded and the error says how long it ran
+pub fn stop(path: &Path) -> Result<Option<(String, Duration)>> {
+    let Some(timer) = Timer::load(path)? else {
+        return Ok(None);
+    };
+    let mut todoer = Todoer::from_config(timer.config.clone(), true)?;
+    let index = todoer.find_value(&timer.name);
+    let Some(todo) = index.and_then(|index| todoer.data.0.get_mut(&index)) else {
+        std::fs::remove_file(path)?;
+        return Err(TodoerError::Conflict(format!(
+            "{} was renamed or removed, {} were not recorded",
+            timer.name,
+            format_duration(timer.elapsed())
+        )));
+    };
+    let stopped = now();
+    let elapsed = stopped - timer.started;
+    if elapsed >= Duration::days(1) {
+        std::fs::remove_file(path)?;
+        return Err(TodoerError::Conflict(format!(
+            "{} ran for a day or more, {} were not recorded",
+            timer.name,
+            format_duration(elapsed)
+        )));
+    }
+    todo.tracked.push(Interval {
+        start: timer.started.time(),
+        end: stopped.time(),
+    });
+    todoer.save_and_commit()?;
+    std::fs::remove_file(path)?;
+    Ok(Some((timer.name, elapsed)))
+}
+
+// Time tracked between `from` and `to`, both included, per todo and per tag with the most first
+pub fn timesheet(todoers: &Todoers, from: NaiveDate, to: NaiveDate) -> String {
+    let mut items: Vec<(String, Duration)> = Vec::new();
+    let mut tags: HashMap<String, Duration> = HashMap::new();
+    let mut total = Duration::zero();
+    for todoer in &todoers.todoers {
+        if !day_of(&todoer.config).is_some_and(|day| from <= day && day <= to) {
+            continue;
+        }
+        let Todos(todos) = &todoer.data;
+        for index in 0..todoer.size {
+            let Some(todo) = todos.get(&index) else {
+                continue;
+            };
+            let tracked = todo.tracked_time();
+            if tracked.is_zero() {
+                continue;
+            }
+            match items.iter_mut().find(|(name, _)| *name == todo.name) {
+                Some((_, time)) => *time = *time + tracked,
+                None => items.push((todo.name.clone(), tracked)),
+            }
+            for tag in todo.tags() {
+                let time = tags
+                    .entry(format!("#{}", tag))
+                    .or_insert_with(Duration::zero);
+                *time = *time + tracked;
+            }
+            total = total + tracked;
+        }
+    }
+
+    let range = format!("{} to {}", from.format(DAY_FORMAT), to.format(DAY_FORMAT));
+    if items.is_empty() {
+        return format!("Nothing tracked from {}\n", range);
+    }
+    let mut tags: Vec<(String, Duration)> = tags.into_iter().collect();
+    // Stable, so ties keep the order they were first tracked in
+    items.sort_by_key(|(_, time)| std::cmp::Reverse(*time));
+    tags.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+
+    let mut res = format!("Timesheet {}\n", range);
+    for (name, time) in &items {
+        res += &format!("{:>8}  {}\n", format_duration(*time), name);
+    }
+    if !tags.is_empty() {
+        res += "\nBy tag\n";
+        for (tag, time) in &tags {
+            res += &format!("{:>8}  {}\n", format_duration(*time), tag);
+        }
+    }
+    res += &format!("\nTotal {}\n", format_duration(total));
+    res
+}
+
+#[cfg(test)]
+mod test {
+    use chrono::{Duration, NaiveDate};
+
+    use super::{start, stop, timer_file, timesheet, Interval, Timer};
+    use crate::error::TodoerError;
+    use crate::testing::todo_dir;
+    use crate::todoer::Todoer;
+    use crate::todoers::Todoers;
+
+    #[test]
+    fn intervals() {
+        let interval = Interval::parse("09:00-10:30").unwrap();
+        assert_eq!(interval.duration().num_minutes(), 90);
+        assert_eq!(interval.to_string(), "09:00-10:30");
+        let interval = Interval::parse("23:30-00:15").unwrap();
+        assert_eq!(interval.duration().num_minutes(), 45);
+        assert!(Interval::parse("9-10").is_none());
+    }
+
+    #[test]
+    fn start_and_stop() {
+        let todo_dir = todo_dir("timer", "start");
+        let path = timer_file(&todo_dir);
+        let mut todoer = Todoer::default_todoer(todo_dir.join("2022-11-25.md"));
+        todoer.set_value(String::from("foo"));
+        todoer.set_value(String::from("bar"));
+        todoer.save().unwrap();
+
+        assert_eq!(start(&todoer, 0, &path).unwrap(), None);
+        let timer = Timer::load(&path).unwrap().unwrap();
+        assert_eq!(timer.name, "foo");
+        assert!(timer.is_on(&todoer, 0));
+
+        // Starting another one stops the first
+        let (name, _) = start(&todoer, 1, &path).unwrap().unwrap();
+        assert_eq!(name, "foo");
+        let (name, _) = stop(&path).unwrap().unwrap();
+        assert_eq!(name, "bar");
+        assert_eq!(stop(&path).unwrap(), None);
+
+        let todoer = Todoer::from_config(todo_dir.join("2022-11-25.md"), true).unwrap();
+        assert_eq!(todoer.data.0[&0].tracked.len(), 1);
+        assert_eq!(todoer.data.0[&1].tracked.len(), 1);
+
+        // Left running over a day, which an interval can't hold
+        start(&todoer, 0, &path).unwrap();
+        let mut timer = Timer::load(&path).unwrap().unwrap();
+        timer.started -= Duration::hours(25);
+        timer.save(&path).unwrap();
+        match stop(&path) {
+            Err(TodoerError::Conflict(message)) => {
+                assert_eq!(message, "foo ran for a day or more, 25h were not recorded")
+            }
+            other => panic!("expected a conflict but got {:?}", other),
+        }
+        assert_eq!(Timer::load(&path).unwrap(), None);
+        let todoer = Todoer::from_config(todo_dir.join("2022-11-25.md"), true).unwrap();
+        assert_eq!(todoer.data.0[&0].tracked.len(), 1);
+    }
+
+    #[test]
+    fn report() {
+        let todo_dir = todo_dir("timer", "report");
+        std::fs::write(
+            todo_dir.join("2022-11-24.md"),
+            "2022-11-24\n0/2\n- [ ] write CLI #work timer:09:00-10:00\n- [ ] call bob timer:11:00-11:20\n",
+        )
+        .unwrap();
+        std::fs::write(
+            todo_dir.join("2022-11-25.md"),
+            "2022-11-25\n1/1\n- [x] write CLI #work timer:09:00-09:30 timer:13:00-13:15\n",
+        )
+        .unwrap();
+        let todoers = Todoers::from_todos_dir(todo_dir).unwrap();
+        let day = |day| NaiveDate::from_ymd_opt(2022, 11, day).unwrap();
+
+        assert_eq!(
+            timesheet(&todoers, day(24), day(25)),
+            "Timesheet 2022-11-24 to 2022-11-25\n  1h 45m  write CLI #work\n     20m  call bob\n\nBy tag\n  1h 45m  #work\n\nTotal 2h 5m\n"
+        );
+        assert_eq!(
+            timesheet(&todoers, day(25), day(25)),
+            "Timesheet 2022-11-25 to 2022-11-25\n     45m  write CLI #work\n\nBy tag\n     45m  #work\n\nTotal 45m\n"
+        );
+        assert_eq!(
+            timesheet(&todoers, day(26), day(30)),
+            "Nothing tracked from 2022-11-26 to 2022-11-30\n"
+        );
+    }
+}
@@ -1,92 +1,592 @@
-use anyhow::{anyhow, Result};
-use chrono::{Datelike, Duration, Local};
+use chrono::{Datelike, Duration, Local, NaiveDate, NaiveDateTime, NaiveTime, Timelike};
+use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
 use std::collections::HashMap;
-use std::path::PathBuf;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
 use std::str::Lines;
 
-#[derive(Debug)]
+use crate::dependencies::Dependencies;
+use crate::error::{Result, TodoerError};
+use crate::sync;
+use crate::timer::Interval;
+
+// Timestamps are written as trailing tokens after the name, see `write_metadata`
+pub const TIME_FORMAT: &str = "%Y-%m-%dT%H:%M";
+pub const DAY_FORMAT: &str = "%Y-%m-%d";
+// Far more than any day file holds, it only bounds ranges such as `0-9`
+pub const MAX_TODOS: u32 = 10_000;
+
+// The box of an item line: `[ ]` and `[x]`, and the common markdown extensions `[/]` in progress,
+// `[!]` blocked, `[>]` deferred and `[-]` cancelled
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Status {
+    #[default]
+    Open,
+    InProgress,
+    Blocked,
+    Deferred,
+    Done,
+    Cancelled,
+}
+
+impl Status {
+    // In the order `print_values` lists them
+    pub const ALL: [Status; 6] = [
+        Status::Open,
+        Status::InProgress,
+        Status::Blocked,
+        Status::Deferred,
+        Status::Done,
+        Status::Cancelled,
+    ];
+
+    pub fn marker(self) -> char {
+        match self {
+            Status::Open => ' ',
+            Status::InProgress => '/',
+            Status::Blocked => '!',
+            Status::Deferred => '>',
+            Status::Done => 'x',
+            Status::Cancelled => '-',
+        }
+    }
+
+    fn from_marker(marker: char) -> Option<Self> {
+        Status::ALL
+            .into_iter()
+            .find(|status| status.marker() == marker)
+    }
+
+    // Done and cancelled todos need nothing more and are not carried over
+    pub fn is_closed(self) -> bool {
+        matches!(self, Status::Done | Status::Cancelled)
+    }
+
+    // Section headings in `print_values`
+    fn heading(self) -> &'static str {
+        match self {
+            Status::Open => "Todo",
+            Status::InProgress => "In progress",
+            Status::Blocked => "Blocked",
+            Status::Deferred => "Deferred",
+            Status::Done => "Done",
+            Status::Cancelled => "Cancelled",
+        }
+    }
+}
+
+impl FromStr for Status {
+    type Err = TodoerError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        Status::ALL
+            .into_iter()
+            .find(|status| status.to_string() == s)
+            .ok_or_else(|| {
+                TodoerError::Config(format!(
+                    "unknown status {}, expected open, in-progress, blocked, deferred, done or cancelled",
+                    s
+                ))
+            })
+    }
+}
+
+impl fmt::Display for Status {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Status::Open => "open",
+            Status::InProgress => "in-progress",
+            Status::Blocked => "blocked",
+            Status::Deferred => "deferred",
+            Status::Done => "done",
+            Status::Cancelled => "cancelled",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
 pub struct Todo {
     pub name: String,
-    pub done: bool,
+    pub status: Status,
+    pub created: Option<NaiveDateTime>,
+    pub completed: Option<NaiveDateTime>,
+    // The day the todo was first listed on, only set once it has been carried over
+    pub since: Option<NaiveDate>,
+    // Time spent on the todo this day, see `timer`
+    pub tracked: Vec<Interval>,
+    // Work periods finished in focus mode this day, see `pomodoro`
+    pub pomodoros: u32,
+    // Stays the same when the todo is carried over, so other todos can wait on it from any day
+    pub id: Option<String>,
+    // IDs of the todos this one waits on, see `dependencies`
+    pub blocked_by: Vec<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Priority {
+    High,
+    Medium,
+    Low,
+}
+
+impl fmt::Display for Priority {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Priority::High => "high",
+            Priority::Medium => "medium",
+            Priority::Low => "low",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SortKey {
+    Priority,
+    Due,
+    Tag,
+    Alpha,
+}
+
+impl FromStr for SortKey {
+    type Err = TodoerError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "priority" => Ok(SortKey::Priority),
+            "due" => Ok(SortKey::Due),
+            "tag" => Ok(SortKey::Tag),
+            "alpha" => Ok(SortKey::Alpha),
+            _ => Err(TodoerError::Config(format!(
+                "unknown sort key {}, expected priority, due, tag or alpha",
+                s
+            ))),
+        }
+    }
+}
+
+impl fmt::Display for SortKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            SortKey::Priority => "priority",
+            SortKey::Due => "due",
+            SortKey::Tag => "tag",
+            SortKey::Alpha => "alpha",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+// Which todos a bulk operation applies to
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Selection {
+    Indices(Vec<u32>),
+    Tag(String),
+    AllDone,
+}
+
+pub fn parse_index(arg: &str) -> Result<u32> {
+    arg.parse()
+        .map_err(|_| TodoerError::Config(format!("expected an index but got {}", arg)))
+}
+
+impl TryFrom<&[String]> for Selection {
+    type Error = TodoerError;
+
+    // Accepts `--tag <tag>`, `--all-done` or a list of indices and ranges like `1 3 5-7`
+    fn try_from(args: &[String]) -> Result<Self> {
+        match args.first().map(String::as_str) {
+            None => Err(TodoerError::Config(String::from(
+                "expected at least one index, --tag or --all-done",
+            ))),
+            Some("--all-done") if args.len() == 1 => Ok(Selection::AllDone),
+            Some("--tag") if args.len() == 2 => Ok(Selection::Tag(args[1].clone())),
+            Some("--all-done") | Some("--tag") => Err(TodoerError::Config(format!(
+                "expected --tag <tag> or --all-done on their own but got {}",
+                args.join(" ")
+            ))),
+            Some(_) => {
+                let mut indices = Vec::new();
+                for arg in args {
+                    if let Some((start, end)) = arg.split_once('-') {
+                        let start = parse_index(start)?;
+                        let end = parse_index(end)?;
+                        if start > end {
+                            return Err(TodoerError::Config(format!("invalid range {}", arg)));
+                        }
+                        // Checked before it is expanded, so a typo can't ask for billions of indices
+                        if end >= MAX_TODOS {
+                            return Err(TodoerError::Parse {
+                                line: 1,
+                                message: format!(
+                                    "range {} goes past the {} todos a list can hold",
+                                    arg, MAX_TODOS
+                                ),
+                            });
+                        }
+                        indices.extend(start..=end);
+                    } else {
+                        indices.push(parse_index(arg)?);
+                    }
+                }
+                Ok(Selection::Indices(indices))
+            }
+        }
+    }
+}
+
+// The day a day file is for, from its name
+pub fn day_of(config: &Path) -> Option<NaiveDate> {
+    let stem = config.file_stem()?.to_str()?;
+    NaiveDate::parse_from_str(stem, DAY_FORMAT).ok()
+}
+
+// Now, to the minute as written in the file
+pub fn now() -> NaiveDateTime {
+    let now = Local::now().naive_local();
+    now.with_second(0)
+        .and_then(|now| now.with_nanosecond(0))
+        .unwrap_or(now)
+}
+
+// ` [1h 20m, 2 pomodoros]` for todos with time tracked or pomodoros finished against them
+pub fn tracked_suffix(todo: &Todo) -> String {
+    let mut parts = Vec::new();
+    let tracked = todo.tracked_time();
+    if !tracked.is_zero() {
+        parts.push(format_duration(tracked));
+    }
+    match todo.pomodoros {
+        0 => {}
+        1 => parts.push(String::from("1 pomodoro")),
+        count => parts.push(format!("{} pomodoros", count)),
+    }
+    if parts.is_empty() {
+        return String::new();
+    }
+    format!(" [{}]", parts.join(", "))
+}
+
+// Human readable length of time such as `1h 20m`
+pub fn format_duration(duration: Duration) -> String {
+    let minutes = duration.num_minutes().max(0);
+    match (minutes / 60, minutes % 60) {
+        (0, minutes) => format!("{}m", minutes),
+        (hours, 0) => format!("{}h", hours),
+        (hours, minutes) => format!("{}h {}m", hours, minutes),
+    }
+}
+
+pub fn parse_priority(word: &str) -> Option<Priority> {
+    match word.strip_prefix('!')? {
+        "high" | "h" | "1" => Some(Priority::High),
+        "medium" | "med" | "m" | "2" => Some(Priority::Medium),
+        "low" | "l" | "3" => Some(Priority::Low),
+        _ => None,
+    }
+}
+
+// Estimates are written as `30m`, `2h` or `1h30m`
+pub fn parse_estimate(value: &str) -> Option<Duration> {
+    let (hours, minutes) = match value.split_once('h') {
+        Some((hours, "")) => (hours, "0"),
+        Some((hours, minutes)) => (hours, minutes.strip_suffix('m')?),
+        None => ("0", value.strip_suffix('m')?),
+    };
+    let minutes = hours.parse::<i64>().ok()? * 60 + minutes.parse::<i64>().ok()?;
+    (minutes > 0).then(|| Duration::minutes(minutes))
+}
+
+pub fn format_estimate(estimate: Duration) -> String {
+    format_duration(estimate).replace(' ', "")
+}
+
+// The tokens written after a todo's name, see `Todo::parse`
+const METADATA: [&str; 7] = [
+    "since:",
+    "created:",
+    "done:",
+    "timer:",
+    "pomodoros:",
+    "id:",
+    "blocked-by:",
+];
+
+// Words of a name that could be read back as metadata, or as an escaped one, get a `\` in front
+fn escape_word(word: &str) -> String {
+    let bare = word.trim_start_matches('\\');
+    if METADATA.iter().any(|prefix| bare.starts_with(prefix)) {
+        return format!("\\{}", word);
+    }
+    word.to_string()
+}
+
+fn unescape_word(word: &str) -> &str {
+    match word.strip_prefix('\\') {
+        Some(rest) if escape_word(rest) == word => rest,
+        _ => word,
+    }
+}
+
+impl Todo {
+    // Splits `since:`, `created:`, `done:`, `timer:`, `pomodoros:`, `id:` and `blocked-by:` tokens off
+    // the end of an item line, leaving words of the name escaped by `escape_word` as they were typed
+    fn parse(line: &str, status: Status) -> Self {
+        let mut todo = Todo {
+            status,
+            ..Todo::default()
+        };
+        let mut words: Vec<&str> = line.split(' ').collect();
+        while let Some(word) = words.last() {
+            let parsed = if let Some(time) = word.strip_prefix("created:") {
+                NaiveDateTime::parse_from_str(time, TIME_FORMAT)
+                    .map(|time| todo.created = Some(time))
+                    .map_err(|_| ())
+            } else if let Some(time) = word.strip_prefix("done:") {
+                NaiveDateTime::parse_from_str(time, TIME_FORMAT)
+                    .map(|time| todo.completed = Some(time))
+                    .map_err(|_| ())
+            } else if let Some(day) = word.strip_prefix("since:") {
+                NaiveDate::parse_from_str(day, DAY_FORMAT)
+                    .map(|day| todo.since = Some(day))
+                    .map_err(|_| ())
+            } else if let Some(interval) = word.strip_prefix("timer:") {
+                // Read from the end, so each one goes before those already read
+                Interval::parse(interval)
+                    .map(|interval| todo.tracked.insert(0, interval))
+                    .ok_or(())
+            } else if let Some(count) = word.strip_prefix("pomodoros:") {
+                count
+                    .parse()
+                    .map(|count| todo.pomodoros = count)
+                    .map_err(|_| ())
+            } else if let Some(id) = word.strip_prefix("id:").filter(|id| !id.is_empty()) {
+                todo.id = Some(id.to_string());
+                Ok(())
+            } else if let Some(id) = word.strip_prefix("blocked-by:").filter(|id| !id.is_empty()) {
+                todo.blocked_by.insert(0, id.to_string());
+                Ok(())
+            } else {
+                break;
+            };
+            if parsed.is_err() {
+                break;
+            }
+            words.pop();
+        }
+        let words: Vec<&str> = words.into_iter().map(unescape_word).collect();
+        todo.name = words.join(" ");
+        todo
+    }
+
+    // The todo as it is written in the day file
+    pub fn line(&self) -> String {
+        let mut line = format!("- [{}] ", self.status.marker());
+        self.write_name(&mut line);
+        self.write_metadata(&mut line);
+        line
+    }
+
+    fn write_name(&self, line: &mut String) {
+        let words: Vec<String> = self.name.split(' ').map(escape_word).collect();
+        *line += &words.join(" ");
+    }
+
+    fn write_metadata(&self, line: &mut String) {
+        if let Some(since) = self.since {
+            *line += &format!(" since:{}", since.format(DAY_FORMAT));
+        }
+        if let Some(created) = self.created {
+            *line += &format!(" created:{}", created.format(TIME_FORMAT));
+        }
+        if let Some(completed) = self.completed {
+            *line += &format!(" done:{}", completed.format(TIME_FORMAT));
+        }
+        for interval in &self.tracked {
+            *line += &format!(" timer:{}", interval);
+        }
+        if self.pomodoros > 0 {
+            *line += &format!(" pomodoros:{}", self.pomodoros);
+        }
+        if let Some(id) = &self.id {
+            *line += &format!(" id:{}", id);
+        }
+        for id in &self.blocked_by {
+            *line += &format!(" blocked-by:{}", id);
+        }
+    }
+
+    pub fn is_done(&self) -> bool {
+        self.status == Status::Done
+    }
+
+    pub fn tracked_time(&self) -> Duration {
+        self.tracked
+            .iter()
+            .fold(Duration::zero(), |total, interval| {
+                total + interval.duration()
+            })
+    }
+
+    // Tags are written inline in the name as `#tag`
+    pub fn tags(&self) -> Vec<&str> {
+        self.name
+            .split_whitespace()
+            .filter_map(|word| word.strip_prefix('#'))
+            .filter(|tag| !tag.is_empty())
+            .collect()
+    }
+
+    // Priority is written inline in the name as `!high`, `!medium` or `!low`
+    pub fn priority(&self) -> Option<Priority> {
+        self.name.split_whitespace().find_map(parse_priority)
+    }
+
+    // Due dates are written inline in the name as `due:YYYY-MM-DD`
+    pub fn due(&self) -> Option<NaiveDate> {
+        self.name.split_whitespace().find_map(|word| {
+            let date = word.strip_prefix("due:")?;
+            NaiveDate::parse_from_str(date, "%Y-%m-%d").ok()
+        })
+    }
+
+    // The time on the due date, written inline in the name as `at:HH:MM`
+    pub fn due_time(&self) -> Option<NaiveTime> {
+        self.name.split_whitespace().find_map(|word| {
+            let time = word.strip_prefix("at:")?;
+            NaiveTime::parse_from_str(time, "%H:%M").ok()
+        })
+    }
+
+    // Contexts are written inline in the name as `@context`
+    pub fn contexts(&self) -> Vec<&str> {
+        self.name
+            .split_whitespace()
+            .filter_map(|word| word.strip_prefix('@'))
+            .filter(|context| !context.is_empty())
+            .collect()
+    }
+
+    // Estimates are written inline in the name as `est:1h30m`
+    pub fn estimate(&self) -> Option<Duration> {
+        self.name
+            .split_whitespace()
+            .find_map(|word| parse_estimate(word.strip_prefix("est:")?))
+    }
+}
+
+// Orders todos missing the key after the ones that have it
+fn compare_missing_last<T: Ord>(a: Option<T>, b: Option<T>) -> Ordering {
+    match (a, b) {
+        (Some(a), Some(b)) => a.cmp(&b),
+        (Some(_), None) => Ordering::Less,
+        (None, Some(_)) => Ordering::Greater,
+        (None, None) => Ordering::Equal,
+    }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct Todos(pub HashMap<u32, Todo>);
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct Todoer {
     pub config: PathBuf,
     pub data: Todos,
     pub size: u32,
     pub done_count: u32,
+    // Lines that aren't todos, such as notes and headings, with the index of the todo they came
+    // before. They are written back in the same place
+    pub notes: Vec<(u32, String)>,
 }
 
 pub fn default_data() -> Todos {
     Todos(HashMap::new())
 }
 
-impl<'a> TryFrom<Lines<'a>> for Todos {
-    type Error = anyhow::Error;
+// `- [ ] ` items with one of ` x/!>-` in the box are todos, any other line is kept as a note
+fn parse_items(lines: Lines<'_>) -> (Todos, Vec<(u32, String)>) {
+    let mut index: u32 = 0;
+    let mut data = HashMap::new();
+    let mut notes = Vec::new();
+    for line in lines {
+        if line.trim().is_empty() {
+            continue;
+        }
 
-    fn try_from(lines: Lines<'a>) -> Result<Self, Self::Error> {
-        let mut index: u32 = 0;
-        let mut data = HashMap::new();
-        lines.for_each(|line| {
-            let done = line.contains("[x]");
-            let name = line.replace("- [ ] ", "").replace("- [x] ", "");
-            let todo = Todo { name, done };
-            data.insert(index, todo);
-            index += 1;
+        let item = line.strip_prefix("- [").and_then(|rest| {
+            let mut chars = rest.chars();
+            let status = Status::from_marker(chars.next()?)?;
+            Some((status, chars.as_str().strip_prefix("] ")?))
         });
-        Ok(Todos(data))
-    }
-}
-
-impl TryFrom<String> for Todoer {
-    type Error = anyhow::Error;
-
-    fn try_from(data: String) -> Result<Self, Self::Error> {
-        let mut lines = data.lines();
-
-        let first_line = lines.next();
-        let home = std::env::var("HOME").unwrap();
-        let mut config = PathBuf::from(home);
-        config.push("wiki");
-        config.push("todo");
-        let mut file = PathBuf::from(first_line.unwrap());
-        file.set_extension("md");
-        config.push(file);
-
-        let mut second_line = lines.next().unwrap().splitn(2, '/');
-        let done_count = second_line
-            .next()
-            .unwrap()
-            .parse()
-            .expect("Expected done_count to be a int");
-        let size = second_line
-            .next()
-            .unwrap()
-            .parse()
-            .expect("Expected size to be a int");
-        let data = lines.try_into().expect("Error parsing todos");
+        let Some((status, item)) = item else {
+            notes.push((index, line.to_string()));
+            continue;
+        };
+        let todo = Todo::parse(item, status);
+        data.insert(index, todo);
+        index += 1;
+    }
+    (Todos(data), notes)
+}
+
+impl Todoer {
+    // The file's contents, which name the day file in `dir` they were read from on their first line
+    pub fn from_contents(dir: &Path, contents: &str) -> Result<Self> {
+        let mut lines = contents.lines();
+
+        let first_line = lines.next().ok_or(TodoerError::Parse {
+            line: 1,
+            message: String::from("expected the file name"),
+        })?;
+        let config = dir.join(format!("{}.md", first_line));
+
+        // The done/size header is only checked, the counts are taken from the items themselves
+        let second_line = lines.next().unwrap_or_default();
+        let counts = second_line.split_once('/').and_then(|(done_count, size)| {
+            Some((done_count.parse::<u32>().ok()?, size.parse::<u32>().ok()?))
+        });
+        if counts.is_none() {
+            return Err(TodoerError::Parse {
+                line: 2,
+                message: format!("expected done/size counts but got {:?}", second_line),
+            });
+        }
+
+        let (data, notes) = parse_items(lines);
+        let Todos(todos) = &data;
+        let size = todos.len().try_into().unwrap();
+        let done_count = todos
+            .values()
+            .filter(|todo| todo.is_done())
+            .count()
+            .try_into()
+            .unwrap();
 
         Ok(Todoer {
             config,
             data,
             done_count,
             size,
+            notes,
         })
     }
 }
 
 impl TryFrom<&Todoer> for String {
-    type Error = anyhow::Error;
+    type Error = TodoerError;
 
-    fn try_from(todoer: &Todoer) -> Result<Self, Self::Error> {
+    fn try_from(todoer: &Todoer) -> Result<Self> {
         let filename = todoer
             .config
             .file_stem()
-            .unwrap()
+            .ok_or_else(|| {
+                TodoerError::Config(format!("{} is not a todo file", todoer.config.display()))
+            })?
             .to_string_lossy()
             .into_owned();
 
@@ -97,14 +597,18 @@ impl TryFrom<&Todoer> for String {
         formatted_data += &(done_count + "/" + &size + "\n");
 
         let Todos(todos) = &todoer.data;
-        for index in 0..todos.keys().len().try_into().unwrap() {
-            let todo = todos.get(&index).unwrap();
-            if todo.done {
-                formatted_data += "- [x] ";
-            } else {
-                formatted_data += "- [ ] ";
+        let mut notes = todoer.notes.iter().peekable();
+        for index in 0..todoer.size {
+            while let Some((_, note)) = notes.next_if(|(before, _)| *before <= index) {
+                formatted_data += note;
+                formatted_data += "\n";
             }
-            formatted_data += &todo.name;
+            let todo = todos.get(&index).ok_or(TodoerError::NotFound(index))?;
+            formatted_data += &todo.line();
+            formatted_data += "\n";
+        }
+        for (_, note) in notes {
+            formatted_data += note;
             formatted_data += "\n";
         }
 
@@ -112,7 +616,8 @@ impl TryFrom<&Todoer> for String {
     }
 }
 
-pub fn get_yesterday_config() -> Result<PathBuf> {
+// Yesterday's file lives next to the given day file
+pub fn get_yesterday_config(config: &Path) -> PathBuf {
     let yesterday = Local::now() - Duration::days(1);
     let filename = format!(
         "{}-{:02}-{:02}",
@@ -120,24 +625,7 @@ pub fn get_yesterday_config() -> Result<PathBuf> {
         yesterday.month(),
         yesterday.day()
     );
-
-    if let Ok(home) = std::env::var("XDG_CONFIG_HOME") {
-        let mut home = PathBuf::from(home);
-        home.push("wiki");
-        home.push("todo");
-        home.push(format!("{}.md", filename));
-        return Ok(home);
-    }
-
-    if let Ok(home) = std::env::var("HOME") {
-        let mut home = PathBuf::from(home);
-        home.push("wiki");
-        home.push("todo");
-        home.push(format!("{}.md", filename));
-        return Ok(home);
-    }
-
-    Err(anyhow!("unable to find config location"))
+    config.with_file_name(format!("{}.md", filename))
 }
 
 impl Todoer {
@@ -147,6 +635,7 @@ impl Todoer {
             data: default_data(),
             size: 0,
             done_count: 0,
+            notes: Vec::new(),
         }
     }
     pub fn get_value_names(&self) -> Vec<&String> {
@@ -160,143 +649,496 @@ impl Todoer {
         ret
     }
 
+    // Todos neither done nor cancelled. `done_count` leaves cancelled ones out, so this isn't
+    // `size - done_count`
+    pub fn open_count(&self) -> u32 {
+        let Todos(todos) = &self.data;
+        todos
+            .values()
+            .filter(|todo| !todo.status.is_closed())
+            .count() as u32
+    }
+
     pub fn get_value_all(&self) -> Vec<(&String, bool)> {
         let mut ret = Vec::new();
         let Todos(todos) = &self.data;
         let mut index: u32 = 0;
         while index < self.size {
-            ret.push((&todos[&index].name, todos[&index].done));
+            ret.push((&todos[&index].name, todos[&index].is_done()));
             index += 1;
         }
         ret
     }
 
+    // Todos grouped by status, `Todo` and `Done` are always listed and the others when not empty
     pub fn print_values(&self) -> String {
-        let mut res = String::from("\nTodo\n");
+        self.print_values_with(&Dependencies::new([self]))
+    }
+
+    // As `print_values`, with what todos wait on looked up in `dependencies` so it may be on other days
+    pub fn print_values_with(&self, dependencies: &Dependencies) -> String {
+        let mut res = String::new();
 
+        let day = day_of(&self.config);
         let Todos(todos) = &self.data;
-        for index in 0..todos.keys().len().try_into().unwrap() {
-            let todo = todos.get(&index).unwrap();
-            if !todo.done {
-                res += &(String::from("- ") + &todo.name.to_string() + "\n");
+        for status in Status::ALL {
+            let indices: Vec<u32> = (0..self.size)
+                .filter(|index| todos.get(index).is_some_and(|todo| todo.status == status))
+                .collect();
+            if indices.is_empty() && !matches!(status, Status::Open | Status::Done) {
+                continue;
+            }
+            res += &format!("\n{}\n", status.heading());
+            for index in indices {
+                let todo = &todos[&index];
+                res += &(index.to_string() + "). " + &todo.name);
+                res += &tracked_suffix(todo);
+                match (todo.completed, todo.since, day) {
+                    (Some(completed), _, day) if todo.is_done() => {
+                        let format = if Some(completed.date()) == day {
+                            "%H:%M"
+                        } else {
+                            "%Y-%m-%d %H:%M"
+                        };
+                        res += &format!(" (done {})", completed.format(format));
+                    }
+                    // Carried over from an earlier day
+                    (_, Some(since), Some(day)) if !todo.status.is_closed() && since < day => {
+                        res += &format!(" (since {})", since.format(DAY_FORMAT));
+                    }
+                    _ => {}
+                }
+                let waiting = dependencies.waiting_on(todo);
+                if !todo.status.is_closed() && !waiting.is_empty() {
+                    res += &format!(" (waiting on {})", waiting.join(", "));
+                }
+                res += "\n";
             }
         }
+        res
+    }
 
-        res += &String::from("\nDone\n");
-        for index in 0..todos.keys().len().try_into().unwrap() {
-            let todo = todos.get(&index).unwrap();
-            if todo.done {
-                res += &(String::from("- ") + &todo.name.to_string() + "\n");
+    // Counts for the day, how long done todos took and how long open ones have been carried
+    pub fn stats(&self) -> String {
+        let Todos(todos) = &self.data;
+        let todos: Vec<&Todo> = (0..self.size)
+            .filter_map(|index| todos.get(&index))
+            .collect();
+        let day = day_of(&self.config).unwrap_or_else(|| Local::now().date_naive());
+
+        let count = |status: Status| todos.iter().filter(|todo| todo.status == status).count();
+        let mut res = format!(
+            "{} todos, {} done, {} open",
+            todos.len(),
+            self.done_count,
+            count(Status::Open)
+        );
+        for status in [
+            Status::InProgress,
+            Status::Blocked,
+            Status::Deferred,
+            Status::Cancelled,
+        ] {
+            if count(status) > 0 {
+                res += &format!(", {} {}", count(status), status);
             }
         }
+        res += "\n";
+
+        let took: Vec<Duration> = todos
+            .iter()
+            .filter_map(|todo| Some(todo.completed? - todo.created?))
+            .collect();
+        if !took.is_empty() {
+            let total = took
+                .iter()
+                .fold(Duration::zero(), |total, took| total + *took);
+            let average = total / took.len().try_into().unwrap();
+            res += &format!("Average time to done: {}\n", format_duration(average));
+        }
+
+        let carried: Vec<&&Todo> = todos
+            .iter()
+            .filter(|todo| !todo.status.is_closed() && todo.since.is_some_and(|since| since < day))
+            .collect();
+        if let Some(oldest) = carried.iter().min_by_key(|todo| todo.since) {
+            let since = oldest.since.expect("filtered on since");
+            res += &format!(
+                "Carried over: {}, oldest {} since {} ({} days)\n",
+                carried.len(),
+                oldest.name,
+                since.format(DAY_FORMAT),
+                (day - since).num_days()
+            );
+        }
         res
     }
 
     pub fn set_value(&mut self, name: String) {
-        self.data.0.insert(self.size, Todo { name, done: false });
+        let todo = Todo {
+            name,
+            created: Some(now()),
+            ..Todo::default()
+        };
+        self.data.0.insert(self.size, todo);
         self.size += 1
     }
 
-    pub fn remove_value(&mut self, index: u32) {
-        let value = &self.data.0.get(&index).expect("Invalid index");
-        if value.done {
-            self.done_count -= 1;
+    fn check_index(&self, index: u32) -> Result<()> {
+        if index >= self.size {
+            return Err(TodoerError::NotFound(index));
+        }
+        Ok(())
+    }
+
+    // The first todo called `name`, for things that outlive indices such as a running timer
+    pub fn find_value(&self, name: &str) -> Option<u32> {
+        let Todos(todos) = &self.data;
+        (0..self.size).find(|index| todos.get(index).is_some_and(|todo| todo.name == name))
+    }
+
+    pub fn remove_value(&mut self, index: u32) -> Result<()> {
+        let value = self
+            .data
+            .0
+            .get(&index)
+            .ok_or(TodoerError::NotFound(index))?;
+        if value.is_done() {
+            self.done_count = self.done_count.saturating_sub(1);
         }
         for i in index + 1..self.size {
-            let Todo { name, done } = self.data.0.get(&i).unwrap();
-            self.data.0.insert(
-                i - 1,
-                Todo {
-                    name: name.clone(),
-                    done: *done,
-                },
-            );
+            let todo = self.data.0.remove(&i).ok_or(TodoerError::NotFound(i))?;
+            self.data.0.insert(i - 1, todo);
         }
         self.data.0.remove(&(self.size - 1));
         self.size -= 1;
+        Ok(())
     }
 
-    pub fn mark_done(&mut self, index: u32) {
-        let todo = &self.data.0.get(&index).expect("Invalid index");
-        let name = &todo.name;
-        self.data.0.insert(
-            index,
-            Todo {
-                name: name.to_string(),
-                done: true,
-            },
-        );
-        self.done_count += 1;
+    pub fn mark_done(&mut self, index: u32) -> Result<()> {
+        let todo = self
+            .data
+            .0
+            .get_mut(&index)
+            .ok_or(TodoerError::NotFound(index))?;
+        if todo.is_done() {
+            return Err(TodoerError::Conflict(format!(
+                "todo {} is already done",
+                index
+            )));
+        }
+        self.set_status(index, Status::Done)
+    }
+
+    pub fn mark_undone(&mut self, index: u32) -> Result<()> {
+        let todo = self
+            .data
+            .0
+            .get_mut(&index)
+            .ok_or(TodoerError::NotFound(index))?;
+        if !todo.is_done() {
+            return Err(TodoerError::Conflict(format!("todo {} is not done", index)));
+        }
+        self.set_status(index, Status::Open)
+    }
+
+    // Unlike `mark_done` and `mark_undone`, setting the status a todo already has is not a conflict
+    pub fn set_status(&mut self, index: u32, status: Status) -> Result<()> {
+        let todo = self
+            .data
+            .0
+            .get_mut(&index)
+            .ok_or(TodoerError::NotFound(index))?;
+        if todo.status == status {
+            return Ok(());
+        }
+        if todo.is_done() {
+            todo.completed = None;
+            self.done_count = self.done_count.saturating_sub(1);
+        }
+        if status == Status::Done {
+            todo.completed = Some(now());
+            self.done_count += 1;
+        }
+        todo.status = status;
+        Ok(())
+    }
+
+    pub fn set_status_all(&mut self, indices: &[u32], status: Status) -> Result<()> {
+        indices
+            .iter()
+            .try_for_each(|index| self.check_index(*index))?;
+        for index in indices {
+            self.set_status(*index, status)?;
+        }
+        Ok(())
+    }
+
+    pub fn edit_value(&mut self, index: u32, name: String) -> Result<()> {
+        let todo = self
+            .data
+            .0
+            .get_mut(&index)
+            .ok_or(TodoerError::NotFound(index))?;
+        todo.name = name;
+        Ok(())
+    }
+
+    // Resolves a selection to sorted, deduplicated indices, failing if any index does not exist
+    pub fn select(&self, selection: &Selection) -> Result<Vec<u32>> {
+        let Todos(todos) = &self.data;
+        let mut indices: Vec<u32> = match selection {
+            Selection::Indices(indices) => indices.clone(),
+            Selection::Tag(tag) => (0..self.size)
+                .filter(|index| todos[index].tags().contains(&tag.as_str()))
+                .collect(),
+            Selection::AllDone => (0..self.size)
+                .filter(|index| todos[index].is_done())
+                .collect(),
+        };
+        indices.sort_unstable();
+        indices.dedup();
+
+        if let Some(index) = indices.iter().find(|index| **index >= self.size) {
+            return Err(TodoerError::NotFound(*index));
+        }
+        Ok(indices)
+    }
+
+    // Todos that are already done are skipped rather than treated as a conflict
+    pub fn mark_done_all(&mut self, indices: &[u32]) -> Result<()> {
+        indices
+            .iter()
+            .try_for_each(|index| self.check_index(*index))?;
+        for index in indices {
+            if !self.data.0[index].is_done() {
+                self.mark_done(*index)?;
+            }
+        }
+        Ok(())
+    }
+
+    pub fn remove_values(&mut self, indices: &[u32]) -> Result<()> {
+        indices
+            .iter()
+            .try_for_each(|index| self.check_index(*index))?;
+        let mut indices = indices.to_vec();
+        indices.sort_unstable();
+        indices.dedup();
+        // Removing from the back keeps the remaining indices valid
+        indices
+            .iter()
+            .rev()
+            .try_for_each(|index| self.remove_value(*index))
+    }
+
+    // Moves the todos at `indices` so the first one lands at `to`, keeping their relative order
+    pub fn move_values(&mut self, indices: &[u32], to: u32) -> Result<()> {
+        indices
+            .iter()
+            .try_for_each(|index| self.check_index(*index))?;
+        let (moved, rest): (Vec<u32>, Vec<u32>) =
+            (0..self.size).partition(|index| indices.contains(index));
+        let mut take = |indices: Vec<u32>| -> Result<Vec<Todo>> {
+            indices
+                .iter()
+                .map(|index| {
+                    self.data
+                        .0
+                        .remove(index)
+                        .ok_or(TodoerError::NotFound(*index))
+                })
+                .collect()
+        };
+        let mut moved = take(moved)?;
+        let mut rest = take(rest)?;
+
+        let to = usize::try_from(to).unwrap().min(rest.len());
+        let tail = rest.split_off(to);
+        rest.append(&mut moved);
+        rest.extend(tail);
+
+        for (index, todo) in (0..).zip(rest) {
+            self.data.0.insert(index, todo);
+        }
+        Ok(())
+    }
+
+    pub fn tag_values(&mut self, tag: &str, indices: &[u32]) -> Result<()> {
+        indices
+            .iter()
+            .try_for_each(|index| self.check_index(*index))?;
+        let tag = tag.trim_start_matches('#');
+        for index in indices {
+            let todo = self
+                .data
+                .0
+                .get_mut(index)
+                .ok_or(TodoerError::NotFound(*index))?;
+            if !todo.tags().contains(&tag) {
+                todo.name += &format!(" #{}", tag);
+            }
+        }
+        Ok(())
+    }
+
+    pub fn move_value(&mut self, from: u32, to: u32) -> Result<()> {
+        let todo = self
+            .data
+            .0
+            .remove(&from)
+            .ok_or(TodoerError::NotFound(from))?;
+        let to = to.min(self.size - 1);
+        if from < to {
+            for i in from + 1..=to {
+                let next = self.data.0.remove(&i).ok_or(TodoerError::NotFound(i))?;
+                self.data.0.insert(i - 1, next);
+            }
+        } else {
+            for i in (to..from).rev() {
+                let prev = self.data.0.remove(&i).ok_or(TodoerError::NotFound(i))?;
+                self.data.0.insert(i + 1, prev);
+            }
+        }
+        self.data.0.insert(to, todo);
+        Ok(())
+    }
+
+    pub fn move_up(&mut self, index: u32) -> Result<()> {
+        self.check_index(index)?;
+        if index > 0 {
+            self.move_value(index, index - 1)?;
+        }
+        Ok(())
+    }
+
+    pub fn move_down(&mut self, index: u32) -> Result<()> {
+        self.check_index(index)?;
+        if index + 1 < self.size {
+            self.move_value(index, index + 1)?;
+        }
+        Ok(())
+    }
+
+    // Stable sort, so todos that compare equal keep their current order
+    pub fn sort_values(&mut self, by: SortKey) -> Result<()> {
+        let mut todos: Vec<Todo> = (0..self.size)
+            .map(|index| {
+                self.data
+                    .0
+                    .remove(&index)
+                    .ok_or(TodoerError::NotFound(index))
+            })
+            .collect::<Result<_>>()?;
+
+        todos.sort_by(|a, b| match by {
+            SortKey::Priority => compare_missing_last(a.priority(), b.priority()),
+            SortKey::Due => compare_missing_last(a.due(), b.due()),
+            SortKey::Tag => {
+                compare_missing_last(a.tags().into_iter().min(), b.tags().into_iter().min())
+            }
+            SortKey::Alpha => a.name.to_lowercase().cmp(&b.name.to_lowercase()),
+        });
+
+        for (index, todo) in (0..).zip(todos) {
+            self.data.0.insert(index, todo);
+        }
+        Ok(())
     }
 
     pub fn save(&self) -> Result<()> {
-        print!("{}", self.config.display());
         if let Some(p) = self.config.parent() {
-            if std::fs::metadata(&p).is_err() {
+            if std::fs::metadata(p).is_err() {
                 std::fs::create_dir_all(p)?;
             }
         }
-        print!("{}", self.config.display());
         let contents: String = self.try_into()?;
         std::fs::write(&self.config, contents)?;
 
         Ok(())
     }
 
-    pub fn from_config(config: PathBuf, is_past: bool) -> Self {
+    // As `save`, then commits the day file when sync is on. It runs git, so the CLI and
+    // interactive mode use it and the server doesn't
+    pub fn save_and_commit(&self) -> Result<()> {
+        let dir = self.config.parent().map(PathBuf::from).unwrap_or_default();
+        if !sync::is_enabled(&dir) {
+            return self.save();
+        }
+        // The previous version, to describe the change in the commit
+        let before = std::fs::read_to_string(&self.config)
+            .ok()
+            .and_then(|contents| Todoer::from_contents(&dir, &contents).ok());
+        self.save()?;
+        sync::commit(&self.config, &sync::describe(before.as_ref(), self))
+    }
+
+    pub fn from_config(config: PathBuf, is_past: bool) -> Result<Self> {
         if std::fs::metadata(&config).is_ok() {
-            let contents = std::fs::read_to_string(&config);
-            let contents = contents.unwrap_or_else(|_| String::from("{\"todos\":[]}"));
-            return contents.try_into().expect("Error parsing data");
+            let contents = std::fs::read_to_string(&config)?;
+            let dir = config.parent().map(PathBuf::from).unwrap_or_default();
+            let mut todoer = Todoer::from_contents(&dir, &contents)?;
+            todoer.config = config;
+            return Ok(todoer);
         }
 
         // Copies over yesterday into today if it exists
         if !is_past {
-            let yesterday_config = get_yesterday_config().unwrap();
+            let yesterday_config = get_yesterday_config(&config);
             if std::fs::metadata(&yesterday_config).is_ok() {
-                let contents = std::fs::read_to_string(&yesterday_config);
-                let contents = contents.unwrap_or_else(|_| String::from("{\"todos\":[]}"));
-                let yesterday_contents: Todoer = contents.try_into().expect("Error parsing data");
+                let contents = std::fs::read_to_string(&yesterday_config)?;
+                let dir = yesterday_config
+                    .parent()
+                    .map(PathBuf::from)
+                    .unwrap_or_default();
+                let yesterday_contents = Todoer::from_contents(&dir, &contents)?;
                 let Todos(todos) = &yesterday_contents.data;
                 let mut new_data = HashMap::new();
                 let mut new_index = 0;
-                for index in 0..todos.keys().len().try_into().unwrap() {
-                    let todo = todos.get(&index).unwrap();
-                    if !todo.done {
-                        let new_todo = Todo {
-                            name: todo.name.clone(),
-                            done: false,
-                        };
-                        new_data.insert(new_index, new_todo);
-                        new_index += 1;
-                    }
+                for index in 0..yesterday_contents.size {
+                    let todo = todos.get(&index).ok_or(TodoerError::NotFound(index))?;
+                    // In progress and blocked todos stay so, deferred ones were put off until today
+                    let status = match todo.status {
+                        Status::Done | Status::Cancelled => continue,
+                        Status::Deferred => Status::Open,
+                        status => status,
+                    };
+                    // Todos from files written before timestamps were kept start from yesterday
+                    let new_todo = Todo {
+                        status,
+                        since: todo.since.or_else(|| day_of(&yesterday_config)),
+                        // Time is counted on the day it was spent
+                        tracked: Vec::new(),
+                        pomodoros: 0,
+                        ..todo.clone()
+                    };
+                    new_data.insert(new_index, new_todo);
+                    new_index += 1;
                 }
                 let data = Todos(new_data);
 
-                let size = yesterday_contents.size - yesterday_contents.done_count;
-                return Todoer {
+                return Ok(Todoer {
                     config,
                     data,
-                    size,
+                    size: new_index,
                     done_count: 0,
-                };
+                    notes: Vec::new(),
+                });
             }
         }
-        Todoer {
-            config,
-            data: default_data(),
-            size: 0,
-            done_count: 0,
-        }
+        Ok(Todoer::default_todoer(config))
     }
 }
 
 #[cfg(test)]
 mod test {
     use std::collections::HashMap;
-    use std::path::PathBuf;
+    use std::path::{Path, PathBuf};
 
-    use super::{Todo, Todoer, Todos};
+    use super::{
+        get_yesterday_config, Priority, Selection, SortKey, Status, Todo, Todoer, Todos, DAY_FORMAT,
+    };
+    use crate::error::TodoerError;
 
     fn get_data() -> HashMap<u32, Todo> {
         HashMap::from([
@@ -304,14 +1146,15 @@ mod test {
                 0,
                 Todo {
                     name: "foo".into(),
-                    done: true,
+                    status: Status::Done,
+                    ..Todo::default()
                 },
             ),
             (
                 1,
                 Todo {
                     name: "bar".into(),
-                    done: false,
+                    ..Todo::default()
                 },
             ),
         ])
@@ -323,6 +1166,7 @@ mod test {
             data: Todos(get_data()),
             size: 2,
             done_count: 1,
+            notes: Vec::new(),
         }
     }
 
@@ -344,7 +1188,7 @@ mod test {
     #[test]
     fn remove_value() {
         let mut proj = get_todoer();
-        proj.remove_value(0);
+        proj.remove_value(0).unwrap();
 
         assert_eq!(proj.get_value_names(), vec![&String::from("bar")]);
     }
@@ -353,7 +1197,7 @@ mod test {
     fn remove_value_end() {
         let mut proj = get_todoer();
         proj.set_value(String::from("fam"));
-        proj.remove_value(2);
+        proj.remove_value(2).unwrap();
 
         assert_eq!(
             proj.get_value_names(),
@@ -365,7 +1209,7 @@ mod test {
     fn remove_value_start() {
         let mut proj = get_todoer();
         proj.set_value(String::from("fam"));
-        proj.remove_value(0);
+        proj.remove_value(0).unwrap();
 
         assert_eq!(proj.done_count, 0);
 
@@ -392,7 +1236,7 @@ mod test {
     #[test]
     fn mark_done() {
         let mut proj = get_todoer();
-        proj.mark_done(1);
+        proj.mark_done(1).unwrap();
 
         assert_eq!(
             proj.get_value_all(),
@@ -401,6 +1245,412 @@ mod test {
         assert_eq!(proj.done_count, 2);
     }
 
+    #[test]
+    fn move_value() {
+        let mut proj = get_todoer();
+        proj.set_value(String::from("fam"));
+        proj.move_value(2, 0).unwrap();
+
+        assert_eq!(
+            proj.get_value_all(),
+            vec![
+                (&String::from("fam"), false),
+                (&String::from("foo"), true),
+                (&String::from("bar"), false)
+            ]
+        );
+
+        proj.move_value(0, 5).unwrap();
+        assert_eq!(
+            proj.get_value_names(),
+            vec![
+                &String::from("foo"),
+                &String::from("bar"),
+                &String::from("fam")
+            ]
+        );
+    }
+
+    #[test]
+    fn move_up_and_down() {
+        let mut proj = get_todoer();
+        proj.move_up(0).unwrap();
+        assert_eq!(
+            proj.get_value_names(),
+            vec![&String::from("foo"), &String::from("bar")]
+        );
+
+        proj.move_up(1).unwrap();
+        assert_eq!(
+            proj.get_value_names(),
+            vec![&String::from("bar"), &String::from("foo")]
+        );
+
+        proj.move_down(1).unwrap();
+        assert_eq!(
+            proj.get_value_names(),
+            vec![&String::from("bar"), &String::from("foo")]
+        );
+    }
+
+    #[test]
+    fn timestamps() {
+        let mut proj = Todoer::default_todoer(PathBuf::from("wiki/todo/2022-11-25.md"));
+        proj.set_value(String::from("foo"));
+        proj.mark_done(0).unwrap();
+        let todo = &proj.data.0[&0];
+        assert!(todo.created.is_some());
+        assert!(todo.completed.is_some());
+        proj.mark_undone(0).unwrap();
+        assert!(proj.data.0[&0].completed.is_none());
+
+        let contents = "2022-11-25\n1/2\n- [ ] call bank due:2022-12-01 since:2022-11-20 created:2022-11-20T09:00\n- [x] foo created:2022-11-25T09:30 done:2022-11-25T10:05\n";
+        let proj = Todoer::from_contents(Path::new("wiki/todo"), contents).unwrap();
+        assert_eq!(proj.data.0[&0].name, "call bank due:2022-12-01");
+        assert_eq!(
+            proj.data.0[&0].since,
+            chrono::NaiveDate::from_ymd_opt(2022, 11, 20)
+        );
+        assert_eq!(String::try_from(&proj).unwrap(), contents);
+        assert_eq!(
+            proj.print_values(),
+            "\nTodo\n0). call bank due:2022-12-01 (since 2022-11-20)\n\nDone\n1). foo (done 10:05)\n"
+        );
+        assert_eq!(
+            proj.stats(),
+            "2 todos, 1 done, 1 open\nAverage time to done: 35m\nCarried over: 1, oldest call bank due:2022-12-01 since 2022-11-20 (5 days)\n"
+        );
+    }
+
+    #[test]
+    fn statuses() {
+        let contents =
+            "2022-11-25\n1/6\n- [ ] foo\n- [/] bar\n- [!] baz\n- [>] fam\n- [x] qux\n- [-] quux\n";
+        let mut proj = Todoer::from_contents(Path::new("wiki/todo"), contents).unwrap();
+        proj.config = PathBuf::from("wiki/todo/2022-11-25.md");
+        assert_eq!(proj.data.0[&1].status, Status::InProgress);
+        assert_eq!(proj.data.0[&5].status, Status::Cancelled);
+        assert_eq!(String::try_from(&proj).unwrap(), contents);
+        assert_eq!(
+            proj.print_values(),
+            "\nTodo\n0). foo\n\nIn progress\n1). bar\n\nBlocked\n2). baz\n\nDeferred\n3). fam\n\nDone\n4). qux\n\nCancelled\n5). quux\n"
+        );
+        assert_eq!(
+            proj.stats().lines().next(),
+            Some("6 todos, 1 done, 1 open, 1 in-progress, 1 blocked, 1 deferred, 1 cancelled")
+        );
+
+        proj.set_status(4, Status::Cancelled).unwrap();
+        assert_eq!(proj.done_count, 0);
+        assert!(proj.data.0[&4].completed.is_none());
+        proj.set_status_all(&[0, 1], Status::Done).unwrap();
+        assert_eq!(proj.done_count, 2);
+        assert!(proj.data.0[&0].completed.is_some());
+        assert!(proj.set_status_all(&[0, 6], Status::Open).is_err());
+        assert!("waiting".parse::<Status>().is_err());
+        // Not a status, so kept as a note rather than read as a todo
+        let proj =
+            Todoer::from_contents(Path::new("wiki/todo"), "2022-11-25\n0/0\n- [?] foo\n").unwrap();
+        assert_eq!(proj.size, 0);
+        assert_eq!(proj.notes, vec![(0, String::from("- [?] foo"))]);
+    }
+
+    #[test]
+    fn carry_over_by_status() {
+        let mut dir = std::env::temp_dir();
+        dir.push(format!("todoer-todoer-carry-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        let today = chrono::Local::now().date_naive();
+        let config = dir.join(format!("{}.md", today.format(DAY_FORMAT)));
+        std::fs::write(
+            get_yesterday_config(&config),
+            "yesterday\n1/6\n- [ ] foo\n- [/] bar\n- [!] baz\n- [>] fam\n- [x] qux\n- [-] quux\n",
+        )
+        .unwrap();
+
+        let proj = Todoer::from_config(config, false).unwrap();
+        let statuses: Vec<(&str, Status)> = (0..proj.size)
+            .map(|index| {
+                (
+                    proj.data.0[&index].name.as_str(),
+                    proj.data.0[&index].status,
+                )
+            })
+            .collect();
+        assert_eq!(
+            statuses,
+            vec![
+                ("foo", Status::Open),
+                ("bar", Status::InProgress),
+                ("baz", Status::Blocked),
+                ("fam", Status::Open),
+            ]
+        );
+    }
+
+    #[test]
+    fn todo_metadata() {
+        let todo = Todo {
+            name: "ship release !high #work #ops due:2022-12-01".into(),
+            ..Todo::default()
+        };
+
+        assert_eq!(todo.tags(), vec!["work", "ops"]);
+        assert_eq!(todo.priority(), Some(Priority::High));
+        assert_eq!(todo.due(), chrono::NaiveDate::from_ymd_opt(2022, 12, 1));
+    }
+
+    #[test]
+    fn sort_values() {
+        let mut proj = get_todoer();
+        proj.set_value(String::from("call bank !low #home due:2022-12-03"));
+        proj.set_value(String::from("Answer email !high #work due:2022-12-05"));
+        proj.set_value(String::from("book flights !medium due:2022-12-01"));
+
+        proj.sort_values(SortKey::Priority).unwrap();
+        assert_eq!(
+            proj.get_value_names()[..3],
+            [
+                "Answer email !high #work due:2022-12-05",
+                "book flights !medium due:2022-12-01",
+                "call bank !low #home due:2022-12-03"
+            ]
+        );
+
+        proj.sort_values(SortKey::Due).unwrap();
+        assert_eq!(
+            proj.get_value_names()[..3],
+            [
+                "book flights !medium due:2022-12-01",
+                "call bank !low #home due:2022-12-03",
+                "Answer email !high #work due:2022-12-05"
+            ]
+        );
+
+        proj.sort_values(SortKey::Tag).unwrap();
+        assert_eq!(
+            proj.get_value_names()[..2],
+            [
+                "call bank !low #home due:2022-12-03",
+                "Answer email !high #work due:2022-12-05"
+            ]
+        );
+
+        proj.sort_values(SortKey::Alpha).unwrap();
+        assert_eq!(
+            proj.get_value_names(),
+            vec![
+                "Answer email !high #work due:2022-12-05",
+                "bar",
+                "book flights !medium due:2022-12-01",
+                "call bank !low #home due:2022-12-03",
+                "foo"
+            ]
+        );
+    }
+
+    #[test]
+    fn select() {
+        let mut proj = get_todoer();
+        proj.set_value(String::from("standup notes #standup"));
+        proj.set_value(String::from("fam #standup"));
+
+        assert_eq!(
+            proj.select(&Selection::Indices(vec![3, 1, 3])).unwrap(),
+            vec![1, 3]
+        );
+        assert_eq!(
+            proj.select(&Selection::Tag(String::from("standup")))
+                .unwrap(),
+            vec![2, 3]
+        );
+        assert_eq!(proj.select(&Selection::AllDone).unwrap(), vec![0]);
+        assert!(proj.select(&Selection::Indices(vec![1, 4])).is_err());
+    }
+
+    #[test]
+    fn parse_selection() {
+        let args: Vec<String> = vec!["1".into(), "3".into(), "5-7".into()];
+        assert_eq!(
+            Selection::try_from(args.as_slice()).unwrap(),
+            Selection::Indices(vec![1, 3, 5, 6, 7])
+        );
+
+        let args: Vec<String> = vec!["--tag".into(), "standup".into()];
+        assert_eq!(
+            Selection::try_from(args.as_slice()).unwrap(),
+            Selection::Tag(String::from("standup"))
+        );
+
+        let args: Vec<String> = vec!["7-5".into()];
+        assert!(Selection::try_from(args.as_slice()).is_err());
+        let args: Vec<String> = vec!["0-4294967295".into()];
+        assert!(matches!(
+            Selection::try_from(args.as_slice()),
+            Err(TodoerError::Parse { .. })
+        ));
+    }
+
+    #[test]
+    fn bulk_values() {
+        let mut proj = get_todoer();
+        proj.set_value(String::from("fam"));
+        proj.set_value(String::from("baz"));
+
+        proj.mark_done_all(&[0, 1, 3]).unwrap();
+        assert_eq!(proj.done_count, 3);
+
+        proj.remove_values(&[3, 0]).unwrap();
+        assert_eq!(
+            proj.get_value_all(),
+            vec![(&String::from("bar"), true), (&String::from("fam"), false)]
+        );
+        assert_eq!(proj.done_count, 1);
+    }
+
+    #[test]
+    fn move_values() {
+        let mut proj = get_todoer();
+        proj.set_value(String::from("fam"));
+        proj.set_value(String::from("baz"));
+        proj.move_values(&[1, 3], 0).unwrap();
+
+        assert_eq!(
+            proj.get_value_names(),
+            vec![
+                &String::from("bar"),
+                &String::from("baz"),
+                &String::from("foo"),
+                &String::from("fam")
+            ]
+        );
+    }
+
+    #[test]
+    fn tag_values() {
+        let mut proj = get_todoer();
+        proj.tag_values("#work", &[0, 1]).unwrap();
+        proj.tag_values("work", &[1]).unwrap();
+
+        assert_eq!(
+            proj.get_value_names(),
+            vec![&String::from("foo #work"), &String::from("bar #work")]
+        );
+    }
+
+    #[test]
+    fn mark_undone() {
+        let mut proj = get_todoer();
+        proj.mark_undone(0).unwrap();
+
+        assert_eq!(
+            proj.get_value_all(),
+            vec![(&String::from("foo"), false), (&String::from("bar"), false)]
+        );
+        assert_eq!(proj.done_count, 0);
+        assert!(matches!(proj.mark_undone(1), Err(TodoerError::Conflict(_))));
+    }
+
+    #[test]
+    fn edit_value() {
+        let mut proj = get_todoer();
+        proj.edit_value(1, String::from("baz")).unwrap();
+
+        assert_eq!(
+            proj.get_value_all(),
+            vec![(&String::from("foo"), true), (&String::from("baz"), false)]
+        );
+        assert!(matches!(
+            proj.edit_value(2, String::from("fam")),
+            Err(TodoerError::NotFound(2))
+        ));
+    }
+
+    #[test]
+    fn invalid_index() {
+        let mut proj = get_todoer();
+
+        assert!(matches!(proj.mark_done(2), Err(TodoerError::NotFound(2))));
+        assert!(matches!(
+            proj.remove_value(5),
+            Err(TodoerError::NotFound(5))
+        ));
+        assert!(matches!(
+            proj.remove_values(&[1, 2]),
+            Err(TodoerError::NotFound(2))
+        ));
+        assert!(matches!(proj.mark_done(0), Err(TodoerError::Conflict(_))));
+        assert_eq!(proj.size, 2);
+        assert_eq!(proj.done_count, 1);
+    }
+
+    #[test]
+    fn parse_todoer() {
+        let proj = Todoer::from_contents(
+            Path::new("wiki/todo"),
+            "22-11-25\n5/9\n- [x] foo\n- [ ] bar\n",
+        )
+        .unwrap();
+
+        assert_eq!(proj.size, 2);
+        assert_eq!(proj.done_count, 1);
+        assert_eq!(
+            proj.get_value_all(),
+            vec![(&String::from("foo"), true), (&String::from("bar"), false)]
+        );
+    }
+
+    #[test]
+    fn parse_todoer_error() {
+        let err = Todoer::from_contents(Path::new("wiki/todo"), "22-11-25\nfoo\n").unwrap_err();
+        assert!(matches!(err, TodoerError::Parse { line: 2, .. }));
+        assert!(Todoer::from_contents(Path::new("wiki/todo"), "").is_err());
+    }
+
+    #[test]
+    fn metadata_in_names() {
+        let mut proj = Todoer::default_todoer(PathBuf::from("wiki/todo/2022-11-25.md"));
+        for name in [
+            "rename the field to id:x",
+            "check done:2022-11-25T10:00",
+            "escaped \\blocked-by:y",
+        ] {
+            proj.set_value(String::from(name));
+        }
+        proj.data.0.get_mut(&0).unwrap().id = Some(String::from("k3xq9a"));
+
+        let contents = String::try_from(&proj).unwrap();
+        assert!(contents.contains("- [ ] rename the field to \\id:x created:"));
+        let read = Todoer::from_contents(Path::new("wiki/todo"), &contents).unwrap();
+        assert_eq!(
+            read.get_value_names(),
+            vec![
+                "rename the field to id:x",
+                "check done:2022-11-25T10:00",
+                "escaped \\blocked-by:y"
+            ]
+        );
+        assert_eq!(read.data.0[&0].id.as_deref(), Some("k3xq9a"));
+        assert_eq!(read.data.0[&1].completed, None);
+    }
+
+    #[test]
+    fn notes() {
+        let contents = "2022-11-25\n0/2\n# Morning\n- [ ] foo\nCall before noon\n- [ ] bar\n";
+        let mut proj = Todoer::from_contents(Path::new("wiki/todo"), contents).unwrap();
+        assert_eq!(proj.config, PathBuf::from("wiki/todo/2022-11-25.md"));
+        assert_eq!(proj.get_value_names(), vec!["foo", "bar"]);
+        assert_eq!(String::try_from(&proj).unwrap(), contents);
+
+        // Notes stay where they were, and ones past the last todo go at the end
+        proj.remove_value(1).unwrap();
+        assert_eq!(
+            String::try_from(&proj).unwrap(),
+            "2022-11-25\n0/1\n# Morning\n- [ ] foo\nCall before noon\n"
+        );
+    }
+
     #[test]
     fn print_values() {
         let proj = get_todoer();
@@ -1,5 +1,5 @@
+use crate::error::Result;
 use crate::todoer::{Todoer, Todos};
-use anyhow::Result;
 use std::collections::HashMap;
 use std::path::PathBuf;
 
@@ -12,20 +12,17 @@ pub fn default_data() -> HashMap<String, Todoer> {
     HashMap::new()
 }
 impl Todoers {
+    // Reads every markdown file in the directory, skipping anything else
     pub fn from_todos_dir(todos_dir: PathBuf) -> Result<Self> {
-        let todoers = std::fs::read_dir(&todos_dir)?
-            .map(|res| {
-                res.map(|entry| {
-                    let path = entry.path();
-                    if std::fs::metadata(&path).is_ok() {
-                        let contents = std::fs::read_to_string(&path).unwrap();
-                        let data: Todoer = contents.try_into().expect("Error parsing data");
-                        return data;
-                    }
-                    Todoer::default_todoer(path)
-                })
-            })
-            .collect::<Result<Vec<_>, std::io::Error>>()?;
+        let mut todoers = Vec::new();
+        for entry in std::fs::read_dir(&todos_dir)? {
+            let path = entry?.path();
+            if path.extension().is_none_or(|extension| extension != "md") {
+                continue;
+            }
+            todoers.push(Todoer::from_config(path, true)?);
+        }
+        todoers.sort_by(|a, b| a.config.cmp(&b.config));
 
         Ok(Todoers { todoers })
     }
@@ -35,7 +32,7 @@ impl Todoers {
         let tododers = &self.todoers;
         tododers.iter().for_each(|todoer| {
             all += "\n";
-            all += todoer.config.as_os_str().to_str().unwrap();
+            all += &todoer.config.to_string_lossy();
             all += &todoer.print_values();
         });
         all
@@ -48,7 +45,7 @@ impl Todoers {
         tododers.iter().for_each(|todoer| {
             let Todos(todos) = &todoer.data;
             todos.values().for_each(|todo| {
-                if !todo.done {
+                if !todo.status.is_closed() {
                     res += &(index.to_string() + "). " + &todo.name + "\n");
                     index += 1;
                 }
@@ -59,7 +56,7 @@ impl Todoers {
         tododers.iter().for_each(|todoer| {
             let Todos(todos) = &todoer.data;
             todos.values().for_each(|todo| {
-                if todo.done {
+                if todo.is_done() {
                     res += &(index.to_string() + "). " + &todo.name + "\n");
                     index += 1;
                 }
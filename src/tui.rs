@@ -0,0 +1,677 @@
+use std::collections::VecDeque;
+use std::path::PathBuf;
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError};
+
+use chrono::{Duration, Local, NaiveDate, NaiveDateTime};
+use console::{pad_str, strip_ansi_codes, style, Alignment, Key, Term};
+
+use crate::agenda::Agenda;
+use crate::calendar::Calendar;
+use crate::error::{Result, TodoerError};
+use crate::interactive::{day_name, Action, Mode, Session};
+use crate::pomodoro::{Phase, Pomodoro};
+use crate::quick_add;
+use crate::todoer::{day_of, format_duration, tracked_suffix, Todos, DAY_FORMAT};
+
+const SIDEBAR_WIDTH: usize = 18;
+
+const HINTS: &str =
+    "a add  x done  u undo  e edit  D delete  J/K move  space mark  t tag  s sort  S status  T timer  f focus  A agenda  C calendar  tab days  q quit";
+
+const FOCUS_HINTS: &str = "x done  q stop focusing";
+
+const AGENDA_HINTS: &str = "q back to the list";
+
+const CALENDAR_HINTS: &str = "h/l day  j/k week  enter open  q back to the list";
+
+// How far ahead the agenda screen looks
+const AGENDA_DAYS: u32 = 7;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Focus {
+    List,
+    Days,
+}
+
+// What the text typed into the footer is used for once enter is pressed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Prompt {
+    Add,
+    Edit,
+    Tag,
+    Sort,
+    Status,
+}
+
+#[derive(Debug)]
+pub struct App {
+    pub session: Session,
+    pub focus: Focus,
+    pub prompt: Option<(Prompt, String)>,
+    pub message: String,
+    // The time of the last tick, which focus mode counts down from
+    pub now: NaiveDateTime,
+    // Shown in place of the list until closed, see `agenda`
+    pub agenda: Option<Agenda>,
+    // The day under the cursor while the calendar is shown in place of the list
+    pub calendar: Option<NaiveDate>,
+}
+
+impl App {
+    pub fn new(session: Session, focus: Focus) -> Self {
+        App {
+            session,
+            focus,
+            prompt: None,
+            message: String::new(),
+            now: Local::now().naive_local(),
+            agenda: None,
+            calendar: None,
+        }
+    }
+
+    // Called before every frame so focus mode moves on even while no key is pressed
+    pub fn tick(&mut self, now: NaiveDateTime) {
+        self.now = now;
+        match self.session.tick(now) {
+            Ok(Some(message)) => self.message = message,
+            Ok(None) => {}
+            Err(err) => self.message = err.to_string(),
+        }
+    }
+
+    fn submit(&mut self, prompt: Prompt, text: String) -> Result<()> {
+        let action = match prompt {
+            Prompt::Add => Action::Add(quick_add::parse(&text, self.now.date()).to_string()),
+            Prompt::Edit => Action::Edit(text),
+            Prompt::Tag => Action::Tag(text),
+            Prompt::Sort => Action::Sort(text.trim().parse()?),
+            Prompt::Status => Action::SetStatus(text.trim().parse()?),
+        };
+        self.session.apply(action)
+    }
+
+    fn handle_prompt_key(&mut self, key: Key) -> Result<()> {
+        let Some((prompt, mut text)) = self.prompt.take() else {
+            return Ok(());
+        };
+        match key {
+            Key::Enter => return self.submit(prompt, text),
+            Key::Escape => return Ok(()),
+            Key::Backspace => {
+                text.pop();
+            }
+            Key::Char(c) => text.push(c),
+            _ => {}
+        }
+        self.prompt = Some((prompt, text));
+        Ok(())
+    }
+
+    fn handle_days_key(&mut self, key: Key) -> Result<bool> {
+        let day_cursor = self.session.day_cursor;
+        match key {
+            Key::Char('q') | Key::Escape => return Ok(false),
+            Key::Char('j') | Key::ArrowDown => {
+                self.session.apply(Action::OpenDay(day_cursor + 1))?
+            }
+            Key::Char('k') | Key::ArrowUp if day_cursor > 0 => {
+                self.session.apply(Action::OpenDay(day_cursor - 1))?
+            }
+            Key::Tab | Key::Enter | Key::Char('l') | Key::ArrowRight => self.focus = Focus::List,
+            _ => {}
+        }
+        Ok(true)
+    }
+
+    fn handle_list_key(&mut self, key: Key) -> Result<bool> {
+        let action = match key {
+            Key::Char('q') | Key::Escape => return Ok(false),
+            Key::Tab | Key::Char('h') | Key::ArrowLeft => {
+                self.focus = Focus::Days;
+                return Ok(true);
+            }
+            Key::Char('a') => {
+                self.prompt = Some((Prompt::Add, String::new()));
+                return Ok(true);
+            }
+            Key::Char('e') => {
+                if let Some(todo) = self.session.todoer.data.0.get(&self.session.cursor) {
+                    self.prompt = Some((Prompt::Edit, todo.name.clone()));
+                }
+                return Ok(true);
+            }
+            Key::Char('t') => {
+                self.prompt = Some((Prompt::Tag, String::new()));
+                return Ok(true);
+            }
+            Key::Char('s') => {
+                self.prompt = Some((Prompt::Sort, String::new()));
+                return Ok(true);
+            }
+            Key::Char('S') => {
+                self.prompt = Some((Prompt::Status, String::new()));
+                return Ok(true);
+            }
+            Key::Char('j') | Key::ArrowDown => Action::CursorDown,
+            Key::Char('k') | Key::ArrowUp => Action::CursorUp,
+            Key::Char('g') | Key::Home => Action::CursorTop,
+            Key::Char('G') | Key::End => Action::CursorBottom,
+            Key::Char(' ') => Action::ToggleMark,
+            Key::Char('x') | Key::Enter => Action::Done,
+            Key::Char('u') => Action::Undone,
+            Key::Char('D') | Key::Del => Action::Delete,
+            Key::Char('J') => Action::MoveDown,
+            Key::Char('K') => Action::MoveUp,
+            Key::Char('T') => Action::ToggleTimer,
+            Key::Char('f') => Action::StartFocus(self.now),
+            Key::Char('C') => {
+                let day = day_of(&self.session.todoer.config).unwrap_or(self.now.date());
+                self.calendar = Some(day);
+                return Ok(true);
+            }
+            Key::Char('A') => {
+                let todo_dir = self.session.todo_dir();
+                let today = self.now.date();
+                let agenda = Agenda::load(&todo_dir, &self.session.todoer, today, AGENDA_DAYS)?;
+                self.agenda = Some(agenda);
+                return Ok(true);
+            }
+            _ => return Ok(true),
+        };
+        self.session.apply(action)?;
+        Ok(true)
+    }
+
+    // Moves across dates, opening the one under the cursor on enter
+    fn handle_calendar_key(&mut self, key: Key, day: NaiveDate) -> Result<()> {
+        let moved = match key {
+            Key::Char('q' | 'C') | Key::Escape => None,
+            Key::Char('h') | Key::ArrowLeft => Some(day - Duration::days(1)),
+            Key::Char('l') | Key::ArrowRight => Some(day + Duration::days(1)),
+            Key::Char('k') | Key::ArrowUp => Some(day - Duration::days(7)),
+            Key::Char('j') | Key::ArrowDown => Some(day + Duration::days(7)),
+            Key::Enter => {
+                let name = day.format(DAY_FORMAT).to_string();
+                let Some(index) = self.session.days.iter().position(|day| day.name == name) else {
+                    return Err(TodoerError::Config(format!(
+                        "there is no list for {}",
+                        name
+                    )));
+                };
+                self.session.apply(Action::OpenDay(index))?;
+                self.focus = Focus::List;
+                None
+            }
+            _ => Some(day),
+        };
+        self.calendar = moved;
+        Ok(())
+    }
+
+    // Quitting from focus mode goes back to the list
+    fn handle_focus_key(&mut self, key: Key) -> Result<()> {
+        let action = match key {
+            Key::Char('q') | Key::Escape => Action::StopFocus,
+            Key::Char('x') | Key::Enter => Action::FinishFocus,
+            _ => return Ok(()),
+        };
+        self.session.apply(action)
+    }
+
+    // Returns false once the user asks to quit. Failed operations are shown in the status bar
+    pub fn handle_key(&mut self, key: Key) -> bool {
+        self.message.clear();
+        let result = if self.prompt.is_some() {
+            self.handle_prompt_key(key).map(|_| true)
+        } else if self.session.pomodoro.is_some() {
+            self.handle_focus_key(key).map(|_| true)
+        } else if let Some(day) = self.calendar {
+            self.handle_calendar_key(key, day).map(|_| true)
+        } else if self.agenda.is_some() {
+            if matches!(key, Key::Char('q' | 'A') | Key::Escape) {
+                self.agenda = None;
+            }
+            Ok(true)
+        } else {
+            match self.focus {
+                Focus::List => self.handle_list_key(key),
+                Focus::Days => self.handle_days_key(key),
+            }
+        };
+        result.unwrap_or_else(|err| {
+            self.message = err.to_string();
+            true
+        })
+    }
+
+    fn render_day(&self, index: usize) -> String {
+        let Some(day) = self.session.days.get(index) else {
+            return " ".repeat(SIDEBAR_WIDTH - 1) + "│";
+        };
+        let line = format!(" {} {}/{}", day.name, day.done_count, day.size);
+        let line = pad_str(&line, SIDEBAR_WIDTH - 1, Alignment::Left, Some("…")).into_owned();
+        let line = if index == self.session.day_cursor && self.focus == Focus::Days {
+            style(line).reverse().to_string()
+        } else if index == self.session.day_cursor {
+            style(line).bold().to_string()
+        } else {
+            line
+        };
+        line + "│"
+    }
+
+    fn render_todo(&self, index: u32, width: usize) -> String {
+        let Todos(todos) = &self.session.todoer.data;
+        let Some(todo) = todos.get(&index) else {
+            return String::new();
+        };
+        let mark = if self.session.marked.contains(&index) {
+            "*"
+        } else {
+            " "
+        };
+        let check = format!("[{}]", todo.status.marker());
+        let timing = match &self.session.timer {
+            Some(timer) if timer.is_on(&self.session.todoer, index) => {
+                format!(" ({} running)", format_duration(timer.elapsed()))
+            }
+            _ => String::new(),
+        };
+        let waiting = self.session.dependencies.waiting_on(todo);
+        let waiting = if todo.status.is_closed() || waiting.is_empty() {
+            String::new()
+        } else {
+            format!(" (waiting on {})", waiting.join(", "))
+        };
+        let line = format!(
+            "{}{:>3}. {} {}{}{}{}",
+            mark,
+            index,
+            check,
+            todo.name,
+            tracked_suffix(todo),
+            timing,
+            waiting
+        );
+        let line = pad_str(&line, width, Alignment::Left, Some("…")).into_owned();
+        if index == self.session.cursor && self.focus == Focus::List {
+            style(line).reverse().to_string()
+        } else if todo.status.is_closed() || !waiting.is_empty() {
+            style(line).dim().to_string()
+        } else {
+            line
+        }
+    }
+
+    // The countdown for the todo being focused on, in place of the list
+    fn render_focus(&self, pomodoro: &Pomodoro, width: usize, rows: usize) -> Vec<String> {
+        let remaining = pomodoro.remaining(self.now).num_seconds();
+        let phase = match pomodoro.phase {
+            Phase::Work => "work",
+            Phase::ShortBreak => "break",
+            Phase::LongBreak => "long break",
+        };
+        let logged = self
+            .session
+            .todoer
+            .find_value(&pomodoro.name)
+            .filter(|_| self.session.todoer.config == pomodoro.config)
+            .map(|index| self.session.todoer.data.0[&index].pomodoros)
+            .unwrap_or(pomodoro.finished);
+        let body = [
+            String::new(),
+            format!(" focus  {}", pomodoro.name),
+            String::new(),
+            format!(" {} {:02}:{:02}", phase, remaining / 60, remaining % 60),
+            format!(" {} today, {} this session", logged, pomodoro.finished),
+        ];
+        (0..rows)
+            .map(|row| {
+                let line = body.get(row).map(String::as_str).unwrap_or_default();
+                let line = pad_str(line, width, Alignment::Left, Some("…")).into_owned();
+                if row == 3 {
+                    style(line).bold().to_string()
+                } else {
+                    line
+                }
+            })
+            .collect()
+    }
+
+    // What is due across every list, in place of the list
+    fn render_agenda(&self, agenda: &Agenda, width: usize, rows: usize) -> Vec<String> {
+        let mut body = vec![String::new()];
+        for (heading, items) in agenda.groups() {
+            body.push(style(format!(" {}", heading)).bold().to_string());
+            for item in items {
+                body.push(format!("   {} ({})", item.name, item.list));
+            }
+        }
+        if body.len() == 1 {
+            body.push(format!(" nothing due in the next {} days", AGENDA_DAYS));
+        }
+        (0..rows)
+            .map(|row| {
+                let line = body.get(row).map(String::as_str).unwrap_or_default();
+                pad_str(line, width, Alignment::Left, Some("…")).into_owned()
+            })
+            .collect()
+    }
+
+    // The month of the day under the cursor, coloured by how much got done each day
+    fn render_calendar(&self, day: NaiveDate, width: usize, rows: usize) -> Vec<String> {
+        let days = self
+            .session
+            .days
+            .iter()
+            .map(|day| (day.name.as_str(), day.done_count, day.size));
+        let mut body = vec![String::new()];
+        body.extend(Calendar::new(day, days).lines(Some(day)));
+        (0..rows)
+            .map(|row| {
+                let line = body.get(row).map(String::as_str).unwrap_or_default();
+                pad_str(line, width, Alignment::Left, Some("…")).into_owned()
+            })
+            .collect()
+    }
+
+    // Renders exactly `height` lines, each `width` columns wide
+    pub fn render(&self, width: usize, height: usize) -> Vec<String> {
+        let list_width = width.saturating_sub(SIDEBAR_WIDTH);
+        let rows = height.saturating_sub(3);
+        let mut lines = Vec::with_capacity(height);
+
+        let title = format!(" todoer  {}", day_name(&self.session.todoer.config));
+        let title = pad_str(&title, width, Alignment::Left, Some("…")).into_owned();
+        lines.push(style(title).bold().reverse().to_string());
+
+        if let Some(pomodoro) = &self.session.pomodoro {
+            lines.extend(self.render_focus(pomodoro, width, rows));
+        } else if let Some(day) = self.calendar {
+            lines.extend(self.render_calendar(day, width, rows));
+        } else if let Some(agenda) = &self.agenda {
+            lines.extend(self.render_agenda(agenda, width, rows));
+        } else {
+            self.render_list(&mut lines, list_width, rows);
+        }
+
+        let status = format!(
+            " {} open  {} done  {} total  {}",
+            self.session.todoer.open_count(),
+            self.session.todoer.done_count,
+            self.session.todoer.size,
+            self.message
+        );
+        let status = pad_str(&status, width, Alignment::Left, Some("…")).into_owned();
+        lines.push(style(status).reverse().to_string());
+
+        let footer = match &self.prompt {
+            Some((prompt, text)) => {
+                let label = match prompt {
+                    Prompt::Add => "add",
+                    Prompt::Edit => "edit",
+                    Prompt::Tag => "tag",
+                    Prompt::Sort => "sort by priority|due|tag|alpha",
+                    Prompt::Status => "status open|in-progress|blocked|deferred|done|cancelled",
+                };
+                format!(" {}> {}_", label, text)
+            }
+            None if self.session.pomodoro.is_some() => format!(" {}", FOCUS_HINTS),
+            None if self.calendar.is_some() => format!(" {}", CALENDAR_HINTS),
+            None if self.agenda.is_some() => format!(" {}", AGENDA_HINTS),
+            None => format!(" {}", HINTS),
+        };
+        lines.push(pad_str(&footer, width, Alignment::Left, Some("…")).into_owned());
+
+        lines.truncate(height);
+        lines
+    }
+
+    fn render_list(&self, lines: &mut Vec<String>, list_width: usize, rows: usize) {
+        let day_offset = (self.session.day_cursor + 1).saturating_sub(rows);
+        let list_offset = usize::try_from(self.session.cursor).unwrap() + 1;
+        let list_offset = list_offset.saturating_sub(rows);
+        for row in 0..rows {
+            let index = u32::try_from(row + list_offset).unwrap();
+            let todo = if index < self.session.todoer.size {
+                self.render_todo(index, list_width)
+            } else if row == 0 && self.session.todoer.size == 0 {
+                pad_str(
+                    " nothing to do, press a to add a todo",
+                    list_width,
+                    Alignment::Left,
+                    None,
+                )
+                .into_owned()
+            } else {
+                " ".repeat(list_width)
+            };
+            lines.push(self.render_day(row + day_offset) + &todo);
+        }
+    }
+}
+
+// What waiting for a key ended with
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Input {
+    Key(Key),
+    // No key within the timeout
+    Tick,
+    // There is no more input, which ends the session
+    Closed,
+}
+
+// Where the UI reads keys from and draws frames to, so it can run without a terminal
+pub trait Backend {
+    // Width and height in columns and rows
+    fn size(&self) -> (usize, usize);
+
+    // Waits for a key, for at most `timeout` when one is given
+    fn read_input(&mut self, timeout: Option<std::time::Duration>) -> Result<Input>;
+
+    fn now(&self) -> NaiveDateTime {
+        Local::now().naive_local()
+    }
+
+    fn draw(&mut self, lines: &[String]) -> Result<()>;
+
+    fn enter(&mut self) -> Result<()> {
+        Ok(())
+    }
+
+    fn leave(&mut self) -> Result<()> {
+        Ok(())
+    }
+}
+
+pub struct TermBackend {
+    term: Term,
+    // Keys read on their own thread, so waiting for one can time out
+    keys: Option<Receiver<std::io::Result<Key>>>,
+}
+
+impl TermBackend {
+    pub fn new(term: Term) -> Self {
+        TermBackend { term, keys: None }
+    }
+
+    fn keys(&mut self) -> &Receiver<std::io::Result<Key>> {
+        self.keys.get_or_insert_with(|| {
+            let (sender, receiver) = mpsc::channel();
+            let term = self.term.clone();
+            std::thread::spawn(move || loop {
+                let key = term.read_key();
+                let failed = key.is_err();
+                if sender.send(key).is_err() || failed {
+                    break;
+                }
+            });
+            receiver
+        })
+    }
+}
+
+impl Backend for TermBackend {
+    fn size(&self) -> (usize, usize) {
+        let (height, width) = self.term.size();
+        (width.into(), height.into())
+    }
+
+    fn read_input(&mut self, timeout: Option<std::time::Duration>) -> Result<Input> {
+        let keys = self.keys();
+        let key = match timeout {
+            Some(timeout) => match keys.recv_timeout(timeout) {
+                Ok(key) => key,
+                Err(RecvTimeoutError::Timeout) => return Ok(Input::Tick),
+                Err(RecvTimeoutError::Disconnected) => return Ok(Input::Closed),
+            },
+            None => match keys.recv() {
+                Ok(key) => key,
+                Err(_) => return Ok(Input::Closed),
+            },
+        };
+        Ok(Input::Key(key?))
+    }
+
+    fn draw(&mut self, lines: &[String]) -> Result<()> {
+        self.term.move_cursor_to(0, 0)?;
+        self.term.write_str(&lines.join("\n"))?;
+        self.term.flush()?;
+        Ok(())
+    }
+
+    fn enter(&mut self) -> Result<()> {
+        self.term.hide_cursor()?;
+        self.term.clear_screen()?;
+        Ok(())
+    }
+
+    fn leave(&mut self) -> Result<()> {
+        self.term.clear_screen()?;
+        self.term.show_cursor()?;
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Step {
+    Key(Key),
+    // Moves the clock on without pressing anything
+    Wait(Duration),
+}
+
+// Replays scripted keys and records every frame with styling stripped. The clock starts at
+// 09:00 on 2022-11-25 and only moves on when told to
+#[derive(Debug)]
+pub struct TestBackend {
+    pub width: usize,
+    pub height: usize,
+    pub keys: VecDeque<Step>,
+    pub screens: Vec<Vec<String>>,
+    pub clock: NaiveDateTime,
+}
+
+impl TestBackend {
+    pub fn new(width: usize, height: usize) -> Self {
+        TestBackend {
+            width,
+            height,
+            keys: VecDeque::new(),
+            screens: Vec::new(),
+            clock: NaiveDate::from_ymd_opt(2022, 11, 25)
+                .and_then(|day| day.and_hms_opt(9, 0, 0))
+                .expect("valid date"),
+        }
+    }
+
+    pub fn key(mut self, key: Key) -> Self {
+        self.keys.push_back(Step::Key(key));
+        self
+    }
+
+    // Queues each character as a key press, with `\n` as enter and `\t` as tab
+    pub fn type_text(mut self, text: &str) -> Self {
+        self.keys.extend(text.chars().map(|c| match c {
+            '\n' => Step::Key(Key::Enter),
+            '\t' => Step::Key(Key::Tab),
+            c => Step::Key(Key::Char(c)),
+        }));
+        self
+    }
+
+    pub fn wait(mut self, duration: Duration) -> Self {
+        self.keys.push_back(Step::Wait(duration));
+        self
+    }
+
+    pub fn last_screen(&self) -> String {
+        self.screens
+            .last()
+            .map(|lines| lines.join("\n"))
+            .unwrap_or_default()
+    }
+}
+
+impl Backend for TestBackend {
+    fn size(&self) -> (usize, usize) {
+        (self.width, self.height)
+    }
+
+    fn read_input(&mut self, _timeout: Option<std::time::Duration>) -> Result<Input> {
+        match self.keys.pop_front() {
+            Some(Step::Key(key)) => Ok(Input::Key(key)),
+            Some(Step::Wait(duration)) => {
+                self.clock += duration;
+                Ok(Input::Tick)
+            }
+            None => Ok(Input::Closed),
+        }
+    }
+
+    fn now(&self) -> NaiveDateTime {
+        self.clock
+    }
+
+    fn draw(&mut self, lines: &[String]) -> Result<()> {
+        self.screens.push(
+            lines
+                .iter()
+                .map(|line| strip_ansi_codes(line).trim_end().to_string())
+                .collect(),
+        );
+        Ok(())
+    }
+}
+
+// Draws a frame, then handles keys until the user quits or the backend runs out of input.
+// In focus mode the frame is redrawn every second for the countdown
+pub fn run_with<B: Backend>(backend: &mut B, app: &mut App) -> Result<()> {
+    backend.enter()?;
+    loop {
+        app.tick(backend.now());
+        let (width, height) = backend.size();
+        backend.draw(&app.render(width, height))?;
+
+        let timeout = app
+            .session
+            .pomodoro
+            .as_ref()
+            .map(|_| std::time::Duration::from_secs(1));
+        match backend.read_input(timeout)? {
+            Input::Key(key) => {
+                if !app.handle_key(key) {
+                    break;
+                }
+            }
+            Input::Tick => {}
+            Input::Closed => break,
+        }
+    }
+    backend.leave()
+}
+
+pub fn run(today: PathBuf, mode: Mode, focus: Focus) -> Result<()> {
+    let mut app = App::new(Session::new(today, mode)?, focus);
+    run_with(&mut TermBackend::new(Term::stdout()), &mut app)
+}
@@ -0,0 +1,688 @@
+use std::path::{Path, PathBuf};
+
+use actix_web::http::{header, Method, StatusCode};
+use actix_web::{test, web, App, HttpServer, ResponseError};
+use rust::api::DayList;
+use rust::auth::{users_file, Users};
+use rust::client::Client;
+use rust::config::get_config;
+use rust::config::Operation;
+use rust::dependencies::stable_id;
+use rust::error::TodoerError;
+use rust::remote::{Outcome, Remote, RemoteSettings};
+use rust::server::{routes, Store, Telemetry};
+use rust::settings::{Settings, StateMode};
+use rust::todoer::{Selection, SortKey, Status, Todoer};
+
+fn get_root(name: &str) -> PathBuf {
+    let mut dir = std::env::temp_dir();
+    dir.push(format!("todoer-server-{}-{}", name, std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+fn write_today(root: &Path, contents: &str) -> PathBuf {
+    let today = get_config(Some(root.to_path_buf()), None).unwrap();
+    std::fs::create_dir_all(today.parent().unwrap()).unwrap();
+    let stem = today.file_stem().unwrap().to_string_lossy().into_owned();
+    std::fs::write(&today, format!("{}\n{}", stem, contents)).unwrap();
+    today
+}
+
+fn store(root: &Path, state: StateMode) -> web::Data<Store> {
+    web::Data::new(Store::new(root.to_path_buf(), state, true))
+}
+
+#[actix_web::test]
+async fn status_codes() {
+    assert_eq!(
+        TodoerError::NotFound(3).status_code(),
+        StatusCode::NOT_FOUND
+    );
+    assert_eq!(
+        TodoerError::Conflict(String::from("todo 0 is already done")).status_code(),
+        StatusCode::CONFLICT
+    );
+    assert_eq!(
+        TodoerError::Config(String::from("bad index")).status_code(),
+        StatusCode::BAD_REQUEST
+    );
+
+    let response = TodoerError::Unauthorized(String::from("unknown user")).error_response();
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+    assert!(response.headers().contains_key("www-authenticate"));
+}
+
+#[actix_web::test]
+async fn file_mode_sees_other_writers() {
+    let root = get_root("file");
+    write_today(&root, "0/1\n- [ ] foo\n");
+    let app = test::init_service(
+        App::new()
+            .app_data(store(&root, StateMode::File))
+            .configure(routes),
+    )
+    .await;
+
+    let req = test::TestRequest::get().uri("/api/todos").to_request();
+    let list: DayList = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(list.size, 1);
+
+    write_today(&root, "0/2\n- [ ] foo\n- [ ] bar\n");
+    let req = test::TestRequest::get().uri("/api/todos").to_request();
+    let list: DayList = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(list.size, 2);
+}
+
+#[actix_web::test]
+async fn memory_mode_keeps_its_copy() {
+    let root = get_root("memory");
+    let today = write_today(&root, "0/1\n- [ ] foo\n");
+    let app = test::init_service(
+        App::new()
+            .app_data(store(&root, StateMode::Memory))
+            .configure(routes),
+    )
+    .await;
+
+    let req = test::TestRequest::post()
+        .uri("/add")
+        .set_payload("bar")
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), StatusCode::OK);
+
+    write_today(&root, "0/0\n");
+    let req = test::TestRequest::post()
+        .uri("/api/todos/1/done")
+        .to_request();
+    let list: DayList = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(list.done_count, 1);
+    let contents = std::fs::read_to_string(today).unwrap();
+    assert!(contents.contains("\n1/2\n- [ ] foo\n- [x] bar created:"));
+}
+
+#[actix_web::test]
+async fn plain_add_is_a_quick_add() {
+    let root = get_root("plain-add");
+    write_today(&root, "0/0\n");
+    let app = test::init_service(
+        App::new()
+            .app_data(store(&root, StateMode::Watch))
+            .configure(routes),
+    )
+    .await;
+
+    let req = test::TestRequest::post()
+        .uri("/add")
+        .set_payload("write notes ~30m !high #docs")
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), StatusCode::OK);
+    let req = test::TestRequest::get().uri("/api/todos").to_request();
+    let list: DayList = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(list.todos[0].name, "write notes !high #docs est:30m");
+}
+
+#[actix_web::test]
+async fn users_need_a_token() {
+    let root = get_root("users");
+    let mut users = Users::default();
+    let token = users.add("alice").unwrap();
+    users.save(&users_file(&root)).unwrap();
+    let app = test::init_service(
+        App::new()
+            .app_data(store(&root, StateMode::Watch))
+            .configure(routes),
+    )
+    .await;
+
+    let req = test::TestRequest::get().uri("/api/todos").to_request();
+    let res = test::call_service(&app, req).await;
+    assert_eq!(res.status(), StatusCode::UNAUTHORIZED);
+
+    let req = test::TestRequest::post()
+        .uri("/api/todos")
+        .insert_header(("Authorization", format!("Bearer {}", token)))
+        .set_json(serde_json::json!({ "name": "foo" }))
+        .to_request();
+    let list: DayList = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(list.size, 1);
+
+    let today = get_config(Some(root.join("users").join("alice")), None).unwrap();
+    assert!(std::fs::metadata(today).is_ok());
+    assert!(std::fs::metadata(root.join("wiki")).is_err());
+}
+
+#[actix_web::test]
+async fn bulk_changes_all_or_nothing() {
+    let root = get_root("bulk");
+    write_today(&root, "0/3\n- [ ] foo id:k3xq9a\n- [ ] bar\n- [ ] baz\n");
+    let app = test::init_service(
+        App::new()
+            .app_data(store(&root, StateMode::Watch))
+            .configure(routes),
+    )
+    .await;
+    let bulk = |todos: serde_json::Value| {
+        test::TestRequest::post()
+            .uri("/api/todos/bulk")
+            .set_json(serde_json::json!({ "action": "done", "todos": todos }))
+            .to_request()
+    };
+
+    let req = bulk(serde_json::json!([{ "id": "k3xq9a", "name": "foo" }, { "name": "qux" }]));
+    let res = test::call_service(&app, req).await;
+    assert_eq!(res.status(), StatusCode::CONFLICT);
+    let req = test::TestRequest::get().uri("/api/todos").to_request();
+    let list: DayList = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(list.done_count, 0);
+
+    let req = bulk(serde_json::json!([{ "id": "k3xq9a", "name": "foo" }, { "name": "baz" }]));
+    let list: DayList = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(list.done_count, 2);
+    assert!(!list.todos[1].done);
+}
+
+#[actix_web::test]
+async fn no_users_refused_unless_anonymous() {
+    let root = get_root("anonymous");
+    write_today(&root, "0/1\n- [ ] foo\n");
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(Store::new(
+                root.clone(),
+                StateMode::Watch,
+                false,
+            )))
+            .configure(routes),
+    )
+    .await;
+
+    let req = test::TestRequest::delete().uri("/api/todos/0").to_request();
+    let res = test::call_service(&app, req).await;
+    assert_eq!(res.status(), StatusCode::UNAUTHORIZED);
+    let req = test::TestRequest::get().uri("/healthz").to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), StatusCode::OK);
+
+    let app = test::init_service(
+        App::new()
+            .app_data(store(&root, StateMode::Watch))
+            .configure(routes),
+    )
+    .await;
+    let req = test::TestRequest::get().uri("/api/todos").to_request();
+    let list: DayList = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(list.size, 1);
+}
+
+#[actix_web::test]
+async fn any_origin_gets_no_credentials() {
+    let cors = |origins: &[&str]| Settings {
+        cors_origins: origins.iter().map(|origin| origin.to_string()).collect(),
+        ..Settings::default()
+    };
+    for (origins, credentials) in [(&["*"][..], false), (&["https://example.com"][..], true)] {
+        let app = test::init_service(
+            App::new()
+                .app_data(store(&get_root("cors"), StateMode::Watch))
+                .wrap(cors(origins).cors())
+                .configure(routes),
+        )
+        .await;
+        let req = test::TestRequest::get()
+            .uri("/api/todos")
+            .insert_header(("Origin", "https://example.com"))
+            .to_request();
+        let res = test::call_service(&app, req).await;
+        assert_eq!(res.status(), StatusCode::OK);
+        assert_eq!(
+            res.headers()
+                .contains_key("access-control-allow-credentials"),
+            credentials
+        );
+    }
+}
+
+#[actix_web::test]
+async fn health_and_metrics_skip_auth() {
+    let root = get_root("metrics");
+    let mut users = Users::default();
+    let token = users.add("alice").unwrap();
+    users.save(&users_file(&root)).unwrap();
+    // Cancelled todos are neither open nor done
+    write_today(
+        &root.join("users").join("alice"),
+        "1/3\n- [ ] foo\n- [x] bar\n- [-] baz\n",
+    );
+    let app = test::init_service(
+        App::new()
+            .app_data(store(&root, StateMode::Watch))
+            .wrap(Telemetry)
+            .configure(routes),
+    )
+    .await;
+
+    for uri in ["/healthz", "/readyz"] {
+        let req = test::TestRequest::get().uri(uri).to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), StatusCode::OK);
+    }
+    let req = test::TestRequest::post().uri("/add").to_request();
+    let res = test::call_service(&app, req).await;
+    assert_eq!(res.status(), StatusCode::UNAUTHORIZED);
+    let req = test::TestRequest::get()
+        .uri("/api/todos")
+        .insert_header(("Authorization", format!("Bearer {}", token)))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), StatusCode::OK);
+
+    let req = test::TestRequest::get().uri("/metrics").to_request();
+    let body = test::call_and_read_body(&app, req).await;
+    let body = String::from_utf8(body.to_vec()).unwrap();
+    assert!(body.contains(
+        "todoer_http_requests_total{method=\"GET\",route=\"/healthz\",status=\"200\"} 1\n"
+    ));
+    assert!(body.contains("status=\"401\"} 1\n"), "{}", body);
+    assert!(body.contains("todoer_todos_open 1\n"), "{}", body);
+    assert!(body.contains("todoer_todos_done 1\n"), "{}", body);
+}
+
+#[actix_web::test]
+async fn openapi_document() {
+    let root = get_root("openapi");
+    let app = test::init_service(
+        App::new()
+            .app_data(store(&root, StateMode::Watch))
+            .configure(routes),
+    )
+    .await;
+
+    let req = test::TestRequest::get().uri("/openapi.json").to_request();
+    let doc: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+    assert!(doc["openapi"].as_str().unwrap().starts_with("3."));
+    assert!(doc["paths"]["/api/todos/{index}/done"]["post"].is_object());
+    assert!(doc["components"]["schemas"]["DayList"].is_object());
+    assert!(doc["components"]["securitySchemes"]["bearer"].is_object());
+}
+
+#[actix_web::test]
+async fn client_against_a_server() {
+    let root = get_root("client");
+    let mut users = Users::default();
+    let token = users.add("alice").unwrap();
+    users.save(&users_file(&root)).unwrap();
+
+    let data = store(&root, StateMode::Watch);
+    let server = HttpServer::new(move || App::new().app_data(data.clone()).configure(routes))
+        .workers(1)
+        .bind(("127.0.0.1", 0))
+        .unwrap();
+    let base = format!("http://{}", server.addrs()[0]);
+    actix_web::rt::spawn(server.run());
+
+    // The client blocks, so it runs off the server's thread
+    web::block(move || {
+        let client = Client::new(&base, None);
+        assert!(matches!(client.todos(), Err(TodoerError::Unauthorized(_))));
+
+        let client = Client::new(&base, Some(token));
+        client.add("foo").unwrap();
+        let list = client.add("bar").unwrap();
+        assert_eq!(list.size, 2);
+        let list = client.done(1).unwrap();
+        assert_eq!(list.done_count, 1);
+        assert!(matches!(client.done(1), Err(TodoerError::Conflict(_))));
+        assert!(matches!(client.remove(5), Err(TodoerError::NotFound(5))));
+        let list = client.edit(0, "baz").unwrap();
+        assert_eq!(list.todos[0].name, "baz");
+        assert_eq!(client.days().unwrap().len(), 1);
+        assert_eq!(client.day(&list.day).unwrap(), list);
+
+        let list = client.set_status(0, Status::Blocked).unwrap();
+        assert_eq!(list.todos[0].status, "blocked");
+        let list = client.set_status(1, Status::Cancelled).unwrap();
+        assert_eq!(list.done_count, 0);
+        assert!(!list.todos[1].done);
+
+        // Added as a quick add, so the agenda finds it by its due date
+        let list = client.add("call vendor today 3pm #ops").unwrap();
+        assert!(list.todos[2].name.starts_with("call vendor #ops due:"));
+        let agenda = client.agenda(7).unwrap();
+        assert_eq!(agenda.entries.len(), 1);
+        assert_eq!(agenda.entries[0].heading, "Today");
+        assert_eq!(agenda.entries[0].time.as_deref(), Some("15:00"));
+    })
+    .await
+    .unwrap();
+}
+
+fn dav(method: &str, uri: &str) -> test::TestRequest {
+    test::TestRequest::default()
+        .method(Method::from_bytes(method.as_bytes()).unwrap())
+        .uri(uri)
+}
+
+fn vtodo(uid: &str, lines: &str) -> String {
+    format!(
+        "BEGIN:VCALENDAR\r\nVERSION:2.0\r\nBEGIN:VTODO\r\nUID:{}\r\n{}END:VTODO\r\nEND:VCALENDAR\r\n",
+        uid, lines
+    )
+}
+
+#[actix_web::test]
+async fn caldav_collections() {
+    let root = get_root("caldav");
+    let today = write_today(&root, "0/2\n- [ ] foo\n- [ ] bar #home\n");
+    std::fs::write(
+        today.with_file_name("errands.md"),
+        "errands\n0/1\n- [ ] buy paint\n",
+    )
+    .unwrap();
+    let day = today.file_stem().unwrap().to_string_lossy().into_owned();
+    let list = format!("/caldav/{}/", day);
+    let app = test::init_service(
+        App::new()
+            .app_data(store(&root, StateMode::Watch))
+            .configure(routes),
+    )
+    .await;
+    let body = |res: actix_web::dev::ServiceResponse| async move {
+        String::from_utf8(test::read_body(res).await.to_vec()).unwrap()
+    };
+
+    let req = test::TestRequest::get()
+        .uri("/.well-known/caldav")
+        .to_request();
+    let res = test::call_service(&app, req).await;
+    assert_eq!(res.status(), StatusCode::MOVED_PERMANENTLY);
+    assert_eq!(res.headers().get(header::LOCATION).unwrap(), "/caldav/");
+
+    let req = dav("PROPFIND", "/caldav/")
+        .insert_header(("Depth", "1"))
+        .to_request();
+    let res = test::call_service(&app, req).await;
+    assert_eq!(res.status(), StatusCode::MULTI_STATUS);
+    let home = body(res).await;
+    assert!(home.contains("<c:calendar-home-set><d:href>/caldav/</d:href>"));
+    assert!(home.contains(&format!("<d:href>{}</d:href>", list)));
+    assert!(home.contains("<d:href>/caldav/errands/</d:href>"));
+    assert!(home.contains("<c:comp name=\"VTODO\"/>"));
+
+    // Listing the todos leaves the file as it was, todos without an ID go by a made up one
+    let contents = std::fs::read_to_string(&today).unwrap();
+    let req = dav("PROPFIND", &list)
+        .insert_header(("Depth", "1"))
+        .to_request();
+    let listed = body(test::call_service(&app, req).await).await;
+    assert_eq!(listed.matches(".ics</d:href>").count(), 2);
+    assert_eq!(std::fs::read_to_string(&today).unwrap(), contents);
+    let todoer = Todoer::from_config(today.clone(), true).unwrap();
+    let id = stable_id(&todoer, &todoer.data.0[&0]);
+    let item = format!("{}{}.ics", list, id);
+    assert!(listed.contains(&format!("<d:href>{}</d:href>", item)));
+
+    let query = "<c:calendar-query xmlns:d=\"DAV:\" xmlns:c=\"urn:ietf:params:xml:ns:caldav\">\
+                 <d:prop><d:getetag/><c:calendar-data/></d:prop>\
+                 <c:filter><c:comp-filter name=\"VCALENDAR\"><c:comp-filter name=\"VTODO\"/>\
+                 </c:comp-filter></c:filter></c:calendar-query>";
+    let req = dav("REPORT", &list).set_payload(query).to_request();
+    let report = body(test::call_service(&app, req).await).await;
+    assert!(report.contains("SUMMARY:foo"));
+    assert!(report.contains("CATEGORIES:home"));
+    assert_eq!(std::fs::read_to_string(&today).unwrap(), contents);
+    let req = dav("REPORT", "/caldav/errands/")
+        .set_payload(query.replace("VTODO", "VEVENT"))
+        .to_request();
+    assert!(!body(test::call_service(&app, req).await)
+        .await
+        .contains("<d:response>"));
+
+    let req = test::TestRequest::get().uri(&item).to_request();
+    let res = test::call_service(&app, req).await;
+    assert_eq!(res.status(), StatusCode::OK);
+    let etag = res
+        .headers()
+        .get(header::ETAG)
+        .unwrap()
+        .to_str()
+        .unwrap()
+        .to_string();
+    assert!(body(res).await.contains(&format!("UID:{}@todoer", id)));
+
+    // Edited by a task app, which doesn't overwrite changes it hasn't seen
+    let done = vtodo(
+        &format!("{}@todoer", id),
+        "SUMMARY:foo\r\nSTATUS:COMPLETED\r\n",
+    );
+    let req = test::TestRequest::put()
+        .uri(&item)
+        .insert_header((header::IF_MATCH, "\"stale\""))
+        .set_payload(done.clone())
+        .to_request();
+    let res = test::call_service(&app, req).await;
+    assert_eq!(res.status(), StatusCode::PRECONDITION_FAILED);
+    let req = test::TestRequest::put()
+        .uri(&item)
+        .insert_header((header::IF_MATCH, etag))
+        .set_payload(done)
+        .to_request();
+    let res = test::call_service(&app, req).await;
+    assert_eq!(res.status(), StatusCode::NO_CONTENT);
+    let req = test::TestRequest::get().uri("/api/todos").to_request();
+    let todos: DayList = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(todos.done_count, 1);
+    assert!(todos.todos[0].done);
+    assert!(std::fs::read_to_string(&today)
+        .unwrap()
+        .contains(&format!("id:{}", id)));
+
+    // Other lists are changed the same way, leaving today's alone
+    let errands = today.with_file_name("errands.md");
+    let todoer = Todoer::from_config(errands.clone(), true).unwrap();
+    let paint = stable_id(&todoer, &todoer.data.0[&0]);
+    let req = test::TestRequest::put()
+        .uri(&format!("/caldav/errands/{}.ics", paint))
+        .set_payload(vtodo(&paint, "SUMMARY:buy paint\r\nSTATUS:COMPLETED\r\n"))
+        .to_request();
+    let res = test::call_service(&app, req).await;
+    assert_eq!(res.status(), StatusCode::NO_CONTENT);
+    assert!(std::fs::read_to_string(&errands)
+        .unwrap()
+        .starts_with("errands\n1/1\n- [x] buy paint"));
+    let req = test::TestRequest::get().uri("/api/todos").to_request();
+    let todos: DayList = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(todos.size, 2);
+
+    // Added by one, under the UID it chose
+    let added = format!("{}phone-1.ics", list);
+    let req = test::TestRequest::put()
+        .uri(&added)
+        .insert_header((header::IF_NONE_MATCH, "*"))
+        .set_payload(vtodo("phone-1", "SUMMARY:call vendor\r\nPRIORITY:1\r\n"))
+        .to_request();
+    assert_eq!(
+        test::call_service(&app, req).await.status(),
+        StatusCode::CREATED
+    );
+    let req = test::TestRequest::put()
+        .uri(&added)
+        .insert_header((header::IF_NONE_MATCH, "*"))
+        .set_payload(vtodo("phone-1", "SUMMARY:call vendor\r\n"))
+        .to_request();
+    assert_eq!(
+        test::call_service(&app, req).await.status(),
+        StatusCode::PRECONDITION_FAILED
+    );
+    let req = test::TestRequest::put()
+        .uri(&added)
+        .set_payload(vtodo("phone-2", "SUMMARY:call vendor\r\n"))
+        .to_request();
+    assert_eq!(
+        test::call_service(&app, req).await.status(),
+        StatusCode::BAD_REQUEST
+    );
+    assert!(std::fs::read_to_string(&today)
+        .unwrap()
+        .contains("- [ ] call vendor !high created:"));
+
+    let multiget = format!(
+        "<c:calendar-multiget xmlns:d=\"DAV:\" xmlns:c=\"urn:ietf:params:xml:ns:caldav\">\
+         <d:prop><c:calendar-data/></d:prop><d:href>{}</d:href><d:href>{}gone.ics</d:href>\
+         </c:calendar-multiget>",
+        added, list
+    );
+    let req = dav("REPORT", &list).set_payload(multiget).to_request();
+    let report = body(test::call_service(&app, req).await).await;
+    assert!(report.contains("UID:phone-1\r\n"));
+    assert!(report.contains("HTTP/1.1 404 Not Found"));
+
+    let req = test::TestRequest::delete().uri(&added).to_request();
+    assert_eq!(
+        test::call_service(&app, req).await.status(),
+        StatusCode::NO_CONTENT
+    );
+    let req = test::TestRequest::get().uri(&added).to_request();
+    assert_eq!(
+        test::call_service(&app, req).await.status(),
+        StatusCode::NOT_FOUND
+    );
+}
+
+#[actix_web::test]
+async fn caldav_client_against_a_server() {
+    let root = get_root("caldav-client");
+    let mut users = Users::default();
+    let token = users.add("alice").unwrap();
+    users.save(&users_file(&root)).unwrap();
+
+    let data = store(&root, StateMode::Watch);
+    let server = HttpServer::new(move || App::new().app_data(data.clone()).configure(routes))
+        .workers(1)
+        .bind(("127.0.0.1", 0))
+        .unwrap();
+    let base = format!("http://{}", server.addrs()[0]);
+    actix_web::rt::spawn(server.run());
+
+    web::block(move || {
+        // As task apps sign in, with the token as a user name and password
+        let basic = format!("Basic {}", base64::encode(&token));
+        let propfind =
+            |uri: &str| ureq::request("PROPFIND", &format!("{}{}", base, uri)).set("Depth", "1");
+        match propfind("/caldav/").call() {
+            Err(ureq::Error::Status(401, res)) => {
+                assert!(res.header("WWW-Authenticate").unwrap().starts_with("Basic"))
+            }
+            other => panic!("expected a 401, got {:?}", other.map(|res| res.status())),
+        }
+
+        let client = Client::new(&base, Some(token));
+        let day = client.add("foo").unwrap().day;
+        let res = propfind("/caldav/")
+            .set("Authorization", &basic)
+            .call()
+            .unwrap();
+        assert_eq!(res.status(), 207);
+        assert!(res
+            .into_string()
+            .unwrap()
+            .contains(&format!("<d:href>/caldav/{}/</d:href>", day)));
+
+        let res = ureq::put(&format!("{}/caldav/{}/app-1.ics", base, day))
+            .set("Authorization", &basic)
+            .set("Content-Type", "text/calendar")
+            .send_string(&vtodo(
+                "app-1",
+                "SUMMARY:from the app\r\nDUE;VALUE=DATE:20221130\r\n",
+            ))
+            .unwrap();
+        assert_eq!(res.status(), 201);
+        let list = client.todos().unwrap();
+        assert_eq!(list.size, 2);
+        assert_eq!(list.todos[1].name, "from the app due:2022-11-30");
+
+        let res = ureq::get(&format!("{}/caldav/{}/app-1.ics", base, day))
+            .set("Authorization", &basic)
+            .call()
+            .unwrap();
+        assert!(res.header("ETag").is_some());
+        assert!(res.into_string().unwrap().contains("UID:app-1\r\n"));
+    })
+    .await
+    .unwrap();
+}
+
+#[actix_web::test]
+async fn remote_replays_its_queue() {
+    let root = get_root("remote");
+    let queue = root.join("remote-queue.json");
+    let data = store(&root, StateMode::Watch);
+    let server = HttpServer::new(move || App::new().app_data(data.clone()).configure(routes))
+        .workers(1)
+        .bind(("127.0.0.1", 0))
+        .unwrap();
+    let url = format!("http://{}", server.addrs()[0]);
+    actix_web::rt::spawn(server.run());
+
+    web::block(move || {
+        let remote = Remote::new(
+            RemoteSettings {
+                url: url.clone(),
+                token: None,
+            },
+            queue.clone(),
+        );
+        for name in ["write report #work", "buy milk", "call bob #work"] {
+            assert_eq!(
+                remote.run(Operation::Add(String::from(name))).unwrap(),
+                Outcome::Done
+            );
+        }
+
+        let offline = Remote::new(
+            RemoteSettings {
+                url: String::from("http://127.0.0.1:9"),
+                token: None,
+            },
+            queue.clone(),
+        );
+        for operation in [
+            Operation::Complete(Selection::Tag(String::from("work"))),
+            Operation::Remove(Selection::Indices(vec![1])),
+            Operation::Sort(SortKey::Alpha),
+        ] {
+            assert_eq!(offline.run(operation).unwrap(), Outcome::Queued);
+        }
+        assert!(matches!(
+            offline.run(Operation::Remove(Selection::Indices(vec![7]))),
+            Err(TodoerError::NotFound(7))
+        ));
+
+        // Another client changes the list before the queue is sent
+        let client = Client::new(&url, None);
+        client.add("pay rent").unwrap();
+        client.move_todos(&[3], 0).unwrap();
+        client.remove(2).unwrap();
+
+        let dropped = remote.flush().unwrap();
+        assert_eq!(dropped.len(), 1);
+        assert!(matches!(dropped[0].1, TodoerError::Conflict(_)));
+        assert!(remote.queued().unwrap().is_empty());
+        assert!(std::fs::metadata(&queue).is_err());
+
+        remote
+            .run(Operation::Tag(
+                String::from("home"),
+                Selection::Indices(vec![0]),
+            ))
+            .unwrap();
+        let Outcome::Print(printed) = remote.run(Operation::Print()).unwrap() else {
+            panic!("expected the list");
+        };
+        let (open, done) = printed.split_once("\nDone\n").unwrap();
+        assert_eq!(open, "\nTodo\n1). pay rent\n");
+        let done: Vec<&str> = done.lines().collect();
+        assert!(done[0].starts_with("0). call bob #work #home (done "));
+        assert!(done[1].starts_with("2). write report #work (done "));
+    })
+    .await
+    .unwrap();
+}
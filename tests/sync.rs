@@ -0,0 +1,96 @@
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use rust::sync::sync;
+use rust::todoer::Todoer;
+
+fn get_root(name: &str) -> PathBuf {
+    let mut dir = std::env::temp_dir();
+    dir.push(format!("todoer-sync-{}-{}", name, std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+fn git(dir: &Path, args: &[&str]) -> String {
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(dir)
+        .args(args)
+        .output()
+        .unwrap();
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    String::from_utf8_lossy(&output.stdout).into_owned()
+}
+
+// A clone of `remote` with sync on, returning its todo directory
+fn clone(root: &Path, remote: &Path, name: &str) -> PathBuf {
+    git(root, &["clone", "-q", &remote.to_string_lossy(), name]);
+    let dir = root.join(name);
+    git(&dir, &["config", "user.name", name]);
+    git(
+        &dir,
+        &["config", "user.email", &format!("{}@example.com", name)],
+    );
+    git(&dir, &["config", "todoer.sync", "true"]);
+    let todo_dir = dir.join("wiki").join("todo");
+    std::fs::create_dir_all(&todo_dir).unwrap();
+    todo_dir
+}
+
+#[test]
+fn sync_merges_day_files() {
+    let root = get_root("merge");
+    let remote = root.join("remote.git");
+    git(&root, &["init", "-q", "--bare", &remote.to_string_lossy()]);
+    let alice = clone(&root, &remote, "alice");
+    let bob = clone(&root, &remote, "bob");
+    let day = "2022-11-25.md";
+
+    let mut todoer = Todoer::default_todoer(alice.join(day));
+    todoer.set_value(String::from("write CLI"));
+    todoer.save_and_commit().unwrap();
+    todoer.set_value(String::from("buy milk"));
+    todoer.save_and_commit().unwrap();
+    todoer.mark_done(0).unwrap();
+    todoer.save_and_commit().unwrap();
+    let log = git(&alice, &["log", "--format=%s"]);
+    assert_eq!(log, "done: write CLI\nadd: buy milk\nadd: write CLI\n");
+    sync(&alice).unwrap();
+
+    // Bob starts the same day on his own before syncing
+    let mut todoer = Todoer::default_todoer(bob.join(day));
+    todoer.set_value(String::from("buy milk"));
+    todoer.set_value(String::from("call alice"));
+    todoer.save_and_commit().unwrap();
+    sync(&bob).unwrap();
+    let merged = Todoer::from_config(bob.join(day), true).unwrap();
+    assert_eq!(
+        merged.get_value_all(),
+        vec![
+            (&String::from("buy milk"), false),
+            (&String::from("call alice"), false),
+            (&String::from("write CLI"), true),
+        ]
+    );
+    assert!(merged.data.0[&2].completed.is_some());
+
+    sync(&alice).unwrap();
+    assert_eq!(
+        std::fs::read_to_string(alice.join(day)).unwrap(),
+        std::fs::read_to_string(bob.join(day)).unwrap()
+    );
+}
+
+#[test]
+fn sync_needs_a_remote() {
+    let root = get_root("no-remote");
+    git(&root, &["init", "-q"]);
+    git(&root, &["config", "user.name", "alice"]);
+    git(&root, &["config", "user.email", "alice@example.com"]);
+    assert!(sync(&root).is_err());
+}
@@ -0,0 +1,260 @@
+use std::path::{Path, PathBuf};
+
+use chrono::Duration;
+use console::Key;
+use rust::{
+    interactive::{Mode, Session},
+    todoer::Todoer,
+    tui::{run_with, App, Focus, TestBackend},
+};
+
+fn get_today(name: &str) -> PathBuf {
+    let mut dir = std::env::temp_dir();
+    dir.push(format!("todoer-tui-{}-{}", name, std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    dir.join("2022-11-25.md")
+}
+
+fn run(today: &Path, mode: Mode, focus: Focus, backend: TestBackend) -> TestBackend {
+    let mut backend = backend;
+    let mut app = App::new(Session::new(today.to_path_buf(), mode).unwrap(), focus);
+    run_with(&mut backend, &mut app).unwrap();
+    backend
+}
+
+#[test]
+fn add_and_complete() {
+    let today = get_today("add");
+    std::fs::write(&today, "2022-11-25\n0/1\n- [-] call bob\n").unwrap();
+    let backend = TestBackend::new(60, 8)
+        .type_text("abuy milk\n")
+        .type_text("awrite CLI #work\n")
+        .type_text("kx");
+    let backend = run(&today, Mode::Today, Focus::List, backend);
+
+    let todoer = Todoer::from_config(today.clone(), true).unwrap();
+    assert_eq!(
+        todoer.get_value_all(),
+        vec![
+            (&String::from("call bob"), false),
+            (&String::from("buy milk"), true),
+            (&String::from("write CLI #work"), false)
+        ]
+    );
+
+    let screen = backend.last_screen();
+    assert!(screen.contains("1. [x] buy milk"), "{}", screen);
+    assert!(screen.contains("2. [ ] write CLI #work"), "{}", screen);
+    // The cancelled todo is neither open nor done
+    assert!(screen.contains(" 1 open  1 done  3 total"), "{}", screen);
+    assert!(screen.contains(" 2022-11-25 1/3"), "{}", screen);
+}
+
+#[test]
+fn prompt_is_shown_while_typing() {
+    let today = get_today("prompt");
+    let backend = TestBackend::new(60, 8).type_text("abuy");
+    let backend = run(&today, Mode::Today, Focus::List, backend);
+
+    assert!(backend.last_screen().ends_with(" add> buy_"));
+    assert!(std::fs::metadata(&today).is_err());
+}
+
+#[test]
+fn edit_and_delete() {
+    let today = get_today("edit");
+    std::fs::write(&today, "2022-11-25\n0/2\n- [ ] foo\n- [ ] bar\n").unwrap();
+
+    let backend = TestBackend::new(60, 8)
+        .type_text("e")
+        .key(Key::Backspace)
+        .key(Key::Backspace)
+        .type_text("am\n")
+        .type_text("jD");
+    run(&today, Mode::Today, Focus::List, backend);
+
+    assert_eq!(
+        std::fs::read_to_string(&today).unwrap(),
+        "2022-11-25\n0/1\n- [ ] fam\n"
+    );
+}
+
+#[test]
+fn browse_past_days() {
+    let today = get_today("days");
+    std::fs::write(
+        today.with_file_name("2022-11-24.md"),
+        "2022-11-24\n1/2\n- [x] foo\n- [ ] bar\n",
+    )
+    .unwrap();
+
+    let backend = TestBackend::new(60, 8).type_text("j\tu");
+    let backend = run(&today, Mode::Today, Focus::Days, backend);
+
+    let screen = backend.last_screen();
+    assert!(screen.starts_with(" todoer  2022-11-24"), "{}", screen);
+    assert!(screen.contains(" 2022-11-24 0/2"), "{}", screen);
+    assert_eq!(
+        std::fs::read_to_string(today.with_file_name("2022-11-24.md")).unwrap(),
+        "2022-11-24\n0/2\n- [ ] foo\n- [ ] bar\n"
+    );
+}
+
+#[test]
+fn errors_go_to_the_status_bar() {
+    let today = get_today("errors");
+    let backend = TestBackend::new(100, 6).type_text("ssize\n");
+    let backend = run(&today, Mode::Today, Focus::List, backend);
+
+    assert!(backend
+        .last_screen()
+        .contains("unknown sort key size, expected priority, due, tag or alpha"));
+}
+
+#[test]
+fn quit_stops_reading_keys() {
+    let today = get_today("quit");
+    let backend = TestBackend::new(60, 8).type_text("qabuy milk\n");
+    let backend = run(&today, Mode::Today, Focus::List, backend);
+
+    assert_eq!(backend.screens.len(), 1);
+    assert_eq!(backend.keys.len(), 10);
+}
+
+#[test]
+fn timer_on_the_cursor() {
+    // The timer is kept next to the todo directory, so give it one of its own
+    let today = get_today("timer");
+    let today = today.parent().unwrap().join("todo").join("2022-11-25.md");
+    std::fs::create_dir_all(today.parent().unwrap()).unwrap();
+    std::fs::write(&today, "2022-11-25\n0/2\n- [ ] foo\n- [ ] bar\n").unwrap();
+
+    let backend = TestBackend::new(60, 8).type_text("jT");
+    let backend = run(&today, Mode::Today, Focus::List, backend);
+    assert!(
+        backend.last_screen().contains("1. [ ] bar (0m running)"),
+        "{}",
+        backend.last_screen()
+    );
+
+    let backend = TestBackend::new(60, 8).type_text("jT");
+    run(&today, Mode::Today, Focus::List, backend);
+    let todoer = Todoer::from_config(today.clone(), true).unwrap();
+    assert_eq!(todoer.data.0[&1].tracked.len(), 1);
+    assert!(std::fs::read_to_string(&today)
+        .unwrap()
+        .contains("- [ ] bar timer:"));
+}
+
+#[test]
+fn focus_counts_down_and_logs_pomodoros() {
+    // Settings are read next to the todo directory, so give it one of its own
+    let today = get_today("focus");
+    let today = today.parent().unwrap().join("todo").join("2022-11-25.md");
+    std::fs::create_dir_all(today.parent().unwrap()).unwrap();
+    std::fs::write(
+        today.parent().unwrap().with_file_name("pomodoro.json"),
+        r#"{"work": 20, "short_break": 5}"#,
+    )
+    .unwrap();
+    std::fs::write(&today, "2022-11-25\n0/2\n- [ ] foo\n- [ ] bar\n").unwrap();
+
+    let backend = TestBackend::new(60, 8)
+        .type_text("jf")
+        .wait(Duration::seconds(90));
+    let backend = run(&today, Mode::Today, Focus::List, backend);
+    let screen = backend.last_screen();
+    assert!(screen.contains(" focus  bar"), "{}", screen);
+    assert!(screen.contains(" work 18:30"), "{}", screen);
+    assert!(screen.ends_with(" x done  q stop focusing"), "{}", screen);
+
+    let backend = TestBackend::new(60, 8)
+        .type_text("jf")
+        .wait(Duration::minutes(20))
+        .wait(Duration::minutes(1));
+    let backend = run(&today, Mode::Today, Focus::List, backend);
+    let screen = backend.last_screen();
+    assert!(screen.contains(" break 04:00"), "{}", screen);
+    assert!(screen.contains(" 1 today, 1 this session"), "{}", screen);
+    assert!(
+        screen.contains("pomodoro 1 done, take a break"),
+        "{}",
+        screen
+    );
+
+    // Done ends focus mode, leaving the count in the day file
+    let backend = TestBackend::new(60, 8).type_text("jfx");
+    let backend = run(&today, Mode::Today, Focus::List, backend);
+    assert!(
+        backend.last_screen().contains("1. [x] bar [1 pomodoro]"),
+        "{}",
+        backend.last_screen()
+    );
+    assert!(std::fs::read_to_string(&today)
+        .unwrap()
+        .ends_with(" pomodoros:1\n"));
+    let todoer = Todoer::from_config(today, true).unwrap();
+    assert_eq!(todoer.data.0[&1].pomodoros, 1);
+    assert!(todoer.data.0[&1].is_done());
+}
+
+#[test]
+fn quick_add_and_agenda() {
+    let today = get_today("agenda");
+    std::fs::write(
+        today.with_file_name("errands.md"),
+        "errands\n0/1\n- [ ] buy paint due:2022-11-20\n",
+    )
+    .unwrap();
+    let backend = TestBackend::new(70, 10)
+        .type_text("acall vendor tomorrow 3pm !high @phone\n")
+        .type_text("A");
+    let backend = run(&today, Mode::Today, Focus::List, backend);
+
+    let todoer = Todoer::from_config(today, true).unwrap();
+    assert_eq!(
+        todoer.data.0[&0].name,
+        "call vendor !high @phone due:2022-11-26 at:15:00"
+    );
+    let screen = backend.last_screen();
+    assert!(screen.contains(" Overdue"), "{}", screen);
+    assert!(
+        screen.contains("   buy paint due:2022-11-20 (errands)"),
+        "{}",
+        screen
+    );
+    assert!(screen.contains(" Tomorrow"), "{}", screen);
+    assert!(screen.ends_with(" q back to the list"), "{}", screen);
+}
+
+#[test]
+fn calendar_opens_a_day() {
+    let today = get_today("calendar");
+    std::fs::write(
+        today.with_file_name("2022-11-21.md"),
+        "2022-11-21\n1/1\n- [x] ship release\n",
+    )
+    .unwrap();
+    let backend = TestBackend::new(60, 14).type_text("Ch");
+    let backend = run(&today, Mode::Today, Focus::List, backend);
+    let screen = backend.last_screen();
+    assert!(screen.contains(" November 2022"), "{}", screen);
+    assert!(screen.contains(" 21 22 23 24 25 26 27"), "{}", screen);
+    assert!(screen.contains(" 1/1 done over 1 day"), "{}", screen);
+
+    let backend = TestBackend::new(60, 14).type_text("Ch\n");
+    let backend = run(&today, Mode::Today, Focus::List, backend);
+    let screen = backend.last_screen();
+    assert!(
+        screen.contains("there is no list for 2022-11-24"),
+        "{}",
+        screen
+    );
+
+    let backend = TestBackend::new(60, 14).type_text("Chhhh\n");
+    let backend = run(&today, Mode::Today, Focus::List, backend);
+    let screen = backend.last_screen();
+    assert!(screen.contains(" todoer  2022-11-21"), "{}", screen);
+    assert!(screen.contains("0. [x] ship release"), "{}", screen);
+}